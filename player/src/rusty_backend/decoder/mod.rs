@@ -7,7 +7,8 @@ use std::time::Duration;
 
 use super::Source;
 
-use self::{read_seek_source::ReadSeekSource, symphonia::SymphoniaDecoder};
+use self::read_seek_source::ReadSeekSource;
+pub use self::symphonia::SymphoniaDecoder;
 use ::symphonia::core::io::{MediaSource, MediaSourceStream};
 mod read_seek_source;
 mod symphonia;
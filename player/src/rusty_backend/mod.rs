@@ -16,25 +16,69 @@ pub use cpal::{
     self, traits::DeviceTrait, Device, Devices, DevicesError, InputDevices, OutputDevices,
     SupportedStreamConfig,
 };
-pub use decoder::Decoder;
+pub use decoder::{Decoder, SymphoniaDecoder};
 use flume::Sender;
 pub use sink::Sink;
 pub use source::Source;
-pub use stream::{OutputStream, OutputStreamHandle, PlayError, StreamError};
+pub use stream::{OutputConfig, OutputStream, OutputStreamHandle, PlayError, StreamError};
 
 use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
-use std::{fs::File, io::BufReader};
+use std::{
+    fs::File,
+    io::{BufReader, Cursor},
+};
 
 use self::stream::CpalDeviceExt;
 
 static VOLUME_STEP: u16 = 5;
 
+/// Builds the short tone played when no sound file is given to `Player::chime`.
+fn beep() -> buffer::SamplesBuffer<f32> {
+    const RATE: u32 = 44100;
+    const LENGTH: usize = RATE as usize / 4;
+    let samples = (0..LENGTH)
+        .map(|i| {
+            let time = i as f32 / RATE as f32;
+            // Fades out so the tone doesn't end with a click
+            let fade = 1.0 - i as f32 / LENGTH as f32;
+            (2.0 * std::f32::consts::PI * 880.0 * time).sin() * 0.3 * fade
+        })
+        .collect::<Vec<_>>();
+    buffer::SamplesBuffer::new(1, RATE, samples)
+}
+
+// Number of seconds skipped by `seek_fw` and `seek_bw`
+const SEEK_STEP: f64 = 5.0;
+
+/// Returns the position `SEEK_STEP` seconds after `elapsed`, `None` if it is past the end of a
+/// song lasting `duration` seconds
+pub fn forward_position(elapsed: Duration, duration: f64) -> Option<Duration> {
+    let position = elapsed.as_secs_f64() + SEEK_STEP;
+    if position > duration {
+        return None;
+    }
+    Some(Duration::from_secs_f64(position))
+}
+
+/// Returns the position `SEEK_STEP` seconds before `elapsed` stopping at the start of the song,
+/// `None` if the song is already at its start
+pub fn backward_position(elapsed: Duration) -> Option<Duration> {
+    let elapsed = elapsed.as_secs_f64();
+    if elapsed < 1.0 {
+        return None;
+    }
+    Some(Duration::from_secs_f64((elapsed - SEEK_STEP).max(0.0)))
+}
+
 pub struct Player {
     sink: Sink,
     data: PlayerData,
     error_sender: Arc<Sender<StreamError>>,
+    // The duration and the track gain of the song queued after the current one with
+    // `queue_decoder`
+    queued: Option<(Option<Duration>, f32)>,
 }
 
 pub struct Guard {
@@ -46,15 +90,20 @@ pub struct Guard {
 pub struct PlayerData {
     total_duration: Option<Duration>,
     volume: u16,
-    safe_guard: bool,
+    track_gain: f32,
+    equalizer: Vec<f32>,
+    silence_threshold: Option<f32>,
+    fade_in: Duration,
+    output: OutputConfig,
 }
 impl Player {
     /// Returns a new stream & handle using the given output device.
     fn try_from_device(
         device: &cpal::Device,
         error_sender: Arc<Sender<StreamError>>,
+        output: OutputConfig,
     ) -> Result<(OutputStream, OutputStreamHandle), StreamError> {
-        let (mixer, stream) = device.try_new_output_stream(error_sender)?;
+        let (mixer, stream) = device.try_new_output_stream(error_sender, output)?;
         stream.play()?;
         let out = OutputStream {
             mixer,
@@ -71,12 +120,13 @@ impl Player {
     /// On failure will fallback to trying any non-default output devices.
     fn try_default(
         error_sender: Arc<Sender<StreamError>>,
+        output: OutputConfig,
     ) -> Result<(OutputStream, OutputStreamHandle), StreamError> {
         let default_device = cpal::default_host()
             .default_output_device()
             .ok_or(StreamError::NoDevice)?;
 
-        let default_stream = Self::try_from_device(&default_device, error_sender.clone());
+        let default_stream = Self::try_from_device(&default_device, error_sender.clone(), output);
 
         default_stream.or_else(move |original_err| {
             // default device didn't work, try other ones
@@ -86,13 +136,16 @@ impl Player {
             };
 
             devices
-                .find_map(|d| Self::try_from_device(&d, error_sender.clone()).ok())
+                .find_map(|d| Self::try_from_device(&d, error_sender.clone(), output).ok())
                 .ok_or(original_err)
         })
     }
-    pub fn new(error_sender: Arc<Sender<StreamError>>) -> Result<(Self, Guard), PlayError> {
+    pub fn new(
+        error_sender: Arc<Sender<StreamError>>,
+        output: OutputConfig,
+    ) -> Result<(Self, Guard), PlayError> {
         let (stream, handle) =
-            Self::try_default(error_sender.clone()).map_err(PlayError::StreamError)?;
+            Self::try_default(error_sender.clone(), output).map_err(PlayError::StreamError)?;
         let sink = Sink::try_new(&handle)?;
         let volume = 50;
         sink.set_volume(f32::from(volume) / 100.0);
@@ -101,10 +154,15 @@ impl Player {
             Self {
                 sink: sink,
                 error_sender: error_sender,
+                queued: None,
                 data: PlayerData {
                     total_duration: None,
                     volume,
-                    safe_guard: false,
+                    track_gain: 1.0,
+                    equalizer: Vec::new(),
+                    silence_threshold: None,
+                    fade_in: Duration::ZERO,
+                    output,
                 },
             },
             Guard {
@@ -114,16 +172,21 @@ impl Player {
         ))
     }
     pub fn update(&self) -> Result<(Self, Guard), PlayError> {
-        let (stream, handle) =
-            Self::try_default(self.error_sender.clone()).map_err(PlayError::StreamError)?;
+        let (stream, handle) = Self::try_default(self.error_sender.clone(), self.data.output)
+            .map_err(PlayError::StreamError)?;
         let sink = Sink::try_new(&handle)?;
         let volume = self.data.volume;
         sink.set_volume(f32::from(volume) / 100.0);
+        sink.set_track_gain(self.data.track_gain);
+        sink.set_equalizer(self.data.equalizer.clone());
+        sink.set_silence_threshold(self.data.silence_threshold);
+        sink.set_fade_in(self.data.fade_in);
         Ok((
             Self {
                 sink,
                 error_sender: self.error_sender.clone(),
                 data: self.data.clone(),
+                queued: None,
             },
             Guard {
                 _stream: stream,
@@ -146,23 +209,102 @@ impl Player {
         self.data.volume = self.data.volume.min(100);
         self.sink.set_volume(f32::from(self.data.volume) / 100.0);
     }
+    /// Changes the gain in dB of the current song, on top of the volume. Playing another song
+    /// keeps it, so it must be set before each song
+    pub fn set_track_gain(&mut self, gain_db: f32) {
+        self.data.track_gain = 10f32.powf(gain_db / 20.0);
+        self.sink.set_track_gain(self.data.track_gain);
+    }
+    /// Changes the gain of each band of `EQUALIZER_BANDS` in dB, kept for the next songs
+    pub fn set_equalizer(&mut self, gains: &[f32]) {
+        self.data.equalizer = gains.to_vec();
+        self.sink.set_equalizer(self.data.equalizer.clone());
+    }
+    /// Trims the silence at the end of the next songs, below `threshold_db` dBFS. `None` keeps
+    /// the songs as they are
+    pub fn set_silence_trim(&mut self, threshold_db: Option<f32>) {
+        self.data.silence_threshold = threshold_db.map(|x| 10f32.powf(x / 20.0));
+        self.sink.set_silence_threshold(self.data.silence_threshold);
+    }
+    /// Fades in the next songs over this time, `Duration::ZERO` starts them at full volume
+    pub fn set_fade_in(&mut self, duration: Duration) {
+        self.data.fade_in = duration;
+        self.sink.set_fade_in(duration);
+    }
     pub fn is_finished(&self) -> bool {
         self.sink.is_empty() || self.sink.sleep_until_end()
     }
     pub fn play(&mut self, path: &Path, guard: &Guard) -> Result<(), PlayError> {
-        self.stop(guard);
+        let decoder = Self::open(path)?;
+        self.play_decoder(decoder, guard);
+        Ok(())
+    }
+    /// Opens a file and prepares its decoder without playing it
+    pub fn open(path: &Path) -> Result<SymphoniaDecoder, PlayError> {
         let file = File::open(path).map_err(PlayError::Io)?;
-        //println!("{:?}", path);
-        let decoder =
-            Decoder::new_decoder(BufReader::new(file)).map_err(PlayError::DecoderError)?;
+        Decoder::new_decoder(BufReader::new(file)).map_err(PlayError::DecoderError)
+    }
+    /// Prepares the decoder of a song kept in memory
+    pub fn open_buffer(data: Arc<[u8]>) -> Result<SymphoniaDecoder, PlayError> {
+        Decoder::new_decoder(Cursor::new(data)).map_err(PlayError::DecoderError)
+    }
+    /// Plays a decoder created with `Player::open`
+    pub fn play_decoder(&mut self, decoder: SymphoniaDecoder, guard: &Guard) {
+        self.stop(guard);
         self.data.total_duration = decoder.total_duration();
         self.sink.append(decoder);
-        Ok(())
+    }
+    /// Plays a decoder right after the current song without any gap, `advance` tells when it
+    /// started. Its gain in dB is applied from its first sample, as with `set_track_gain`
+    pub fn queue_decoder(&mut self, decoder: SymphoniaDecoder, gain_db: f32) {
+        let track_gain = 10f32.powf(gain_db / 20.0);
+        self.queued = Some((decoder.total_duration(), track_gain));
+        self.sink.append_with_gain(decoder, track_gain);
+    }
+    /// Returns true if a song was queued with `queue_decoder` and didn't start yet
+    pub fn has_queued(&self) -> bool {
+        self.queued.is_some()
+    }
+    /// Removes the song queued with `queue_decoder`, returns false if it already started
+    pub fn unqueue(&mut self) -> bool {
+        if self.queued.is_none() || !self.sink.clear_queued() {
+            return false;
+        }
+        self.queued = None;
+        true
+    }
+    /// Returns true once the song queued with `queue_decoder` started, it becomes the current song
+    pub fn advance(&mut self) -> bool {
+        if self.queued.is_none() || self.sink.len() > 1 {
+            return false;
+        }
+        if let Some((total_duration, track_gain)) = self.queued.take() {
+            self.data.total_duration = total_duration;
+            self.data.track_gain = track_gain;
+        }
+        self.sink.advance();
+        true
     }
     pub fn stop(&mut self, guard: &Guard) -> Result<(), PlayError> {
+        self.queued = None;
         self.sink.destroy();
         self.sink = Sink::try_new(&guard.handle)?;
         self.sink.set_volume(f32::from(self.data.volume) / 100.0);
+        self.sink.set_track_gain(self.data.track_gain);
+        self.sink.set_equalizer(self.data.equalizer.clone());
+        self.sink.set_silence_threshold(self.data.silence_threshold);
+        self.sink.set_fade_in(self.data.fade_in);
+        Ok(())
+    }
+    /// Plays a sound over the song with its own sink, the built-in beep if no file is given
+    pub fn chime(&self, path: Option<&Path>, guard: &Guard) -> Result<(), PlayError> {
+        let mut sink = Sink::try_new(&guard.handle)?;
+        sink.set_volume(f32::from(self.data.volume) / 100.0);
+        match path {
+            Some(path) => sink.append(Self::open(path)?),
+            None => sink.append(beep()),
+        }
+        sink.detach();
         Ok(())
     }
     pub fn elapsed(&self) -> Duration {
@@ -176,23 +318,38 @@ impl Player {
     pub fn toggle_playback(&self) {
         self.sink.toggle_playback();
     }
-    pub fn seek_fw(&mut self) {
-        let new_pos = self.elapsed().as_secs_f64() + 5.0;
+    /**
+     * Returns false without seeking if the new position is past the end of the song
+     */
+    pub fn seek_fw(&mut self) -> bool {
         if let Some(duration) = self.duration() {
-            if new_pos > duration {
-                self.data.safe_guard = true;
-            } else {
-                self.seek_to(Duration::from_secs_f64(new_pos));
+            match forward_position(self.elapsed(), duration) {
+                Some(position) => self.seek_to(position),
+                None => return false,
             }
         }
+        true
     }
-    pub fn seek_bw(&self) {
-        let mut new_pos = self.elapsed().as_secs_f64() - 5.0;
-        if new_pos < 0.0 {
-            new_pos = 0.0;
+    /**
+     * Seeks to the end of the song, the song is then finished after the last buffered samples
+     */
+    pub fn seek_to_end(&self) {
+        if let Some(duration) = self.duration() {
+            self.seek_to(Duration::from_secs_f64(duration.max(0.0)));
+        }
+    }
+    /**
+     * Seeks 5 seconds back, stopping at the start of the song. Returns false without seeking if
+     * the song was already at its start
+     */
+    pub fn seek_bw(&self) -> bool {
+        match backward_position(self.elapsed()) {
+            Some(position) => {
+                self.seek_to(position);
+                true
+            }
+            None => false,
         }
-
-        self.seek_to(Duration::from_secs_f64(new_pos));
     }
     pub fn seek_to(&self, time: Duration) {
         self.sink.seek(time);
@@ -252,10 +409,10 @@ impl Player {
 
     pub fn seek(&mut self, secs: i64) {
         if secs.is_positive() {
-            self.seek_fw();
+            let _ = self.seek_fw();
             return;
         }
-        self.seek_bw();
+        let _ = self.seek_bw();
     }
 
     #[allow(
@@ -84,6 +84,16 @@ where
         rx
     }
 
+    /// Removes the sounds that didn't start yet, returns their number.
+    ///
+    /// Their `Receiver` are never signalled.
+    pub fn clear(&self) -> usize {
+        let mut next_sounds = self.next_sounds.lock().unwrap();
+        let removed = next_sounds.len();
+        next_sounds.clear();
+        removed
+    }
+
     /// Sets whether the queue stays alive if there's no more sound to play.
     ///
     /// See also the constructor.
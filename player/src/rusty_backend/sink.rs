@@ -17,6 +17,8 @@ use super::{OutputStreamHandle, PlayError};
 pub struct Sink {
     queue_tx: Arc<queue::SourcesQueueInput<f32>>,
     sleep_until_end: VecDeque<Receiver<()>>,
+    // Factor applied on top of the volume for each sound of the queue, the first one is playing
+    track_gains: VecDeque<Arc<Mutex<f32>>>,
 
     controls: Arc<Controls>,
     sound_count: Arc<AtomicUsize>,
@@ -29,6 +31,14 @@ pub struct Sink {
 struct Controls {
     pause: AtomicBool,
     volume: Mutex<f32>,
+    // Factor applied on top of the volume of the sounds appended next
+    track_gain: Mutex<f32>,
+    // Gain of each band of the equalizer in dB
+    equalizer: Mutex<Vec<f32>>,
+    // Amplitude under which the end of a sound is trimmed, read when a sound is appended
+    silence_threshold: Mutex<Option<f32>>,
+    // Time the volume takes to rise at the start of a sound, read when a sound is appended
+    fade_in: Mutex<Duration>,
     seek: Mutex<Option<Duration>>,
     stopped: AtomicBool,
 }
@@ -51,9 +61,14 @@ impl Sink {
         let sink = Self {
             queue_tx,
             sleep_until_end: VecDeque::new(),
+            track_gains: VecDeque::new(),
             controls: Arc::new(Controls {
                 pause: AtomicBool::new(false),
                 volume: Mutex::new(1.0),
+                track_gain: Mutex::new(1.0),
+                equalizer: Mutex::new(Vec::new()),
+                silence_threshold: Mutex::new(None),
+                fade_in: Mutex::new(Duration::ZERO),
                 stopped: AtomicBool::new(false),
                 seek: Mutex::new(None),
             }),
@@ -71,11 +86,30 @@ impl Sink {
         S: Source + Send + 'static,
         S::Item: Sample + Send,
         // S::Item: Send,
+    {
+        let track_gain = *self.controls.track_gain.lock().unwrap();
+        self.append_with_gain(source, track_gain);
+    }
+
+    /// Appends a sound played with its own track gain, so it starts at the right loudness even
+    /// if the sound before it is still playing.
+    pub fn append_with_gain<S>(&mut self, source: S, track_gain: f32)
+    where
+        S: Source + Send + 'static,
+        S::Item: Sample + Send,
     {
         let controls = self.controls.clone();
+        let track_gain = Arc::new(Mutex::new(track_gain));
+        self.track_gains.push_back(track_gain.clone());
 
         let elapsed = self.elapsed.clone();
+        let silence_threshold = *controls.silence_threshold.lock().unwrap();
+        let fade_in = *controls.fade_in.lock().unwrap();
         let source = source
+            .convert_samples::<f32>()
+            .fade_in(fade_in)
+            .trim_silence(silence_threshold)
+            .equalizer()
             .pausable(false)
             .amplify(1.0)
             .stoppable()
@@ -92,10 +126,15 @@ impl Sink {
                         }
                     }
                     *elapsed.write().unwrap() = src.elapsed();
-                    src.inner_mut().set_factor(*controls.volume.lock().unwrap());
+                    src.inner_mut()
+                        .set_factor(*controls.volume.lock().unwrap() * *track_gain.lock().unwrap());
                     src.inner_mut()
                         .inner_mut()
                         .set_paused(controls.pause.load(Ordering::SeqCst));
+                    src.inner_mut()
+                        .inner_mut()
+                        .inner_mut()
+                        .set_gains(&controls.equalizer.lock().unwrap());
                 }
             })
             .convert_samples();
@@ -123,6 +162,37 @@ impl Sink {
         *self.controls.volume.lock().unwrap() = value;
     }
 
+    /// Changes the factor applied on top of the volume of the sound playing and of the sounds
+    /// appended next, `1.0` leaves the sound as it is.
+    #[inline]
+    pub fn set_track_gain(&self, factor: f32) {
+        *self.controls.track_gain.lock().unwrap() = factor;
+        if let Some(track_gain) = self.track_gains.front() {
+            *track_gain.lock().unwrap() = factor;
+        }
+    }
+
+    /// Changes the gain of each band of `EQUALIZER_BANDS` in dB, an empty list disables the
+    /// equalizer.
+    #[inline]
+    pub fn set_equalizer(&self, gains: Vec<f32>) {
+        *self.controls.equalizer.lock().unwrap() = gains;
+    }
+
+    /// Trims the final silence of the sounds appended next, the samples quieter than this
+    /// amplitude. `None` disables the trimming.
+    #[inline]
+    pub fn set_silence_threshold(&self, threshold: Option<f32>) {
+        *self.controls.silence_threshold.lock().unwrap() = threshold;
+    }
+
+    /// Raises the volume from silence over this time at the start of the sounds appended next,
+    /// `Duration::ZERO` disables the fade.
+    #[inline]
+    pub fn set_fade_in(&self, duration: Duration) {
+        *self.controls.fade_in.lock().unwrap() = duration;
+    }
+
     /// Resumes playback of a paused sink.
     ///
     /// No effect if not paused.
@@ -177,7 +247,30 @@ impl Sink {
         }
     }
 
+    /// Removes the sounds waiting after the current one, returns false if there was none.
+    pub fn clear_queued(&mut self) -> bool {
+        let removed = self.queue_tx.clear();
+        if removed == 0 {
+            return false;
+        }
+        self.sound_count.fetch_sub(removed, Ordering::Relaxed);
+        for _ in 0..removed {
+            self.sleep_until_end.pop_back();
+            self.track_gains.pop_back();
+        }
+        true
+    }
+
+    /// Forgets the sound that ended once the next one started, its elapsed time is reset until
+    /// the next one updates it.
+    pub fn advance(&mut self) {
+        self.sleep_until_end.pop_front();
+        self.track_gains.pop_front();
+        *self.elapsed.write().unwrap() = Duration::ZERO;
+    }
+
     pub fn get_current_receiver(&mut self) -> Option<Receiver<()>> {
+        self.track_gains.pop_front();
         self.sleep_until_end.pop_front()
     }
     /// Returns true if this sink has no more sounds to play.
@@ -0,0 +1,168 @@
+use std::f32::consts::PI;
+use std::time::Duration;
+
+use super::Source;
+
+/// Center frequencies of the bands of the equalizer, in Hz.
+pub const EQUALIZER_BANDS: [f32; 5] = [60.0, 250.0, 1000.0, 4000.0, 12000.0];
+
+// Width of each band, the same for every band so they overlap evenly
+const BAND_Q: f32 = 1.0;
+
+/// Internal function that builds a `Equalizer` object.
+pub fn equalizer<I>(input: I) -> Equalizer<I>
+where
+    I: Source<Item = f32>,
+{
+    let channels = input.channels() as usize;
+    Equalizer {
+        input,
+        gains: Vec::new(),
+        filters: Vec::new(),
+        channel: 0,
+        channels,
+    }
+}
+
+/// Peaking filter of one band, see the Audio EQ Cookbook by Robert Bristow-Johnson.
+#[derive(Clone, Debug)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    // The last two inputs and outputs of each channel
+    state: Vec<[f32; 4]>,
+}
+
+impl Biquad {
+    fn peaking(frequency: f32, gain_db: f32, sample_rate: u32, channels: usize) -> Self {
+        let a = 10f32.powf(gain_db / 40.0);
+        let w0 = 2.0 * PI * frequency / sample_rate as f32;
+        let alpha = w0.sin() / (2.0 * BAND_Q);
+        let a0 = 1.0 + alpha / a;
+        Self {
+            b0: (1.0 + alpha * a) / a0,
+            b1: -2.0 * w0.cos() / a0,
+            b2: (1.0 - alpha * a) / a0,
+            a1: -2.0 * w0.cos() / a0,
+            a2: (1.0 - alpha / a) / a0,
+            state: vec![[0.0; 4]; channels.max(1)],
+        }
+    }
+
+    #[inline]
+    fn process(&mut self, channel: usize, x: f32) -> f32 {
+        let [x1, x2, y1, y2] = self.state[channel];
+        let y = self.b0 * x + self.b1 * x1 + self.b2 * x2 - self.a1 * y1 - self.a2 * y2;
+        self.state[channel] = [x, x1, y, y1];
+        y
+    }
+}
+
+/// Filter that boosts or cuts the bands of `EQUALIZER_BANDS`.
+#[derive(Clone, Debug)]
+pub struct Equalizer<I> {
+    input: I,
+    // Gain of each band in dB, empty or all zeros lets the samples through
+    gains: Vec<f32>,
+    filters: Vec<Biquad>,
+    // Channel of the next sample, the samples of the channels are interleaved
+    channel: usize,
+    channels: usize,
+}
+
+#[allow(clippy::missing_const_for_fn, unused)]
+impl<I> Equalizer<I>
+where
+    I: Source<Item = f32>,
+{
+    /// Changes the gain of each band in dB, the filters are only rebuilt if they changed.
+    pub fn set_gains(&mut self, gains: &[f32]) {
+        if self.gains == gains {
+            return;
+        }
+        self.gains = gains.to_vec();
+        let sample_rate = self.input.sample_rate();
+        self.filters = EQUALIZER_BANDS
+            .iter()
+            .zip(gains)
+            // The bands above the Nyquist frequency can't be filtered
+            .filter(|(frequency, gain)| **gain != 0.0 && **frequency < sample_rate as f32 / 2.0)
+            .map(|(frequency, gain)| Biquad::peaking(*frequency, *gain, sample_rate, self.channels))
+            .collect();
+    }
+
+    /// Returns a mutable reference to the inner source.
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut I {
+        &mut self.input
+    }
+}
+
+impl<I> Iterator for Equalizer<I>
+where
+    I: Source<Item = f32>,
+{
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.input.next()?;
+        if self.filters.is_empty() {
+            return Some(sample);
+        }
+        let channel = self.channel;
+        self.channel = (self.channel + 1) % self.channels.max(1);
+        Some(
+            self.filters
+                .iter_mut()
+                .fold(sample, |x, filter| filter.process(channel, x)),
+        )
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> Source for Equalizer<I>
+where
+    I: Source<Item = f32>,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    #[inline]
+    fn elapsed(&mut self) -> Duration {
+        self.input.elapsed()
+    }
+
+    fn seek(&mut self, time: Duration) -> Result<Duration, ()> {
+        // The state of the filters belongs to the samples before the seek
+        for filter in &mut self.filters {
+            filter.state.iter_mut().for_each(|x| *x = [0.0; 4]);
+        }
+        self.channel = 0;
+        self.input.seek(time)
+    }
+}
@@ -7,24 +7,28 @@ use super::Sample;
 pub use self::amplify::Amplify;
 pub use self::done::Done;
 pub use self::empty::Empty;
+pub use self::equalizer::{Equalizer, EQUALIZER_BANDS};
 pub use self::fadein::FadeIn;
 pub use self::pausable::Pausable;
 pub use self::periodic::PeriodicAccess;
 pub use self::samples_converter::SamplesConverter;
 pub use self::stoppable::Stoppable;
 pub use self::take::TakeDuration;
+pub use self::trim_silence::TrimSilence;
 pub use self::uniform::UniformSourceIterator;
 pub use self::zero::Zero;
 
 mod amplify;
 mod done;
 mod empty;
+mod equalizer;
 mod fadein;
 mod pausable;
 mod periodic;
 mod samples_converter;
 mod stoppable;
 mod take;
+mod trim_silence;
 mod uniform;
 mod zero;
 
@@ -200,4 +204,23 @@ where
     {
         stoppable::stoppable(self)
     }
+
+    /// Boosts or cuts the bands of `EQUALIZER_BANDS`, flat until the gains are set.
+    #[inline]
+    fn equalizer(self) -> Equalizer<Self>
+    where
+        Self: Sized + Source<Item = f32>,
+    {
+        equalizer::equalizer(self)
+    }
+
+    /// Ends the sound at the start of its final silence, the samples quieter than `threshold`.
+    /// `None` keeps the sound as it is.
+    #[inline]
+    fn trim_silence(self, threshold: Option<f32>) -> TrimSilence<Self>
+    where
+        Self: Sized + Source<Item = f32>,
+    {
+        trim_silence::trim_silence(self, threshold)
+    }
 }
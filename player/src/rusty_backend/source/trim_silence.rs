@@ -0,0 +1,132 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use super::Source;
+
+/// Only the silence in this last part of a song is trimmed.
+const TAIL: Duration = Duration::from_secs(30);
+
+/// Internal function that builds a `TrimSilence` object.
+pub fn trim_silence<I>(input: I, threshold: Option<f32>) -> TrimSilence<I>
+where
+    I: Source<Item = f32>,
+{
+    // The tail can't be found without the duration
+    let tail_start = match (threshold, input.total_duration()) {
+        (Some(_), Some(duration)) => Some(samples_at(&input, duration.saturating_sub(TAIL))),
+        _ => None,
+    };
+    TrimSilence {
+        input,
+        threshold: threshold.unwrap_or(0.0),
+        tail_start,
+        position: 0,
+        buffer: VecDeque::new(),
+    }
+}
+
+/// Number of samples of all the channels played at the given time.
+fn samples_at<I: Source<Item = f32>>(input: &I, time: Duration) -> u64 {
+    (time.as_secs_f64() * f64::from(input.sample_rate()) * f64::from(input.channels())) as u64
+}
+
+/// Filter that ends the source at the start of its final silence.
+///
+/// In the tail of the source, the quiet samples are held back until a louder sample comes. If
+/// the source ends first, they are dropped. The samples are only read ahead during the silence,
+/// so a slow read can't be heard.
+#[derive(Clone, Debug)]
+pub struct TrimSilence<I> {
+    input: I,
+    // Amplitude under which a sample is silent
+    threshold: f32,
+    // The sample where the tail starts, `None` disables the trimming
+    tail_start: Option<u64>,
+    // Number of samples read from the input
+    position: u64,
+    // The quiet samples read ahead, played once a louder sample is found
+    buffer: VecDeque<f32>,
+}
+
+impl<I> Iterator for TrimSilence<I>
+where
+    I: Source<Item = f32>,
+{
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        if let Some(sample) = self.buffer.pop_front() {
+            return Some(sample);
+        }
+        let sample = self.input.next()?;
+        self.position += 1;
+        if !matches!(self.tail_start, Some(start) if self.position >= start)
+            || sample.abs() >= self.threshold
+        {
+            return Some(sample);
+        }
+        self.buffer.push_back(sample);
+        loop {
+            match self.input.next() {
+                Some(sample) => {
+                    self.position += 1;
+                    self.buffer.push_back(sample);
+                    if sample.abs() >= self.threshold {
+                        return self.buffer.pop_front();
+                    }
+                }
+                None => {
+                    self.buffer.clear();
+                    return None;
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.buffer.len(), None)
+    }
+}
+
+impl<I> Source for TrimSilence<I>
+where
+    I: Source<Item = f32>,
+{
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input
+            .current_frame_len()
+            .map(|x| x + self.buffer.len())
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    #[inline]
+    fn elapsed(&mut self) -> Duration {
+        self.input.elapsed()
+    }
+
+    fn seek(&mut self, time: Duration) -> Result<Duration, ()> {
+        let result = self.input.seek(time);
+        if result.is_ok() {
+            self.buffer.clear();
+            self.position = samples_at(&self.input, time);
+        }
+        result
+    }
+}
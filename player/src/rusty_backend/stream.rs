@@ -32,7 +32,8 @@ impl OutputStream {
         device: &cpal::Device,
         error_sender: Arc<Sender<StreamError>>,
     ) -> Result<(Self, OutputStreamHandle), StreamError> {
-        let (mixer, stream) = device.try_new_output_stream(error_sender)?;
+        let (mixer, stream) =
+            device.try_new_output_stream(error_sender, OutputConfig::default())?;
         stream.play()?;
         let out = Self {
             mixer,
@@ -196,17 +197,28 @@ impl error::Error for StreamError {
     }
 }
 
+/// Settings of the output stream, `None` lets the device choose
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OutputConfig {
+    /// Size of the output buffer in frames
+    pub buffer_size: Option<u32>,
+    /// Sample rate every source is resampled to
+    pub sample_rate: Option<u32>,
+}
+
 /// Extensions to `cpal::Device`
 pub trait CpalDeviceExt {
     fn new_output_stream_with_format(
         &self,
         error_sender: Arc<Sender<StreamError>>,
         format: cpal::SupportedStreamConfig,
+        buffer_size: Option<u32>,
     ) -> Result<(Arc<DynamicMixerController<f32>>, cpal::Stream), cpal::BuildStreamError>;
 
     fn try_new_output_stream(
         &self,
         error_sender: Arc<Sender<StreamError>>,
+        output: OutputConfig,
     ) -> Result<(Arc<DynamicMixerController<f32>>, cpal::Stream), StreamError>;
 }
 
@@ -215,17 +227,28 @@ impl CpalDeviceExt for cpal::Device {
         &self,
         error_sender: Arc<Sender<StreamError>>,
         format: cpal::SupportedStreamConfig,
+        buffer_size: Option<u32>,
     ) -> Result<(Arc<DynamicMixerController<f32>>, cpal::Stream), cpal::BuildStreamError> {
         let (mixer_tx, mut mixer_rx) =
             dynamic_mixer::mixer::<f32>(format.channels(), format.sample_rate().0);
 
+        let mut config = format.config();
+        if let Some(frames) = buffer_size {
+            // Clamped to the sizes supported by the device
+            config.buffer_size = cpal::BufferSize::Fixed(match format.buffer_size() {
+                cpal::SupportedBufferSize::Range { min, max } => frames.clamp(*min, *max),
+                cpal::SupportedBufferSize::Unknown => frames,
+            });
+        }
+
         let error_callback = move |err: cpal::StreamError| {
-            error_sender.send(StreamError::StreamError(err)).unwrap();
+            // The receiver is gone when the app quits
+            let _ = error_sender.send(StreamError::StreamError(err));
         };
 
         match format.sample_format() {
             cpal::SampleFormat::F32 => self.build_output_stream::<f32, _, _>(
-                &format.config(),
+                &config,
                 move |data, _| {
                     data.iter_mut()
                         .for_each(|d| *d = mixer_rx.next().unwrap_or(0_f32));
@@ -233,7 +256,7 @@ impl CpalDeviceExt for cpal::Device {
                 error_callback,
             ),
             cpal::SampleFormat::I16 => self.build_output_stream::<i16, _, _>(
-                &format.config(),
+                &config,
                 move |data, _| {
                     data.iter_mut()
                         .for_each(|d| *d = mixer_rx.next().map_or(0_i16, |s| s.to_i16()));
@@ -241,7 +264,7 @@ impl CpalDeviceExt for cpal::Device {
                 error_callback,
             ),
             cpal::SampleFormat::U16 => self.build_output_stream::<u16, _, _>(
-                &format.config(),
+                &config,
                 move |data, _| {
                     for d in data.iter_mut() {
                         *d = mixer_rx.next().map_or(u16::max_value() / 2, |s| s.to_u16());
@@ -256,17 +279,24 @@ impl CpalDeviceExt for cpal::Device {
     fn try_new_output_stream(
         &self,
         error_sender: Arc<Sender<StreamError>>,
+        output: OutputConfig,
     ) -> Result<(Arc<DynamicMixerController<f32>>, cpal::Stream), StreamError> {
         // Determine the format to use for the new stream.
-        let default_format = self.default_output_config()?;
+        // It is read again when the stream is recreated as the device may have changed.
+        let default_format = output_format(self, output.sample_rate)?;
+        let buffer_size = output.buffer_size;
 
-        self.new_output_stream_with_format(error_sender.clone(), default_format)
+        self.new_output_stream_with_format(error_sender.clone(), default_format, buffer_size)
             .or_else(|err| {
                 // look through all supported formats to see if another works
                 supported_output_formats(self)?
                     .find_map(|format| {
-                        self.new_output_stream_with_format(error_sender.clone(), format)
-                            .ok()
+                        self.new_output_stream_with_format(
+                            error_sender.clone(),
+                            format,
+                            buffer_size,
+                        )
+                        .ok()
                     })
                     // return original error if nothing works
                     .ok_or(StreamError::BuildStreamError(err))
@@ -274,6 +304,28 @@ impl CpalDeviceExt for cpal::Device {
     }
 }
 
+/// The default format of the device, using the given sample rate if the device supports it
+fn output_format(
+    device: &cpal::Device,
+    sample_rate: Option<u32>,
+) -> Result<cpal::SupportedStreamConfig, StreamError> {
+    let default_format = device.default_output_config()?;
+    let rate = match sample_rate {
+        Some(rate) if rate != default_format.sample_rate().0 => cpal::SampleRate(rate),
+        _ => return Ok(default_format),
+    };
+    let format = device
+        .supported_output_configs()?
+        .filter(|x| {
+            x.channels() == default_format.channels()
+                && x.min_sample_rate() <= rate
+                && rate <= x.max_sample_rate()
+        })
+        .max_by(|a, b| a.cmp_default_heuristics(b))
+        .map(|x| x.with_sample_rate(rate));
+    Ok(format.unwrap_or(default_format))
+}
+
 /// All the supported output formats with sample rates
 fn supported_output_formats(
     device: &cpal::Device,
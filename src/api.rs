@@ -0,0 +1,119 @@
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use once_cell::sync::{Lazy, OnceCell};
+use tokio::sync::{Mutex, Semaphore};
+use ytpapi::YTApi;
+
+use crate::{config::CONFIG, systems::logger::log_};
+
+// The API shared between the systems, set by the API task once connected
+pub static API: OnceCell<Arc<YTApi>> = OnceCell::new();
+
+// Limits the number of requests sent to YouTube at the same time
+static CONCURRENT_REQUESTS: Lazy<Semaphore> =
+    Lazy::new(|| Semaphore::new(CONFIG.api_max_concurrent.max(1)));
+
+// When the last request was started
+static LAST_REQUEST: Lazy<Mutex<Option<Instant>>> = Lazy::new(|| Mutex::new(None));
+
+// Set when YouTube answered as if the user wasn't logged in, cleared by a successful reconnection
+pub static SESSION_EXPIRED: AtomicBool = AtomicBool::new(false);
+
+/**
+ * Logs an error of the API, the session is marked as expired if it is the cause
+ */
+pub fn report_error(e: &ytpapi::Error) {
+    if e.is_auth_error() {
+        SESSION_EXPIRED.store(true, Ordering::SeqCst);
+    }
+    log_(format!("{:?}", e));
+}
+
+pub fn is_session_expired() -> bool {
+    SESSION_EXPIRED.load(Ordering::SeqCst)
+}
+
+// Number of requests and downloads running, shown by the activity indicator
+static NETWORK_TASKS: AtomicUsize = AtomicUsize::new(0);
+
+/**
+ * Counts a network task as running until it is dropped, so a cancelled task is counted out too
+ */
+pub struct NetworkActivity(());
+
+impl NetworkActivity {
+    pub fn start() -> Self {
+        NETWORK_TASKS.fetch_add(1, Ordering::SeqCst);
+        Self(())
+    }
+}
+
+impl Drop for NetworkActivity {
+    fn drop(&mut self) {
+        NETWORK_TASKS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub fn is_network_busy() -> bool {
+    NETWORK_TASKS.load(Ordering::SeqCst) != 0
+}
+
+/**
+ * Returns the API if the connection succeeded
+ */
+pub fn api() -> Option<Arc<YTApi>> {
+    API.get().cloned()
+}
+
+/**
+ * Runs an API request once the rate limit allows it.
+ * Every call to the API should go through this function so bursts of requests are smoothed out.
+ */
+pub async fn limited<T>(request: impl Future<Output = T>) -> T {
+    let _activity = NetworkActivity::start();
+    let _permit = CONCURRENT_REQUESTS.acquire().await.unwrap();
+    {
+        let mut last = LAST_REQUEST.lock().await;
+        if let Some(last) = *last {
+            let next = last + Duration::from_millis(CONFIG.api_min_interval_ms);
+            tokio::time::sleep(next.saturating_duration_since(Instant::now())).await;
+        }
+        *last = Some(Instant::now());
+    }
+    request.await
+}
+
+/**
+ * Runs an API request with `limited`, it is sent again after a growing delay while it fails with
+ * a transient error
+ */
+pub async fn retried<T, F: Future<Output = Result<T, ytpapi::Error>>>(
+    mut request: impl FnMut() -> F,
+) -> Result<T, ytpapi::Error> {
+    let mut delay = Duration::from_millis(CONFIG.api_retry_delay_ms);
+    let mut attempts = 0;
+    loop {
+        match limited(request()).await {
+            Err(e) if e.is_transient() && attempts < CONFIG.api_retries => {
+                attempts += 1;
+                log_(format!(
+                    "Request failed, retrying in {}ms ({}/{}): {:?}",
+                    delay.as_millis(),
+                    attempts,
+                    CONFIG.api_retries,
+                    e
+                ));
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            result => return result,
+        }
+    }
+}
@@ -0,0 +1,108 @@
+pub const USAGE: &str = r#"Usage: ytermusic [OPTIONS]
+
+Options:
+  --play <query>     Play the first search result of the query
+  --playlist <name>  Play the playlist with the given name
+  --file <path>      Play an audio file from the disk
+  --no-ui            Run without the terminal interface, stop with CTRL + C
+  --verify-cache     Check that every downloaded song can be decoded and exit
+  --find-duplicates  List the songs downloaded several times under different ids and exit
+  --fix              With --verify-cache, download the broken songs again
+                     With --find-duplicates, keep only one copy of each song
+  --export <path>    Write the list of the downloaded songs to the file and exit
+  --format <format>  With --export, `json` (the default) or `csv`
+  --import <path>    Save the songs listed in the file as a playlist and exit, the file is a
+                     JSON list of ids, URLs or songs (like --export) or has one id or URL per line
+  --download         With --import, download the songs of the playlist
+  --help             Print this message"#;
+
+/**
+ * The format of the file written by `--export`
+ */
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+/**
+ * The command line arguments
+ */
+#[derive(Debug, Default, Clone)]
+pub struct Args {
+    pub play: Option<String>,
+    pub playlist: Option<String>,
+    pub file: Option<String>,
+    pub no_ui: bool,
+    pub verify_cache: bool,
+    pub find_duplicates: bool,
+    pub fix: bool,
+    pub export: Option<String>,
+    pub export_format: ExportFormat,
+    pub import: Option<String>,
+    pub download: bool,
+    pub help: bool,
+    // Hidden from the usage, measures the download speed
+    pub benchmark: bool,
+}
+
+impl Args {
+    /**
+     * Parses the arguments given to the program
+     */
+    pub fn parse() -> Result<Self, String> {
+        Self::parse_from(std::env::args().skip(1))
+    }
+
+    fn parse_from(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--play" => {
+                    parsed.play = Some(args.next().ok_or("Missing query after `--play`")?);
+                }
+                "--playlist" => {
+                    parsed.playlist = Some(args.next().ok_or("Missing name after `--playlist`")?);
+                }
+                "--file" => {
+                    parsed.file = Some(args.next().ok_or("Missing path after `--file`")?);
+                }
+                "--export" => {
+                    parsed.export = Some(args.next().ok_or("Missing path after `--export`")?);
+                }
+                "--format" => {
+                    parsed.export_format = match args.next().as_deref() {
+                        Some("json") => ExportFormat::Json,
+                        Some("csv") => ExportFormat::Csv,
+                        _ => {
+                            return Err("`--format` must be followed by `json` or `csv`".to_owned())
+                        }
+                    };
+                }
+                "--import" => {
+                    parsed.import = Some(args.next().ok_or("Missing path after `--import`")?);
+                }
+                "--download" => parsed.download = true,
+                "--no-ui" => parsed.no_ui = true,
+                "--verify-cache" => parsed.verify_cache = true,
+                "--find-duplicates" => parsed.find_duplicates = true,
+                "--fix" => parsed.fix = true,
+                "--benchmark" => parsed.benchmark = true,
+                "--help" | "-h" => parsed.help = true,
+                e => return Err(format!("Unknown argument `{}`", e)),
+            }
+        }
+        Ok(parsed)
+    }
+
+    /**
+     * Returns true if the playlist was asked to be played on startup
+     */
+    pub fn is_requested_playlist(&self, name: &str) -> bool {
+        self.playlist
+            .as_ref()
+            .map(|x| x.eq_ignore_ascii_case(name.trim()))
+            .unwrap_or(false)
+    }
+}
@@ -0,0 +1,824 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use directories::ProjectDirs;
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use tui::style::Color;
+use ytpapi::SearchType;
+
+use crate::{systems::logger::log_, term::Screens};
+
+/**
+ * What to do when a downloaded song can't be decoded
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecodeErrorAction {
+    // Download the song again and play the next songs in the meantime
+    #[default]
+    Skip,
+    // Download the song again and wait for it before playing anything else
+    Wait,
+    // Remove the song from the cache without downloading it again
+    Remove,
+}
+
+/**
+ * What to do when the last song of the queue is finished
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueEndAction {
+    // Stay idle
+    #[default]
+    Stop,
+    // Play the whole queue again
+    RepeatAll,
+    // Queue songs similar to the last one
+    Radio,
+    // Go back to the playlist selector
+    Playlist,
+}
+
+/**
+ * What to do when seeking forward past the end of a song
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeekEndAction {
+    // Stay in the song, it ends by itself
+    #[default]
+    Clamp,
+    // Play the next song of the queue
+    Next,
+    // Pause at the end of the song, the next one starts when play is pressed
+    Stop,
+}
+
+/**
+ * What to do when seeking backward at the start of a song
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeekStartAction {
+    // Stay at the start of the song
+    #[default]
+    Clamp,
+    // Play the previous song, the first press still goes back to the start of the song
+    Previous,
+}
+
+/**
+ * What a click on a song of the queue does
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueClick {
+    // Play the song right away
+    #[default]
+    Play,
+    // Select the song, a double click or `Enter` plays it
+    Select,
+}
+
+/**
+ * What a click on the progress bar of the player does, outside of its time label
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressClick {
+    // Seek to the clicked position of the song
+    #[default]
+    Seek,
+    Nothing,
+}
+
+/**
+ * What to do when a song already in the queue is added to it
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateAction {
+    // Add the song again
+    #[default]
+    Allow,
+    // Keep the queue as it is
+    Skip,
+    // Move the song to the end of the queue
+    Move,
+}
+
+/**
+ * What `Space` does in the player when no song is loaded
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdleSpaceAction {
+    // Nothing, like pausing an empty player
+    #[default]
+    Nothing,
+    // Play the queue again from its first song
+    PlayQueue,
+    // Play the last playlist chosen in the playlist selector
+    ResumeLast,
+}
+
+/**
+ * The characters used for the status of the songs and the indicators
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Glyphs {
+    #[default]
+    Unicode,
+    // Plain ASCII, for the terminals and fonts without the Unicode symbols
+    Ascii,
+    // ASCII if the locale isn't UTF-8 or the terminal is the Linux console
+    Auto,
+}
+
+/**
+ * How many lines each song takes in the playlist of the player
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueDensity {
+    // The artist and the title on one line
+    #[default]
+    Compact,
+    // The album and the duration on a second line
+    Expanded,
+}
+
+/**
+ * Which song of the download queue is downloaded next
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadOrder {
+    // In the order they were added
+    #[default]
+    Fifo,
+    // The closest after the current song first, then the closest before it
+    Proximity,
+}
+
+/**
+ * The program used to download the songs
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadBackendKind {
+    #[default]
+    Rustube,
+    YtDlp,
+}
+
+/**
+ * The screen shown when the app starts
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartScreen {
+    #[default]
+    Playlist,
+    Search,
+    MusicPlayer,
+}
+
+impl From<StartScreen> for Screens {
+    fn from(screen: StartScreen) -> Self {
+        match screen {
+            StartScreen::Playlist => Screens::Playlist,
+            StartScreen::Search => Screens::Search,
+            StartScreen::MusicPlayer => Screens::MusicPlayer,
+        }
+    }
+}
+
+/**
+ * What `CTRL + C` and `CTRL + D` do
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InterruptAction {
+    // Quits right away
+    #[default]
+    Quit,
+    // Asks before quitting, pressing it again while asked quits
+    Confirm,
+    // Stops the current song and plays the next one, pressing it twice in a second quits
+    StopTrack,
+}
+
+/**
+ * What the `Esc` key does on a screen
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EscAction {
+    // Goes back to the previous screen
+    #[default]
+    Back,
+    // Clears the text typed first, then goes back when it is already empty
+    Clear,
+    Nothing,
+}
+
+/**
+ * What `Enter` does in the search when nothing is typed
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmptySearchAction {
+    // Searches the last text submitted again
+    #[default]
+    RepeatLast,
+    // Shows the mix of YouTube Music for the last song played
+    Recommendations,
+    // Asks to type something
+    Prompt,
+}
+
+/**
+ * How a song played on its own (from the search or the history) is added to the queue
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnaryPlayPolicy {
+    // Plays it after the current song, then the queue continues
+    #[default]
+    Next,
+    // Stops the current song to play it, then the queue continues
+    Now,
+    // Stops the current song and forgets the queue to play it
+    Replace,
+}
+
+/**
+ * The text shown on the progress bar of the player
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressLabel {
+    // Only the bar
+    None,
+    Elapsed,
+    #[default]
+    ElapsedTotal,
+    Percentage,
+    // The time left before the end of the song and the total
+    Remaining,
+}
+
+/**
+ * What `--no-ui` does once its standard input is closed
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StdinEofAction {
+    // Keeps playing until the process is interrupted
+    #[default]
+    Continue,
+    Quit,
+}
+
+/**
+ * A color written as a name (`light_blue`, `reset` for the terminal's color) or as `#rrggbb`
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ConfigColor(pub Color);
+
+impl TryFrom<String> for ConfigColor {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if let Some(hex) = value.strip_prefix('#') {
+            let rgb = u32::from_str_radix(hex, 16)
+                .ok()
+                .filter(|_| hex.len() == 6)
+                .ok_or_else(|| format!("Invalid color `{}`", value))?;
+            return Ok(Self(Color::Rgb(
+                (rgb >> 16) as u8,
+                (rgb >> 8) as u8,
+                rgb as u8,
+            )));
+        }
+        Ok(Self(
+            match value.to_lowercase().replace(['-', ' '], "_").as_str() {
+                "reset" => Color::Reset,
+                "black" => Color::Black,
+                "red" => Color::Red,
+                "green" => Color::Green,
+                "yellow" => Color::Yellow,
+                "blue" => Color::Blue,
+                "magenta" => Color::Magenta,
+                "cyan" => Color::Cyan,
+                "gray" => Color::Gray,
+                "dark_gray" => Color::DarkGray,
+                "light_red" => Color::LightRed,
+                "light_green" => Color::LightGreen,
+                "light_yellow" => Color::LightYellow,
+                "light_blue" => Color::LightBlue,
+                "light_magenta" => Color::LightMagenta,
+                "light_cyan" => Color::LightCyan,
+                "white" => Color::White,
+                _ => return Err(format!("Invalid color `{}`", value)),
+            },
+        ))
+    }
+}
+
+/**
+ * The colors of the rows of the search results
+ */
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SearchColors {
+    pub selected_fg: ConfigColor,
+    pub selected_bg: ConfigColor,
+    // The text of the downloaded songs
+    pub local_fg: ConfigColor,
+    // The text of the songs that aren't downloaded
+    pub remote_fg: ConfigColor,
+    pub bg: ConfigColor,
+}
+
+impl Default for SearchColors {
+    fn default() -> Self {
+        Self {
+            selected_fg: ConfigColor(Color::Black),
+            selected_bg: ConfigColor(Color::White),
+            local_fg: ConfigColor(Color::White),
+            remote_fg: ConfigColor(Color::LightBlue),
+            bg: ConfigColor(Color::Black),
+        }
+    }
+}
+
+/**
+ * Named gains of the equalizer, switched with `e` in the player
+ */
+#[derive(Debug, Clone, Deserialize)]
+pub struct EqualizerPreset {
+    pub name: String,
+    // Gain in dB of each band, from the lowest (60Hz) to the highest (12kHz)
+    pub gains: Vec<f32>,
+}
+
+impl EqualizerPreset {
+    fn new(name: &str, gains: [f32; 5]) -> Self {
+        Self {
+            name: name.to_owned(),
+            gains: gains.to_vec(),
+        }
+    }
+}
+
+/**
+ * Which audio stream of a song is downloaded
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioQuality {
+    // The mp4 stream with the best bitrate, or the best one under `max_bitrate_kbps`
+    #[default]
+    Highest,
+    // The mp4 stream with the lowest bitrate, to save bandwidth
+    Lowest,
+}
+
+/**
+ * The order of the songs of the database, shown in "Local musics"
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DatabaseSort {
+    // By artist, then by title
+    #[default]
+    ArtistTitle,
+    Title,
+    // The songs downloaded last first
+    DateAdded,
+}
+
+/**
+ * What is done on startup with the song played when the player was last closed
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResumeSession {
+    // The song isn't played again
+    Off,
+    // The song is queued at the time it was stopped at and waits for the user to press play
+    #[default]
+    Paused,
+    // The song is played from the time it was stopped at
+    Playing,
+}
+
+/**
+ * The keys of an action in the `[keys]` table, one key or a list of keys
+ */
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+/**
+ * The user configuration read from the `config.toml` file
+ */
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub on_decode_error: DecodeErrorAction,
+    // Number of lines (or volume steps) moved by one mouse wheel step
+    pub scroll_sensitivity: usize,
+    pub on_queue_end: QueueEndAction,
+    // Number of seconds a cached playlist is used before being fetched again
+    pub playlist_cache_ttl: u64,
+    // Show the current track in the terminal title
+    pub terminal_title: bool,
+    // Command run when a new track starts, `{title}`, `{author}`, `{album}`, `{id}` and `{duration}` are replaced
+    pub on_track_change: Option<String>,
+    // Number of seconds without playback nor input before quitting, 0 to never quit
+    pub idle_quit_secs: u64,
+    // Maximum number of requests sent to YouTube at the same time
+    pub api_max_concurrent: usize,
+    // Minimum number of milliseconds between the start of two requests
+    pub api_min_interval_ms: u64,
+    // Size of the audio output buffer in frames, bigger values avoid stutters but add latency
+    pub audio_buffer_size: Option<u32>,
+    // Ask before replacing a queue that isn't empty
+    pub confirm_clear_queue: bool,
+    // Path of a Unix socket accepting commands, disabled if unset
+    pub control_socket: Option<String>,
+    pub start_screen: StartScreen,
+    // A song counts as played for the history and the stats once either threshold is reached,
+    // 0 disables a threshold. Skipping a song before that counts as a skip.
+    pub played_threshold_secs: u64,
+    pub played_threshold_percent: u8,
+    pub download_backend: DownloadBackendKind,
+    // Download with yt-dlp when rustube can't extract the audio of a video
+    pub ytdlp_fallback: bool,
+    pub ytdlp_path: String,
+    // Number of seconds before the end of a song where the next one is opened, 0 to disable
+    pub preload_secs: u64,
+    pub esc_music_player: EscAction,
+    pub esc_search: EscAction,
+    // Sample rate of the output, every song is resampled to it. The device's rate if unset
+    pub sample_rate: Option<u32>,
+    pub search_colors: SearchColors,
+    // Maximum number of songs in the queue and in the download queue, 0 for no limit
+    pub max_queue_len: usize,
+    // Write the title, artist, album and thumbnail in the downloaded files
+    pub embed_tags: bool,
+    // No download is started when the disk has less free space than this, in megabytes
+    pub min_free_space_mb: u64,
+    pub search_type: SearchType,
+    // The first song is loaded paused instead of playing
+    pub start_paused: bool,
+    pub seek_past_end: SeekEndAction,
+    pub seek_before_start: SeekStartAction,
+    // Keep the audio of the songs in memory while they are played instead of downloading them
+    pub streaming: bool,
+    pub queue_click: QueueClick,
+    // Milliseconds between two redraws once idle, 0 to always redraw every 250ms
+    pub idle_tick_ms: u64,
+    // Show the main keys of the screen on its bottom border
+    pub footer_hints: bool,
+    // Number of times a request failing because of the network is sent again
+    pub api_retries: usize,
+    // Delay before the first retry in milliseconds, doubled after each retry
+    pub api_retry_delay_ms: u64,
+    // Where the songs and the playlists are cached, the cache directory of the user if unset
+    pub cache_dir: Option<PathBuf>,
+    // Number of songs downloaded at the same time
+    pub downloader_count: usize,
+    // Change of the volume in percents for each `+` or `-` press
+    pub volume_step: u8,
+    pub on_duplicate: DuplicateAction,
+    // Search the last text again when the search is opened
+    pub search_refresh_on_open: bool,
+    // The settings and the stats are written at most once every this many seconds, 0 writes
+    // them on every change
+    pub persist_interval_secs: u64,
+    // Maximum number of results shown in the search, 0 for no limit
+    pub max_search_results: usize,
+    // Flush the downloaded songs to the disk before marking them as downloaded
+    pub fsync_downloads: bool,
+    // Ask for the cookie and create `headers.txt` when it is missing instead of printing the steps
+    pub setup_wizard: bool,
+    pub equalizer_presets: Vec<EqualizerPreset>,
+    // A download taking longer than this many seconds fails, 0 for no limit
+    pub download_timeout_secs: u64,
+    pub download_order: DownloadOrder,
+    // Skip the silence at the end of the songs, quieter than `silence_threshold_db` dBFS
+    pub trim_silence: bool,
+    pub silence_threshold_db: f32,
+    pub space_when_idle: IdleSpaceAction,
+    // Download one song at a time with a single task, `downloader_count` is ignored
+    pub single_downloader: bool,
+    pub glyphs: Glyphs,
+    pub queue_density: QueueDensity,
+    pub interrupt_action: InterruptAction,
+    // Play the songs louder or quieter as told by the ReplayGain tag of their file
+    pub use_gain_tags: bool,
+    // Show the last playlist played in the playlist selector on startup
+    pub show_last_playlist: bool,
+    // The color of the song being played in the songs of a playlist
+    pub playing_color: ConfigColor,
+    // Pause while a call is playing, needs the `audio-focus` feature
+    pub pause_on_call: bool,
+    // Only redraw the screen when something on it changed, less flicker on slow terminals
+    pub redraw_on_change: bool,
+    pub progress_click: ProgressClick,
+    // Number of songs of the queue after the current one whose audio is fetched again if missing
+    pub download_ahead: usize,
+    // The color and the marker of the songs of a playlist that aren't downloaded
+    pub missing_color: ConfigColor,
+    pub missing_marker: Option<String>,
+    // Total bytes per second of all the downloads and streams together, 0 for no limit
+    pub download_bandwidth_limit: u64,
+    // Milliseconds of silence between a song ending and the next one, 0 to play them back to back
+    pub track_gap_ms: u64,
+    // Logs the audio streams of the songs downloaded and shows them in their details
+    pub debug_streams: bool,
+    // The itag of the audio stream to download when a song has it, instead of the best mp4 one
+    pub force_itag: Option<u64>,
+    // Playlists with more songs are kept on the disk and read when opened or played, 0 for never
+    pub eager_playlist_len: usize,
+    // What `--no-ui` does once its standard input is closed
+    pub on_stdin_eof: StdinEofAction,
+    // The text of the progress bar, `t` switches between it and the remaining time
+    pub progress_label: ProgressLabel,
+    // Sends a search in the background on startup so the first search of the user is faster
+    pub prewarm_search: bool,
+    // How a song played on its own is added to the queue
+    pub unary_play: UnaryPlayPolicy,
+    // The volume in percents on the first start, the last volume is kept afterwards
+    pub default_volume: u8,
+    // What `Enter` does in the search when nothing is typed
+    pub empty_search: EmptySearchAction,
+    // Sound file played when the download queue is finished, "beep" for a built-in tone
+    pub download_chime: Option<String>,
+    // Number of the function key writing `state_dump.txt` for the bug reports, 0 disables it
+    pub state_dump_key: u8,
+    // Time in milliseconds the volume takes to rise at the start of each song, 0 disables it
+    pub fade_in_ms: u64,
+    // Number of failed downloads that can be tried again during a session, 0 for no limit
+    pub retry_budget: usize,
+    // Number of failed downloads tried again at the same time, 0 for no limit
+    pub max_concurrent_retries: usize,
+    // Number of times a download is tried before giving up on the song
+    pub download_attempts: usize,
+    // Delay before trying a download again in milliseconds, doubled after each failure
+    pub download_retry_delay_ms: u64,
+    // The keys of the actions of the player and the search by action name, see `term/keymap.rs`
+    pub keys: BTreeMap<String, KeyList>,
+    pub audio_quality: AudioQuality,
+    // The best stream under this bitrate in kbps is downloaded, or the lowest one if none is
+    // under it. 0 for no cap
+    pub max_bitrate_kbps: u64,
+    // The least recently played downloads are removed when they take more than this in
+    // megabytes, 0 for no limit
+    pub max_cache_mb: u64,
+    // Queues the song of the last session at the time it was stopped at, if it is still downloaded
+    pub resume_session: ResumeSession,
+    pub database_sort: DatabaseSort,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            on_decode_error: DecodeErrorAction::default(),
+            scroll_sensitivity: 1,
+            on_queue_end: QueueEndAction::default(),
+            playlist_cache_ttl: 3600,
+            terminal_title: true,
+            on_track_change: None,
+            idle_quit_secs: 0,
+            api_max_concurrent: 2,
+            api_min_interval_ms: 250,
+            audio_buffer_size: None,
+            confirm_clear_queue: false,
+            control_socket: None,
+            start_screen: StartScreen::default(),
+            played_threshold_secs: 240,
+            played_threshold_percent: 50,
+            download_backend: DownloadBackendKind::default(),
+            ytdlp_fallback: false,
+            ytdlp_path: "yt-dlp".to_owned(),
+            preload_secs: 10,
+            esc_music_player: EscAction::default(),
+            esc_search: EscAction::default(),
+            sample_rate: None,
+            search_colors: SearchColors::default(),
+            max_queue_len: 0,
+            embed_tags: false,
+            min_free_space_mb: 100,
+            search_type: SearchType::default(),
+            start_paused: false,
+            seek_past_end: SeekEndAction::default(),
+            seek_before_start: SeekStartAction::default(),
+            streaming: false,
+            queue_click: QueueClick::default(),
+            idle_tick_ms: 2000,
+            footer_hints: true,
+            api_retries: 3,
+            api_retry_delay_ms: 500,
+            cache_dir: None,
+            downloader_count: 4,
+            volume_step: 5,
+            on_duplicate: DuplicateAction::default(),
+            search_refresh_on_open: false,
+            persist_interval_secs: 10,
+            max_search_results: 0,
+            fsync_downloads: false,
+            setup_wizard: true,
+            download_timeout_secs: 120,
+            download_order: DownloadOrder::default(),
+            trim_silence: false,
+            silence_threshold_db: -50.0,
+            space_when_idle: IdleSpaceAction::default(),
+            single_downloader: false,
+            glyphs: Glyphs::default(),
+            queue_density: QueueDensity::default(),
+            interrupt_action: InterruptAction::default(),
+            use_gain_tags: true,
+            show_last_playlist: true,
+            playing_color: ConfigColor(Color::LightGreen),
+            pause_on_call: false,
+            redraw_on_change: true,
+            progress_click: ProgressClick::default(),
+            download_ahead: 2,
+            missing_color: ConfigColor(Color::LightBlue),
+            missing_marker: None,
+            download_bandwidth_limit: 0,
+            track_gap_ms: 0,
+            debug_streams: false,
+            force_itag: None,
+            eager_playlist_len: 1000,
+            on_stdin_eof: StdinEofAction::default(),
+            progress_label: ProgressLabel::default(),
+            prewarm_search: false,
+            unary_play: UnaryPlayPolicy::default(),
+            default_volume: 50,
+            empty_search: EmptySearchAction::default(),
+            download_chime: None,
+            state_dump_key: 12,
+            fade_in_ms: 0,
+            retry_budget: 50,
+            max_concurrent_retries: 1,
+            download_attempts: 3,
+            download_retry_delay_ms: 500,
+            keys: BTreeMap::new(),
+            audio_quality: AudioQuality::default(),
+            max_bitrate_kbps: 0,
+            max_cache_mb: 0,
+            resume_session: ResumeSession::default(),
+            database_sort: DatabaseSort::default(),
+            equalizer_presets: vec![
+                EqualizerPreset::new("Flat", [0.0, 0.0, 0.0, 0.0, 0.0]),
+                EqualizerPreset::new("Bass Boost", [6.0, 4.0, 0.0, 0.0, 0.0]),
+                EqualizerPreset::new("Vocal", [-2.0, -1.0, 3.0, 3.0, 0.0]),
+                EqualizerPreset::new("Treble Boost", [0.0, 0.0, 0.0, 3.0, 6.0]),
+            ],
+        }
+    }
+}
+
+// Bounds of `audio_buffer_size`
+const AUDIO_BUFFER_SIZES: std::ops::RangeInclusive<u32> = 64..=65536;
+// Bounds of the gains of the equalizer presets in dB
+const EQUALIZER_GAINS: std::ops::RangeInclusive<f32> = -24.0..=24.0;
+
+impl Config {
+    /**
+     * Returns every value out of its bounds
+     */
+    fn validate(self) -> Result<Self, String> {
+        let mut errors = Vec::new();
+        if let Some(size) = self.audio_buffer_size {
+            if !AUDIO_BUFFER_SIZES.contains(&size) {
+                errors.push(format!(
+                    "`audio_buffer_size` must be between {} and {}, got {}",
+                    AUDIO_BUFFER_SIZES.start(),
+                    AUDIO_BUFFER_SIZES.end(),
+                    size
+                ));
+            }
+        }
+        if self.sample_rate == Some(0) {
+            errors.push(
+                "`sample_rate` can't be 0, remove it to use the rate of the device".to_owned(),
+            );
+        }
+        if self.downloader_count == 0 {
+            errors.push("`downloader_count` must be at least 1".to_owned());
+        }
+        if self.download_attempts == 0 {
+            errors.push("`download_attempts` must be at least 1".to_owned());
+        }
+        if self.volume_step == 0 || self.volume_step > 100 {
+            errors.push(format!(
+                "`volume_step` must be between 1 and 100, got {}",
+                self.volume_step
+            ));
+        }
+        if self.default_volume > 100 {
+            errors.push(format!(
+                "`default_volume` must be at most 100, got {}",
+                self.default_volume
+            ));
+        }
+        if self.played_threshold_percent > 100 {
+            errors.push(format!(
+                "`played_threshold_percent` must be at most 100, got {}",
+                self.played_threshold_percent
+            ));
+        }
+        if self.silence_threshold_db >= 0.0 {
+            errors.push(format!(
+                "`silence_threshold_db` must be below 0, got {}",
+                self.silence_threshold_db
+            ));
+        }
+        if let Some(command) = &self.on_track_change {
+            if command.trim().is_empty() {
+                errors
+                    .push("`on_track_change` can't be empty, remove it to run nothing".to_owned());
+            }
+        }
+        if let Some(chime) = &self.download_chime {
+            if chime != "beep" && !std::path::Path::new(chime).is_file() {
+                errors.push(format!("The `download_chime` file {} doesn't exist", chime));
+            }
+        }
+        for preset in &self.equalizer_presets {
+            if preset.gains.len() > player::source::EQUALIZER_BANDS.len() {
+                errors.push(format!(
+                    "The equalizer preset `{}` has {} gains but there are only {} bands",
+                    preset.name,
+                    preset.gains.len(),
+                    player::source::EQUALIZER_BANDS.len()
+                ));
+            }
+            if preset.gains.iter().any(|x| !EQUALIZER_GAINS.contains(x)) {
+                errors.push(format!(
+                    "The gains of the equalizer preset `{}` must be between {} and {} dB",
+                    preset.name,
+                    EQUALIZER_GAINS.start(),
+                    EQUALIZER_GAINS.end()
+                ));
+            }
+        }
+        if errors.is_empty() {
+            Ok(self)
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+}
+
+/**
+ * The configuration file: `config.toml` in the working directory, or in the configuration
+ * directory of the user (`~/.config/ytermusic/config.toml` on Linux)
+ */
+pub fn config_path() -> Option<PathBuf> {
+    let local = PathBuf::from("config.toml");
+    if local.exists() {
+        return Some(local);
+    }
+    ProjectDirs::from("com", "ccgauche", "ytermusic")
+        .map(|x| x.config_dir().join("config.toml"))
+        .filter(|x| x.exists())
+}
+
+/**
+ * Reads the configuration file, the defaults are used if there is none
+ */
+pub fn load() -> Result<Config, String> {
+    let path = match config_path() {
+        Some(e) => e,
+        None => return Ok(Config::default()),
+    };
+    let invalid = |e: String| format!("Invalid configuration file {}:\n{}", path.display(), e);
+    let text = std::fs::read_to_string(&path).map_err(|e| invalid(e.to_string()))?;
+    toml::from_str::<Config>(&text)
+        .map_err(|e| e.to_string())
+        .and_then(Config::validate)
+        .map_err(invalid)
+}
+
+// A global variable to store the configuration, `load` is checked on startup so the defaults are
+// only used if the file changed since
+pub static CONFIG: Lazy<Config> = Lazy::new(|| {
+    load().unwrap_or_else(|e| {
+        log_(e);
+        Config::default()
+    })
+});
@@ -3,7 +3,7 @@ use std::path::PathBuf;
 use directories::ProjectDirs;
 use once_cell::sync::Lazy;
 
-use crate::systems::logger::log_;
+use crate::{config::CONFIG, systems::logger::log_};
 
 pub const HEADER_TUTORIAL: &str = r#"To configure the YTerMusic:
 1. Open the YouTube Music website in your browser;
@@ -15,6 +15,9 @@ pub const HEADER_TUTORIAL: &str = r#"To configure the YTerMusic:
 7. Restart YterMusic"#;
 
 pub static CACHE_DIR: Lazy<PathBuf> = Lazy::new(|| {
+    if let Some(dir) = &CONFIG.cache_dir {
+        return dir.clone();
+    }
     let pdir = ProjectDirs::from("com", "ccgauche", "ytermusic");
     if let Some(dir) = pdir {
         return dir.cache_dir().to_path_buf();
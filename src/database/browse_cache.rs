@@ -0,0 +1,59 @@
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use ytpapi::Video;
+
+use crate::{config::CONFIG, consts::CACHE_DIR, systems::logger::log_};
+
+/**
+ * The content of a playlist as it was the last time it was fetched
+ */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CachedPlaylist {
+    pub name: String,
+    pub videos: Vec<Video>,
+}
+
+/**
+ * Reads every cached playlist
+ */
+pub fn read_all() -> Vec<CachedPlaylist> {
+    let entries = match std::fs::read_dir(CACHE_DIR.join("browse")) {
+        Ok(e) => e,
+        Err(_) => return Vec::new(),
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| super::read_json(&entry.path()))
+        .collect()
+}
+
+/**
+ * Reads a cached playlist, the boolean is true if the cache is recent enough to not be refreshed
+ */
+pub fn read(browse_id: &str) -> Option<(CachedPlaylist, bool)> {
+    let path = CACHE_DIR.join(format!("browse/{}.json", browse_id));
+    let playlist = super::read_json(&path)?;
+    let fresh = std::fs::metadata(&path)
+        .and_then(|x| x.modified())
+        .ok()
+        .and_then(|x| SystemTime::now().duration_since(x).ok())
+        .map(|x| x < Duration::from_secs(CONFIG.playlist_cache_ttl))
+        .unwrap_or(false);
+    Some((playlist, fresh))
+}
+
+/**
+ * Caches the content of a playlist
+ */
+pub fn write(browse_id: &str, playlist: &CachedPlaylist) {
+    let result = std::fs::create_dir_all(CACHE_DIR.join("browse")).and_then(|_| {
+        std::fs::write(
+            CACHE_DIR.join(format!("browse/{}.json", browse_id)),
+            serde_json::to_string(playlist)?,
+        )
+    });
+    if let Err(e) = result {
+        log_(format!("Can't cache the playlist {}: {}", playlist.name, e));
+    }
+}
@@ -0,0 +1,77 @@
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use ytpapi::Video;
+
+use crate::{consts::CACHE_DIR, systems::logger::log_};
+
+// Number of plays kept in the history file
+const HISTORY_SIZE: usize = 1000;
+
+/**
+ * A song that started playing
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    // Seconds since the Unix epoch
+    pub time: u64,
+    pub video: Video,
+}
+
+/**
+ * Appends a song to the history file
+ */
+pub fn append(video: &Video) {
+    let entry = HistoryEntry {
+        time: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|x| x.as_secs())
+            .unwrap_or(0),
+        video: video.clone(),
+    };
+    let result = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(CACHE_DIR.join("history.jsonl"))
+        .and_then(|mut file| writeln!(file, "{}", serde_json::to_string(&entry)?));
+    if let Err(e) = result {
+        log_(format!("Can't write the history: {}", e));
+    }
+}
+
+/**
+ * Reads the history, the most recent plays first
+ */
+pub fn read() -> Vec<HistoryEntry> {
+    let mut entries: Vec<HistoryEntry> = std::fs::read_to_string(CACHE_DIR.join("history.jsonl"))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|x| serde_json::from_str(x).ok())
+        .collect();
+    entries.reverse();
+    entries
+}
+
+/**
+ * Removes the oldest plays when the history is too long, called on startup
+ */
+pub fn rotate() {
+    let entries = read();
+    if entries.len() <= HISTORY_SIZE {
+        return;
+    }
+    let content: String = entries
+        .iter()
+        .take(HISTORY_SIZE)
+        .rev()
+        .filter_map(|x| serde_json::to_string(x).ok())
+        .map(|x| x + "\n")
+        .collect();
+    if let Err(e) = std::fs::write(CACHE_DIR.join("history.jsonl"), content) {
+        log_(format!("Can't rotate the history: {}", e));
+    }
+}
@@ -0,0 +1,72 @@
+use std::{collections::HashMap, sync::RwLock, time::Duration};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::{consts::CACHE_DIR, systems::logger::log_};
+
+/**
+ * The parts of a song skipped each time it is played, set with `(` and `)` in the player
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SkipMarkers {
+    // The song starts at this position, in milliseconds
+    pub intro_ms: Option<u64>,
+    // The song ends at this position, in milliseconds
+    pub outro_ms: Option<u64>,
+}
+
+// The markers of the songs that have some, by video id
+static MARKERS: Lazy<RwLock<HashMap<String, SkipMarkers>>> = Lazy::new(|| {
+    RwLock::new(super::read_json(&CACHE_DIR.join("markers.json")).unwrap_or_default())
+});
+
+pub fn get(video_id: &str) -> SkipMarkers {
+    MARKERS
+        .read()
+        .unwrap()
+        .get(video_id)
+        .copied()
+        .unwrap_or_default()
+}
+
+/**
+ * Changes the markers of a song, the song is forgotten once it has none
+ */
+pub fn update(video_id: &str, change: impl FnOnce(&mut SkipMarkers)) {
+    {
+        let mut markers = MARKERS.write().unwrap();
+        let entry = markers.entry(video_id.to_owned()).or_default();
+        change(entry);
+        if *entry == SkipMarkers::default() {
+            markers.remove(video_id);
+        }
+    }
+    save();
+}
+
+/**
+ * Returns the position where the song starts, if its intro is skipped
+ */
+pub fn intro(video_id: &str) -> Option<Duration> {
+    get(video_id).intro_ms.map(Duration::from_millis)
+}
+
+/**
+ * Returns the position where the song ends, if its outro is skipped
+ */
+pub fn outro(video_id: &str) -> Option<Duration> {
+    get(video_id).outro_ms.map(Duration::from_millis)
+}
+
+// The markers are rarely changed so they are written right away
+fn save() {
+    match serde_json::to_string(&*MARKERS.read().unwrap()) {
+        Ok(e) => {
+            if let Err(e) = std::fs::write(CACHE_DIR.join("markers.json"), e) {
+                log_(format!("Can't write the skip markers: {}", e));
+            }
+        }
+        Err(e) => log_(format!("Can't serialize the skip markers: {}", e)),
+    }
+}
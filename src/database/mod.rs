@@ -1,15 +1,35 @@
-use std::{fs::OpenOptions, sync::RwLock};
+use std::{
+    cmp::Reverse,
+    collections::HashSet,
+    fs::OpenOptions,
+    io::{ErrorKind, Write},
+    path::Path,
+    sync::RwLock,
+    time::SystemTime,
+};
 
 use once_cell::sync::Lazy;
+use serde::de::DeserializeOwned;
 use ytpapi::Video;
 
+pub mod browse_cache;
+pub mod history;
+pub mod markers;
+pub mod playlists;
 mod reader;
+pub mod resume;
+pub mod stats;
 mod writer;
 
 pub use reader::read;
 pub use writer::{write, write_video};
 
-use crate::{consts::CACHE_DIR, systems::logger::log_};
+use crate::{
+    config::{DatabaseSort, CONFIG},
+    consts::CACHE_DIR,
+    errors::report,
+    systems::logger::log_,
+};
 
 // A global variable to store the current musical Database
 pub static DATABASE: Lazy<RwLock<Vec<Video>>> = Lazy::new(|| RwLock::new(Vec::new()));
@@ -18,13 +38,123 @@ pub static DATABASE: Lazy<RwLock<Vec<Video>>> = Lazy::new(|| RwLock::new(Vec::ne
  * append a video to the database
  */
 pub fn append(video: Video) {
-    let mut file = OpenOptions::new()
-        .write(true)
+    let result = OpenOptions::new()
         .append(true)
         .create(true)
         .open(CACHE_DIR.join("db.bin"))
-        .unwrap();
-    write_video(&mut file, &video);
-    log_(format!("Appended {} to database", video.title));
+        .and_then(|mut file| {
+            // A new database starts with the header
+            if file.metadata()?.len() == 0 {
+                file.write_all(writer::HEADER)?;
+            }
+            write_video(&mut file, &video)
+        });
+    match result {
+        Ok(()) => log_(format!("Appended {} to database", video.title)),
+        // The song is still found by the scan of the downloads on the next start
+        Err(e) => log_(format!("Can't append {} to database: {}", video.title, e)),
+    }
     DATABASE.write().unwrap().push(video);
 }
+
+/**
+ * Removes the videos from the database and writes it
+ */
+pub fn remove(video_ids: &[String]) {
+    DATABASE
+        .write()
+        .unwrap()
+        .retain(|x| !video_ids.contains(&x.video_id));
+    write();
+}
+
+// Number of files scanned between two updates of the progress
+const SCAN_PROGRESS_STEP: usize = 100;
+
+/**
+ * Reads the metadata of every downloaded song, `progress` gets the number of files scanned so
+ * far from time to time and `None` once done
+ */
+pub fn scan(mut progress: impl FnMut(Option<usize>)) -> Vec<Video> {
+    let mut videos = HashSet::new();
+    if let Ok(entries) = std::fs::read_dir(CACHE_DIR.join("downloads")) {
+        for (i, path) in entries.flatten().map(|x| x.path()).enumerate() {
+            if i % SCAN_PROGRESS_STEP == 0 {
+                progress(Some(i));
+            }
+            if path.as_os_str().to_string_lossy().ends_with(".json") {
+                match std::fs::read_to_string(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|x| serde_json::from_str(&x).map_err(|e| e.to_string()))
+                {
+                    Ok(video) => {
+                        videos.insert(video);
+                    }
+                    Err(e) => report(format!("Can't read {}: {}", path.display(), e)),
+                }
+            }
+        }
+    }
+    progress(None);
+    let mut videos = videos.into_iter().collect::<Vec<_>>();
+    sort(&mut videos);
+    videos
+}
+
+/**
+ * Reads the database, the downloads are scanned when it is missing or corrupt so it can be
+ * written back safely
+ */
+pub fn read_or_scan() -> Vec<Video> {
+    read().unwrap_or_else(|| scan(|_| {}))
+}
+
+/**
+ * Sorts the songs of the database as set by `database_sort`, returns true if their order changed
+ */
+pub fn sort(videos: &mut [Video]) -> bool {
+    let before = videos
+        .iter()
+        .map(|x| x.video_id.clone())
+        .collect::<Vec<_>>();
+    match CONFIG.database_sort {
+        DatabaseSort::ArtistTitle => {
+            videos.sort_by_cached_key(|x| (x.author.to_lowercase(), x.title.to_lowercase()))
+        }
+        DatabaseSort::Title => videos.sort_by_cached_key(|x| x.title.to_lowercase()),
+        // The newest downloads first, the song is downloaded when its file is written
+        DatabaseSort::DateAdded => videos.sort_by_cached_key(|x| {
+            Reverse(
+                std::fs::metadata(CACHE_DIR.join(format!("downloads/{}.json", x.video_id)))
+                    .and_then(|x| x.modified())
+                    .unwrap_or(SystemTime::UNIX_EPOCH),
+            )
+        }),
+    }
+    videos.iter().map(|x| &x.video_id).ne(before.iter())
+}
+
+/**
+ * Reads a JSON file of the cache, a missing file gives `None` and a corrupt one is also reported
+ */
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(e) => e,
+        Err(e) if e.kind() == ErrorKind::NotFound => return None,
+        Err(e) => {
+            report(format!("Can't read {}: {}", path.display(), e));
+            return None;
+        }
+    };
+    match serde_json::from_str(&content) {
+        Ok(e) => Some(e),
+        Err(e) => {
+            report(format!(
+                "Skipping the corrupt file {}: {}",
+                path.display(),
+                e
+            ));
+            None
+        }
+    }
+}
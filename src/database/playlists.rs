@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use ytpapi::Video;
+
+use crate::{consts::CACHE_DIR, systems::logger::log_};
+
+/**
+ * A playlist saved from the queue, stored in `CACHE_DIR/playlists`
+ */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LocalPlaylist {
+    pub name: String,
+    pub videos: Vec<Video>,
+}
+
+// The playlist of the songs liked with `l` in the player
+pub const FAVORITES: &str = "Liked songs";
+
+/**
+ * Reads every saved playlist
+ */
+pub fn read_all() -> Vec<LocalPlaylist> {
+    let entries = match std::fs::read_dir(CACHE_DIR.join("playlists")) {
+        Ok(e) => e,
+        Err(_) => return Vec::new(),
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| super::read_json(&entry.path()))
+        .collect()
+}
+
+/**
+ * Saves a playlist, a playlist with the same name is replaced
+ */
+pub fn write(playlist: &LocalPlaylist) -> Result<(), String> {
+    // The name is kept in the file, the file name only has to be valid
+    let file_name: String = playlist
+        .name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' || c == ' ' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    std::fs::create_dir_all(CACHE_DIR.join("playlists"))
+        .and_then(|_| {
+            std::fs::write(
+                CACHE_DIR.join(format!("playlists/{}.json", file_name)),
+                serde_json::to_string(playlist)?,
+            )
+        })
+        .map_err(|e| {
+            log_(format!("Can't save the playlist {}: {}", playlist.name, e));
+            e.to_string()
+        })
+}
+
+/**
+ * Adds the song to the liked songs or removes it, returns the playlist to show it
+ */
+pub fn set_favorite(video: &Video, favorite: bool) -> Result<LocalPlaylist, String> {
+    let mut playlist = read_all()
+        .into_iter()
+        .find(|x| x.name == FAVORITES)
+        .unwrap_or_else(|| LocalPlaylist {
+            name: FAVORITES.to_owned(),
+            videos: Vec::new(),
+        });
+    playlist.videos.retain(|x| x.video_id != video.video_id);
+    if favorite {
+        playlist.videos.push(video.clone());
+    }
+    write(&playlist)?;
+    Ok(playlist)
+}
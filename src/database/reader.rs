@@ -1,18 +1,34 @@
 use std::io::{Cursor, Read};
 
 use varuint::ReadVarint;
-use ytpapi::Video;
+use ytpapi::{Chapter, Video};
 
-use crate::consts::CACHE_DIR;
+use crate::{consts::CACHE_DIR, systems::logger::log_};
+
+use super::writer::HEADER;
 
 /**
- * Reads the database
+ * Reads the database, `None` if it is missing or corrupt so the downloads are scanned instead
  */
 pub fn read() -> Option<Vec<Video>> {
     let mut buffer = Cursor::new(std::fs::read(CACHE_DIR.join("db.bin")).ok()?);
+    if !buffer.get_ref().starts_with(HEADER) {
+        log_("The database was written by another version, scanning the downloads");
+        return None;
+    }
+    buffer.set_position(HEADER.len() as u64);
     let mut videos = Vec::new();
     while buffer.get_mut().len() > buffer.position() as usize {
-        videos.push(read_video(&mut buffer)?);
+        match read_video(&mut buffer) {
+            Some(video) => videos.push(video),
+            None => {
+                log_(format!(
+                    "The database is corrupt after {} songs, scanning the downloads",
+                    videos.len()
+                ));
+                return None;
+            }
+        }
     }
     Some(videos)
 }
@@ -27,6 +43,14 @@ fn read_video(buffer: &mut Cursor<Vec<u8>>) -> Option<Video> {
         album: read_str(buffer)?,
         video_id: read_str(buffer)?,
         duration: read_str(buffer)?,
+        chapters: (0..read_u32(buffer)?)
+            .map(|_| {
+                Some(Chapter {
+                    start: read_u32(buffer)?,
+                    title: read_str(buffer)?,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?,
     })
 }
 /**
@@ -0,0 +1,101 @@
+use std::{
+    io::ErrorKind,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, TryLockError,
+    },
+    time::Duration,
+};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use ytpapi::Video;
+
+use crate::{consts::CACHE_DIR, systems::logger::log_};
+
+/**
+ * The song played when the player was last closed and where it was stopped
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResumeState {
+    pub video: Video,
+    // The time reached in the song, in seconds
+    pub position_secs: u64,
+}
+
+// The song being played, `None` once the queue ended
+static RESUME: Lazy<Mutex<Option<ResumeState>>> = Lazy::new(|| Mutex::new(None));
+
+// Set when the song or its position changed since they were written
+static DIRTY: AtomicBool = AtomicBool::new(false);
+
+/**
+ * Reads the song to resume, written by the last session
+ */
+pub fn read() -> Option<ResumeState> {
+    super::read_json(&CACHE_DIR.join("resume.json"))
+}
+
+/**
+ * Changes the song being played and writes it right away, so a crash still resumes it
+ */
+pub fn set(video: Option<&Video>) {
+    *RESUME.lock().unwrap() = video.map(|video| ResumeState {
+        video: video.clone(),
+        position_secs: 0,
+    });
+    DIRTY.store(true, Ordering::SeqCst);
+    flush();
+}
+
+/**
+ * Updates the time reached in the song being played, written by the next flush
+ */
+pub fn set_position(video_id: &str, position: Duration) {
+    let mut resume = RESUME.lock().unwrap();
+    if let Some(state) = resume.as_mut().filter(|x| x.video.video_id == video_id) {
+        if state.position_secs != position.as_secs() {
+            state.position_secs = position.as_secs();
+            DIRTY.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+/**
+ * Writes the song to resume if it changed, the file is removed once nothing is played
+ */
+pub fn flush() {
+    if !DIRTY.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    let path = CACHE_DIR.join("resume.json");
+    // Skipped while the lock is held, it may be held by the thread running the panic hook
+    let state = match RESUME.try_lock() {
+        Ok(e) => e.clone(),
+        Err(TryLockError::Poisoned(e)) => e.into_inner().clone(),
+        Err(TryLockError::WouldBlock) => {
+            DIRTY.store(true, Ordering::SeqCst);
+            return;
+        }
+    };
+    let state = match state {
+        Some(e) => e,
+        None => {
+            match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != ErrorKind::NotFound => {
+                    log_(format!("Can't remove {}: {}", path.display(), e));
+                }
+                _ => {}
+            }
+            return;
+        }
+    };
+    match serde_json::to_string(&state) {
+        Ok(e) => {
+            if let Err(e) = std::fs::write(&path, e) {
+                log_(format!("Can't write the song to resume: {}", e));
+            }
+        }
+        Err(e) => log_(format!("Can't serialize the song to resume: {}", e)),
+    }
+}
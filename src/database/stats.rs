@@ -0,0 +1,118 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        RwLock, TryLockError,
+    },
+};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::CONFIG, consts::CACHE_DIR, systems::logger::log_};
+
+/**
+ * How a song was listened to
+ */
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct TrackStats {
+    // Number of times the song was skipped before counting as played
+    pub skips: u32,
+    // Number of times the song counted as played, see `played_threshold_secs` in the config
+    pub completions: u32,
+    // 1 if the song is liked, -1 if it is disliked, set with `l` and `d` in the player
+    #[serde(default)]
+    pub rating: i8,
+}
+
+// The stats of every song that was skipped or played, by video id
+static STATS: Lazy<RwLock<HashMap<String, TrackStats>>> =
+    Lazy::new(|| RwLock::new(super::read_json(&CACHE_DIR.join("stats.json")).unwrap_or_default()));
+
+pub fn get(video_id: &str) -> TrackStats {
+    STATS
+        .read()
+        .unwrap()
+        .get(video_id)
+        .copied()
+        .unwrap_or_default()
+}
+
+pub fn record_skip(video_id: &str) {
+    STATS
+        .write()
+        .unwrap()
+        .entry(video_id.to_owned())
+        .or_default()
+        .skips += 1;
+    save();
+}
+
+pub fn record_completion(video_id: &str) {
+    STATS
+        .write()
+        .unwrap()
+        .entry(video_id.to_owned())
+        .or_default()
+        .completions += 1;
+    save();
+}
+
+pub fn set_rating(video_id: &str, rating: i8) {
+    STATS
+        .write()
+        .unwrap()
+        .entry(video_id.to_owned())
+        .or_default()
+        .rating = rating;
+    save();
+}
+
+pub fn is_disliked(video_id: &str) -> bool {
+    get(video_id).rating < 0
+}
+
+/**
+ * The lower the score the more the song is liked, used to put the skipped songs last.
+ * The liked songs come before every other song and the disliked ones after them.
+ */
+pub fn skip_score(video_id: &str) -> i64 {
+    let stats = get(video_id);
+    stats.skips as i64 - stats.completions as i64 - stats.rating as i64 * 1_000_000
+}
+
+// Set when the stats changed since they were written
+static DIRTY: AtomicBool = AtomicBool::new(false);
+
+fn save() {
+    DIRTY.store(true, Ordering::SeqCst);
+    if CONFIG.persist_interval_secs == 0 {
+        flush();
+    }
+}
+
+/**
+ * Writes the stats to the disk if they changed
+ */
+pub fn flush() {
+    if !DIRTY.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    // Waiting here would hang the panic hook if the panicking thread holds the lock
+    let content = match STATS.try_read() {
+        Ok(e) => serde_json::to_string(&*e),
+        Err(TryLockError::Poisoned(e)) => serde_json::to_string(&*e.into_inner()),
+        Err(TryLockError::WouldBlock) => {
+            DIRTY.store(true, Ordering::SeqCst);
+            return;
+        }
+    };
+    match content {
+        Ok(e) => {
+            if let Err(e) = std::fs::write(CACHE_DIR.join("stats.json"), e) {
+                log_(format!("Can't write the stats: {}", e));
+            }
+        }
+        Err(e) => log_(format!("Can't serialize the stats: {}", e)),
+    }
+}
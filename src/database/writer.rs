@@ -1,48 +1,67 @@
-use std::{fs::OpenOptions, io::Write};
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+};
 
 use varuint::WriteVarint;
 use ytpapi::Video;
 
-use crate::consts::CACHE_DIR;
+use crate::{consts::CACHE_DIR, systems::logger::log_};
+
+// Starts the database file, bumped when the layout of the songs changes so an older file is
+// scanned again instead of being misread
+pub const HEADER: &[u8] = b"YTMDB\x02";
 
 /**
- * Writes the database to the disk
+ * Writes the database to the disk, a failure is logged and the downloads are scanned again on the
+ * next start
  */
 pub fn write() {
     let db = super::DATABASE.read().unwrap();
-    let mut file = OpenOptions::new()
+    let result = OpenOptions::new()
         .write(true)
         .append(false)
+        .truncate(true)
         .create(true)
         .open(CACHE_DIR.join("db.bin"))
-        .unwrap();
-    for video in db.iter() {
-        write_video(&mut file, video)
+        .and_then(|mut file| {
+            file.write_all(HEADER)?;
+            db.iter()
+                .try_for_each(|video| write_video(&mut file, video))
+        });
+    if let Err(e) = result {
+        log_(format!("Can't write the database: {}", e));
     }
 }
 
 /**
  * Writes a video to a file
  */
-pub fn write_video(buffer: &mut impl Write, video: &Video) {
-    write_str(buffer, &video.title);
-    write_str(buffer, &video.author);
-    write_str(buffer, &video.album);
-    write_str(buffer, &video.video_id);
-    write_str(buffer, &video.duration);
+pub fn write_video(buffer: &mut impl Write, video: &Video) -> io::Result<()> {
+    write_str(buffer, &video.title)?;
+    write_str(buffer, &video.author)?;
+    write_str(buffer, &video.album)?;
+    write_str(buffer, &video.video_id)?;
+    write_str(buffer, &video.duration)?;
+    write_u32(buffer, video.chapters.len() as u32)?;
+    for chapter in video.chapters.iter() {
+        write_u32(buffer, chapter.start)?;
+        write_str(buffer, &chapter.title)?;
+    }
+    Ok(())
 }
 
 /**
  * Writes a string from the cursor
  */
-fn write_str(cursor: &mut impl Write, value: &str) {
-    write_u32(cursor, value.len() as u32);
-    cursor.write_all(value.as_bytes()).unwrap();
+fn write_str(cursor: &mut impl Write, value: &str) -> io::Result<()> {
+    write_u32(cursor, value.len() as u32)?;
+    cursor.write_all(value.as_bytes())
 }
 
 /**
  * Writes a u32 from the cursor
  */
-fn write_u32(cursor: &mut impl Write, value: u32) {
-    cursor.write_varint(value).unwrap();
+fn write_u32(cursor: &mut impl Write, value: u32) -> io::Result<()> {
+    cursor.write_varint(value).map(|_| ())
 }
@@ -1,6 +1,70 @@
+use std::{fmt::Debug, sync::Mutex};
+
 use flume::Sender;
+use once_cell::sync::OnceCell;
+
+use crate::{
+    systems::logger::log_,
+    term::{ManagerMessage, Screens},
+};
+
+/**
+ * Sending on a channel whose receiver is gone, which happens while the app quits
+ */
+pub trait SendOrLog<T> {
+    /**
+     * Sends the message, it is logged and dropped if the receiver is gone
+     */
+    fn send_or_log(&self, message: T);
+}
+
+impl<T: Debug> SendOrLog<T> for Sender<T> {
+    fn send_or_log(&self, message: T) {
+        if let Err(e) = self.send(message) {
+            log_(format!(
+                "Message dropped, the receiver is closed: {:?}",
+                e.0
+            ));
+        }
+    }
+}
+
+// Where the reported errors are sent, set once the channel of the manager exists
+static REPORTER: OnceCell<Sender<ManagerMessage>> = OnceCell::new();
+
+// The errors reported before `REPORTER` was set
+static PENDING: Mutex<Vec<String>> = Mutex::new(Vec::new());
 
-use crate::term::{ManagerMessage, Screens};
+/**
+ * Logs an error and shows it on the error screen, the errors found before the manager exists are
+ * shown once it does
+ */
+pub fn report(message: String) {
+    log_(message.clone());
+    let mut pending = PENDING.lock().unwrap();
+    match REPORTER.get() {
+        Some(updater) => send_error(updater, message),
+        None => pending.push(message),
+    }
+}
+
+/**
+ * Sends the reported errors to the manager from now on, with the ones reported before
+ */
+pub fn set_reporter(updater: Sender<ManagerMessage>) {
+    let mut pending = PENDING.lock().unwrap();
+    for message in pending.drain(..) {
+        send_error(&updater, message);
+    }
+    let _ = REPORTER.set(updater);
+}
+
+fn send_error(updater: &Sender<ManagerMessage>, message: String) {
+    updater.send_or_log(ManagerMessage::PassTo(
+        Screens::DeviceLost,
+        Box::new(ManagerMessage::Error(message)),
+    ));
+}
 
 /**
  * Utils to handle errors
@@ -16,12 +80,10 @@ where
     match a {
         Ok(e) => Some(e),
         Err(a) => {
-            updater
-                .send(ManagerMessage::PassTo(
-                    Screens::DeviceLost,
-                    Box::new(ManagerMessage::Error(format!("{} {}", error_type, a))),
-                ))
-                .unwrap();
+            updater.send_or_log(ManagerMessage::PassTo(
+                Screens::DeviceLost,
+                Box::new(ManagerMessage::Error(format!("{} {}", error_type, a))),
+            ));
             None
         }
     }
@@ -0,0 +1,34 @@
+use std::time::Duration;
+
+use crate::{
+    config::CONFIG,
+    database::{resume, stats},
+    settings,
+    systems::logger::log_,
+};
+
+/**
+ * Spawns the task writing the settings, the stats and the song to resume that changed every `persist_interval_secs`
+ * seconds, so the changes made in a row are written only once
+ */
+pub fn start() {
+    if CONFIG.persist_interval_secs == 0 {
+        return;
+    }
+    tokio::task::spawn(async {
+        log_("Flusher task on");
+        loop {
+            tokio::time::sleep(Duration::from_secs(CONFIG.persist_interval_secs)).await;
+            flush();
+        }
+    });
+}
+
+/**
+ * Writes everything that changed since the last flush, called when quitting and on panic
+ */
+pub fn flush() {
+    settings::flush();
+    stats::flush();
+    resume::flush();
+}
@@ -0,0 +1,61 @@
+/**
+ * Returns the id of a video given as is or as a YouTube URL
+ */
+pub fn video_id(text: &str) -> Option<String> {
+    let text = text.trim();
+    // The short links carry the id in their path, before the query such as `?si=` or `?t=`
+    let id = if let Some((_, path)) = text.split_once("youtu.be/") {
+        path.split(['?', '#']).next().unwrap_or(path)
+    } else if let Some((_, query)) = text.split_once('?') {
+        query.split('&').find_map(|x| x.strip_prefix("v="))?
+    } else {
+        text
+    };
+    // The ids are 11 characters of the URL safe base64 alphabet
+    (id.len() == 11
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+    .then(|| id.to_owned())
+}
+
+/**
+ * Returns true if the text is a YouTube link rather than a search
+ */
+pub fn is_link(text: &str) -> bool {
+    let text = text.trim();
+    (text.contains("youtube.com/") || text.contains("youtu.be/")) && video_id(text).is_some()
+}
+
+/**
+ * Returns the time in seconds given by the `t` parameter of a link, as `t=90`, `t=90s` or
+ * `t=1m30s`
+ */
+pub fn start_offset(link: &str) -> Option<u64> {
+    let (_, query) = link.trim().split_once('?')?;
+    let value = query.split(['&', '#']).find_map(|x| x.strip_prefix("t="))?;
+    if let Ok(seconds) = value.parse() {
+        return Some(seconds);
+    }
+    let mut seconds = 0;
+    let mut number = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        seconds += number.parse::<u64>().ok()? * unit;
+        number.clear();
+    }
+    // Only a number without a unit is left
+    if !number.is_empty() {
+        return None;
+    }
+    Some(seconds)
+}
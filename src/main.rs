@@ -1,20 +1,37 @@
+use cli::{Args, USAGE};
+use config::{ResumeSession, StdinEofAction, CONFIG};
 use consts::CACHE_DIR;
+use flume::{Receiver, Sender};
 use rustube::Error;
 use term::{Manager, ManagerMessage, Screens};
 
-use std::collections::HashSet;
-use std::{path::PathBuf, str::FromStr, sync::Arc};
-use systems::download::downloader;
-use systems::player::player_system;
+use std::io::IsTerminal;
+use std::time::Duration;
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+use systems::download::{downloader, replace_queue, start_task_unary};
+use systems::player::{player_system, song_path, PlayerState, LOCAL_FILE_PREFIX};
 
-use ytpapi::{Video, YTApi};
+use ytpapi::{Playlist, Video, YTApi};
 
 use crate::consts::HEADER_TUTORIAL;
+use crate::errors::{report, SendOrLog};
 use crate::systems::logger::log_;
 
+mod api;
+mod cli;
+mod config;
 mod consts;
 mod database;
 mod errors;
+mod flusher;
+mod links;
+mod maintenance;
+mod settings;
+mod setup;
 mod systems;
 mod term;
 
@@ -38,28 +55,110 @@ pub enum SoundAction {
     RestartPlayer,
     Plus,
     Minus,
+    // Sets the volume to 0, or back to the volume before the mute
+    ToggleMute,
     Previous(usize),
     Forward,
     Backward,
+    // Moves in the current song by this number of seconds, stopping at its start and its end
+    Seek(i64),
+    // Moves to this time of the current song, stopping at its end
+    SeekTo(Duration),
+    ChapterForward,
+    ChapterBackward,
     Next(usize),
     PlayVideo(Video),
+    // Plays the video right after the current one and then continues with the queue
     PlayVideoUnary(Video),
+    // Plays the song of the last session from the time it was stopped at
+    Resume(Video, Duration),
+    // Deletes the downloaded files of the video unless it is being played
+    RemoveFromCache(Video),
+    // Removes the song at this index of the queue and cancels its download
+    RemoveFromQueue(usize),
+    // Moves a song of the queue to another index
+    MoveInQueue { from: usize, to: usize },
+    SetVolume(i32),
+    // An error of the downloader shown to the user
+    DownloadError(String),
+    // Plays the results of a search page by page, `None` stops the station
+    Station(Option<String>),
+    // Every song of the download queue is downloaded
+    DownloadsFinished,
+    // Removes the oldest downloads if they take more than `max_cache_mb`
+    LimitCache,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
-    std::fs::write("log.txt", "# YTerMusic log file\n\n").unwrap();
-    std::fs::create_dir_all(CACHE_DIR.join("downloads")).unwrap();
-    if !PathBuf::from_str("headers.txt").unwrap().exists() {
-        println!("The `headers.txt` file is not present in the root directory.");
-        println!("{}", HEADER_TUTORIAL);
+    let args = match Args::parse() {
+        Ok(e) => e,
+        Err(e) => {
+            println!("{}\n\n{}", e, USAGE);
+            return Ok(());
+        }
+    };
+    if args.help {
+        println!("{}", USAGE);
         return Ok(());
     }
-    if !std::fs::read_to_string("headers.txt")
-        .unwrap()
-        .contains("Cookie: ")
-    {
-        println!("The `headers.txt` file is not configured correctly.");
+    if let Err(e) = config::load() {
+        println!("{}", e);
+        return Ok(());
+    }
+    let file = match args.file.as_deref().map(local_file).transpose() {
+        Ok(e) => e,
+        Err(e) => {
+            println!("{}", e);
+            return Ok(());
+        }
+    };
+    if let Err(e) = std::fs::write("log.txt", "# YTerMusic log file\n\n") {
+        println!("Can't write `log.txt` in the current directory: {}", e);
+        return Ok(());
+    }
+    if let Err(e) = std::fs::create_dir_all(CACHE_DIR.join("downloads")) {
+        println!("Can't create the cache in {}: {}", CACHE_DIR.display(), e);
+        return Ok(());
+    }
+    if args.verify_cache {
+        maintenance::verify_cache(args.fix).await;
+        return Ok(());
+    }
+    if args.find_duplicates {
+        maintenance::find_duplicates(args.fix);
+        return Ok(());
+    }
+    if let Some(path) = &args.export {
+        maintenance::export(PathBuf::from(path), args.export_format).await;
+        return Ok(());
+    }
+    if let Some(path) = &args.import {
+        maintenance::import(PathBuf::from(path), args.download).await;
+        return Ok(());
+    }
+    if args.benchmark {
+        maintenance::benchmark().await;
+        return Ok(());
+    }
+    if !PathBuf::from_str("headers.txt").unwrap().exists() {
+        // The setup needs a user typing in the terminal, the tutorial is printed otherwise
+        let interactive = CONFIG.setup_wizard && std::io::stdin().is_terminal();
+        if !interactive || !setup::run().await {
+            println!("The `headers.txt` file is not present in the root directory.");
+            println!("{}", HEADER_TUTORIAL);
+            return Ok(());
+        }
+    }
+    let headers = match std::fs::read_to_string("headers.txt") {
+        Ok(e) => e,
+        Err(e) => {
+            println!("Can't read `headers.txt`: {}", e);
+            return Ok(());
+        }
+    };
+    if let Err(e) = setup::check_headers(&headers) {
+        println!("{}", e);
         println!("{}", HEADER_TUTORIAL);
         return Ok(());
     }
@@ -68,131 +167,401 @@ async fn main() -> Result<(), Error> {
 
     // Spawn the clean task
     let (updater_s, updater_r) = flume::unbounded::<ManagerMessage>();
+    // The errors found from now on are shown on the error screen
+    errors::set_reporter(updater_s.clone());
     std::thread::spawn(move || {
         log_("Cleaning service on");
         clean();
+        if CONFIG.use_gain_tags {
+            systems::tags::store_missing_gains();
+        }
+        history::rotate();
     });
     let updater_s = Arc::new(updater_s);
     // Spawn the player task
     let (sa, player) = player_system(updater_s.clone());
     // Spawn the downloader task
     downloader(sa.clone());
+    flusher::start();
+    #[cfg(unix)]
+    systems::control::start(sa.clone());
+    #[cfg(all(feature = "audio-focus", target_os = "linux"))]
+    systems::focus::start(sa.clone());
+    if let Some(video) = file {
+        // The file is played as is, without going through the downloader
+        sa.send_or_log(SoundAction::PlayVideoUnary(video));
+        updater_s.send_or_log(ManagerMessage::ChangeState(Screens::MusicPlayer));
+    } else if CONFIG.resume_session != ResumeSession::Off
+        && args.play.is_none()
+        && args.playlist.is_none()
     {
+        if let Some(state) = resume::read() {
+            // The last playlist is still shown in the playlist selector otherwise
+            if song_path(&state.video.video_id).exists() {
+                sa.send_or_log(SoundAction::Resume(
+                    state.video,
+                    Duration::from_secs(state.position_secs),
+                ));
+            } else {
+                log_(format!(
+                    "Can't resume {}, it was removed from the downloads",
+                    state.video.title
+                ));
+            }
+        }
+    }
+    if CONFIG.show_last_playlist {
         let updater_s = updater_s.clone();
         // Spawn playlist updater task
         tokio::task::spawn(async move {
             log_("Last playlist task on");
-            let playlist = std::fs::read_to_string(CACHE_DIR.join("last-playlist.json")).ok()?;
-            let mut playlist: (String, Vec<Video>) = serde_json::from_str(&playlist).ok()?;
+            let mut playlist = term::playlist::last_playlist()?;
             if !playlist.0.starts_with("Last playlist: ") {
                 playlist.0 = format!("Last playlist: {}", playlist.0);
             }
-            updater_s
-                .send(ManagerMessage::AddElementToChooser(playlist).pass_to(Screens::Playlist))
-                .unwrap();
+            updater_s.send_or_log(
+                ManagerMessage::AddElementToChooser(playlist).pass_to(Screens::Playlist),
+            );
             Some(())
         });
     }
     {
         let updater_s = updater_s.clone();
+        let sa = sa.clone();
+        let args = args.clone();
         // Spawn the API task
         tokio::task::spawn(async move {
             log_("API task on");
-            match YTApi::from_header_file(PathBuf::from_str("headers.txt").unwrap().as_path()).await
-            {
+            // Show the cached playlists while the API is connecting
+            for playlist in browse_cache::read_all() {
+                updater_s.send_or_log(
+                    ManagerMessage::AddElementToChooser((playlist.name, playlist.videos))
+                        .pass_to(Screens::Playlist),
+                );
+            }
+            match setup::connect(Path::new("headers.txt")).await {
                 Ok(api) => {
                     let api = Arc::new(api);
-                    for playlist in api.playlists() {
-                        let updater_s = updater_s.clone();
-                        let playlist = playlist.clone();
-                        let api = api.clone();
-                        tokio::task::spawn(async move {
-                            match api.browse_playlist(&playlist.browse_id).await {
-                                Ok(videos) => {
-                                    updater_s
-                                        .send(
-                                            ManagerMessage::AddElementToChooser((
-                                                format!(
-                                                    "{} ({})",
-                                                    playlist.name, playlist.subtitle
-                                                ),
-                                                videos,
-                                            ))
-                                            .pass_to(Screens::Playlist),
-                                        )
-                                        .unwrap();
-                                }
-                                Err(e) => {
-                                    log_(format!("{:?}", e));
+                    let _ = api::API.set(api.clone());
+                    if let Some(query) = &args.play {
+                        // Searched like in the search screen so the same song comes first
+                        match api::retried(|| api.search_page_with_type(query, CONFIG.search_type))
+                            .await
+                        {
+                            Ok((videos, _)) => {
+                                if let Some(video) = videos.into_iter().next() {
+                                    start_task_unary(sa.clone(), video);
+                                    updater_s.send_or_log(ManagerMessage::ChangeState(
+                                        Screens::MusicPlayer,
+                                    ));
+                                } else {
+                                    log_(format!("No search result for `{}`", query));
                                 }
                             }
-                        });
+                            Err(e) => {
+                                api::report_error(&e);
+                            }
+                        }
+                    }
+                    for playlist in api.playlists() {
+                        let play = args
+                            .is_requested_playlist(&playlist.name)
+                            .then(|| sa.clone());
+                        fetch_playlist(
+                            api.clone(),
+                            playlist.clone(),
+                            updater_s.clone(),
+                            play,
+                            false,
+                        );
                     }
                 }
                 Err(e) => {
-                    log_(format!("{:?}", e));
+                    api::report_error(&e);
                 }
             }
         });
     }
     {
         let updater_s = updater_s.clone();
+        let sa = sa.clone();
+        let play = args.is_requested_playlist("Local musics");
         // Spawn the database getter task
         tokio::task::spawn(async move {
             log_("Database getter task on");
-            if let Some(e) = read() {
+            if let Some(mut e) = read() {
+                // The database is written again so it stays in the order it is shown in
+                let sorted = sort(&mut e);
                 *DATABASE.write().unwrap() = e.clone();
-
-                updater_s
-                    .send(
-                        ManagerMessage::AddElementToChooser(("Local musics".to_owned(), e))
-                            .pass_to(Screens::Playlist),
-                    )
-                    .unwrap();
-            } else {
-                let mut videos = HashSet::new();
-                for files in std::fs::read_dir(CACHE_DIR.join("downloads")).unwrap() {
-                    let path = files.unwrap().path();
-                    if path.as_os_str().to_string_lossy().ends_with(".json") {
-                        let video =
-                            serde_json::from_str(std::fs::read_to_string(path).unwrap().as_str())
-                                .unwrap();
-                        videos.insert(video);
-                    }
+                if sorted {
+                    write();
                 }
 
-                let k = videos.into_iter().collect::<Vec<_>>();
+                if play {
+                    replace_queue(&e, sa.clone());
+                    updater_s.send_or_log(ManagerMessage::ChangeState(Screens::MusicPlayer));
+                }
+                updater_s.send_or_log(
+                    ManagerMessage::AddElementToChooser(("Local musics".to_owned(), e))
+                        .pass_to(Screens::Playlist),
+                );
+            } else {
+                let k = scan_downloads(&updater_s);
 
                 *DATABASE.write().unwrap() = k.clone();
 
-                updater_s
-                    .send(
-                        ManagerMessage::AddElementToChooser(("Local musics".to_owned(), k))
-                            .pass_to(Screens::Playlist),
-                    )
-                    .unwrap();
+                if play {
+                    replace_queue(&k, sa.clone());
+                    updater_s.send_or_log(ManagerMessage::ChangeState(Screens::MusicPlayer));
+                }
+                updater_s.send_or_log(
+                    ManagerMessage::AddElementToChooser(("Local musics".to_owned(), k))
+                        .pass_to(Screens::Playlist),
+                );
                 write();
             }
+            send_saved_playlists(&updater_s);
+            // After the songs of the database are known
+            sa.send_or_log(SoundAction::LimitCache);
         });
     }
 
+    if args.no_ui {
+        log_("Running without the manager");
+        run_headless(player, &updater_r).await;
+    }
     log_("Running the manager");
-    let mut manager = Manager::new(sa, player).await;
+    let mut manager = Manager::new(sa, player, CONFIG.start_screen.into()).await;
     manager.run(&updater_r).unwrap();
+    flusher::flush();
+    #[cfg(unix)]
+    systems::control::cleanup();
     Ok(())
 }
 
+/**
+ * Keeps the player running without the terminal interface, errors are printed instead of displayed.
+ * It is controlled by the commands read on the standard input and stops on `q` or when the process
+ * is interrupted.
+ */
+async fn run_headless(mut player: PlayerState, updater: &Receiver<ManagerMessage>) -> ! {
+    let commands = read_commands();
+    loop {
+        while let Ok(command) = commands.try_recv() {
+            match command {
+                Some(action) => player.apply_sound_action(action),
+                None => {
+                    flusher::flush();
+                    #[cfg(unix)]
+                    systems::control::cleanup();
+                    std::process::exit(0);
+                }
+            }
+        }
+        while let Ok(e) = updater.try_recv() {
+            match e {
+                ManagerMessage::Toast(e) => println!("{}", e),
+                ManagerMessage::PassTo(_, e) => {
+                    if let ManagerMessage::Error(e) = *e {
+                        eprintln!("{}", e);
+                    }
+                }
+                _ => (),
+            }
+        }
+        player.update();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/**
+ * Reads the commands of `--no-ui` on the standard input in a thread, one per line. `None` is sent
+ * to quit.
+ */
+fn read_commands() -> Receiver<Option<SoundAction>> {
+    let (sender, receiver) = flume::unbounded();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines() {
+            let line = match line {
+                Ok(e) => e,
+                Err(e) => {
+                    log_(format!("Can't read the standard input: {}", e));
+                    break;
+                }
+            };
+            let command = match line.trim() {
+                "" => continue,
+                "p" => Some(SoundAction::PlayPause),
+                "n" => Some(SoundAction::Next(1)),
+                "b" => Some(SoundAction::Previous(1)),
+                "+" => Some(SoundAction::Plus),
+                "-" => Some(SoundAction::Minus),
+                "q" => None,
+                other => {
+                    println!("Unknown command `{}`, use p, n, b, +, - or q", other);
+                    continue;
+                }
+            };
+            if sender.send(command).is_err() {
+                return;
+            }
+        }
+        log_("The standard input is closed");
+        if CONFIG.on_stdin_eof == StdinEofAction::Quit {
+            let _ = sender.send(None);
+        }
+    });
+    receiver
+}
+
+/**
+ * Creates the video played for a file given with `--file`, fails if the file can't be decoded
+ */
+fn local_file(path: &str) -> Result<Video, String> {
+    let path = std::fs::canonicalize(path).map_err(|e| format!("Can't open {}: {}", path, e))?;
+    if let Err(e) = player::Player::open(&path) {
+        return Err(format!(
+            "Can't play {}, the format isn't supported: {}",
+            path.display(),
+            e
+        ));
+    }
+    Ok(Video {
+        title: path
+            .file_stem()
+            .map(|x| x.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        author: "Local file".to_owned(),
+        album: String::new(),
+        video_id: format!("{}{}", LOCAL_FILE_PREFIX, path.display()),
+        duration: String::new(),
+        chapters: Vec::new(),
+    })
+}
+
+/**
+ * Fetches the songs of a playlist and adds it to the chooser.
+ * The cached version is used if it is recent enough, unless `force` is set.
+ * If `play` is set, the queue is replaced by the songs of the playlist.
+ */
+fn fetch_playlist(
+    api: Arc<YTApi>,
+    playlist: Playlist,
+    updater_s: Arc<Sender<ManagerMessage>>,
+    play: Option<Arc<Sender<SoundAction>>>,
+    force: bool,
+) {
+    tokio::task::spawn(async move {
+        let cached = browse_cache::read(&playlist.browse_id);
+        let fresh = !force && matches!(cached, Some((_, true)));
+        let videos = match cached {
+            Some((ref e, _)) if fresh => e.videos.clone(),
+            _ => match api::retried(|| api.browse_playlist(&playlist.browse_id)).await {
+                Ok(videos) => videos,
+                Err(e) => {
+                    api::report_error(&e);
+                    updater_s.send_or_log(ManagerMessage::Toast(format!(
+                        "Can't fetch the playlist {}",
+                        playlist.name
+                    )));
+                    return;
+                }
+            },
+        };
+        if let Some(sa) = play {
+            replace_queue(&videos, sa);
+            updater_s.send_or_log(ManagerMessage::ChangeState(Screens::MusicPlayer));
+        }
+        if fresh {
+            return;
+        }
+        let browse_id = playlist.browse_id;
+        let playlist = browse_cache::CachedPlaylist {
+            name: format!("{} ({})", playlist.name, playlist.subtitle),
+            videos,
+        };
+        browse_cache::write(&browse_id, &playlist);
+        // The chooser already shows the cached version if nothing changed
+        if cached
+            .map(|(e, _)| e.videos != playlist.videos)
+            .unwrap_or(true)
+        {
+            updater_s.send_or_log(
+                ManagerMessage::AddElementToChooser((playlist.name, playlist.videos))
+                    .pass_to(Screens::Playlist),
+            );
+        }
+    });
+}
+
+/**
+ * Reads the metadata of every downloaded song, the progress is shown in the chooser
+ */
+fn scan_downloads(updater_s: &Sender<ManagerMessage>) -> Vec<Video> {
+    scan(|progress| {
+        updater_s.send_or_log(ManagerMessage::ScanProgress(progress).pass_to(Screens::Playlist));
+    })
+}
+
+/**
+ * Adds the playlists saved from the queue to the chooser
+ */
+fn send_saved_playlists(updater_s: &Sender<ManagerMessage>) {
+    for playlist in playlists::read_all() {
+        updater_s.send_or_log(
+            ManagerMessage::AddElementToChooser((playlist.name, playlist.videos))
+                .pass_to(Screens::Playlist),
+        );
+    }
+}
+
+/**
+ * Scans the downloads again and fetches the playlists without using the cache
+ */
+pub fn refresh(updater_s: Arc<Sender<ManagerMessage>>) {
+    tokio::task::spawn(async move {
+        log_("Refreshing the database and the playlists");
+        let videos = scan_downloads(&updater_s);
+        *DATABASE.write().unwrap() = videos.clone();
+        write();
+        updater_s.send_or_log(
+            ManagerMessage::AddElementToChooser(("Local musics".to_owned(), videos))
+                .pass_to(Screens::Playlist),
+        );
+        send_saved_playlists(&updater_s);
+        // Connecting again to get the playlists created since the start
+        match setup::connect(Path::new("headers.txt")).await {
+            Ok(api) => {
+                api::SESSION_EXPIRED.store(false, std::sync::atomic::Ordering::SeqCst);
+                let api = Arc::new(api);
+                for playlist in api.playlists() {
+                    fetch_playlist(api.clone(), playlist.clone(), updater_s.clone(), None, true);
+                }
+            }
+            Err(e) => api::report_error(&e),
+        }
+    });
+}
+
 /**
  * This function is called on start to clean the database and the files that are incompletly downloaded due to a crash.
  */
 fn clean() {
-    for i in std::fs::read_dir(CACHE_DIR.join("downloads")).unwrap() {
-        let path = i.unwrap().path();
+    let entries = match std::fs::read_dir(CACHE_DIR.join("downloads")) {
+        Ok(e) => e,
+        Err(e) => {
+            report(format!("Can't clean the downloads: {}", e));
+            return;
+        }
+    };
+    for path in entries.flatten().map(|x| x.path()) {
         if path.ends_with(".mp4") {
             let mut path1 = path.clone();
             path1.set_extension("json");
             if !path1.exists() {
-                std::fs::remove_file(&path).unwrap();
+                if let Err(e) = std::fs::remove_file(&path) {
+                    report(format!("Can't remove {}: {}", path.display(), e));
+                }
             }
         }
     }
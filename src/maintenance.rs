@@ -0,0 +1,592 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+use ytpapi::{Video, YTApi};
+
+use crate::{
+    cli::ExportFormat,
+    config::CONFIG,
+    consts::CACHE_DIR,
+    database::playlists::{self, LocalPlaylist},
+    links,
+    systems::{download, stream, thumbnails},
+    SoundAction, DATABASE,
+};
+
+/**
+ * Checks that every cached song can be decoded and prints a summary.
+ * When `fix` is set, the broken songs are removed from the cache and downloaded again.
+ */
+pub async fn verify_cache(fix: bool) {
+    let mut healthy = 0;
+    let mut broken: Vec<Video> = Vec::new();
+    let mut unreadable = 0;
+    let entries = match std::fs::read_dir(CACHE_DIR.join("downloads")) {
+        Ok(e) => e,
+        Err(e) => {
+            println!("Can't read the download directory: {}", e);
+            return;
+        }
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().map(|x| x != "json").unwrap_or(true) {
+            continue;
+        }
+        let video: Video = match std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|x| serde_json::from_str(&x).map_err(|e| e.to_string()))
+        {
+            Ok(e) => e,
+            Err(e) => {
+                println!("Unreadable metadata {}: {}", path.display(), e);
+                unreadable += 1;
+                continue;
+            }
+        };
+        match check_audio(&path.with_extension("mp4")) {
+            Ok(()) => healthy += 1,
+            Err(e) => {
+                println!(
+                    "Broken {} | {} ({}): {}",
+                    video.author, video.title, video.video_id, e
+                );
+                broken.push(video);
+            }
+        }
+    }
+    println!(
+        "{} healthy, {} broken, {} with unreadable metadata",
+        healthy,
+        broken.len(),
+        unreadable
+    );
+    if !fix || broken.is_empty() {
+        return;
+    }
+
+    // Remove the broken songs from the cache and the database
+    *DATABASE.write().unwrap() = crate::read_or_scan();
+    for video in broken.iter() {
+        for extension in ["mp4", "json"] {
+            let path = CACHE_DIR.join(format!("downloads/{}.{}", video.video_id, extension));
+            if path.exists() {
+                if let Err(e) = std::fs::remove_file(&path) {
+                    println!("Can't remove {}: {}", path.display(), e);
+                }
+            }
+        }
+        DATABASE
+            .write()
+            .unwrap()
+            .retain(|x| x.video_id != video.video_id);
+    }
+    crate::write();
+
+    let count = broken.len();
+    let downloaded = download_all(broken).await;
+    println!("{}/{} broken songs downloaded again", downloaded, count);
+}
+
+/**
+ * Downloads the songs with the usual download workers and waits for them, returns the number of
+ * songs downloaded
+ */
+async fn download_all(videos: Vec<Video>) -> usize {
+    let (sender, receiver) = flume::unbounded::<SoundAction>();
+    let sender = Arc::new(sender);
+    let count = videos.len();
+    for video in videos {
+        download::add(video, &sender);
+    }
+    download::downloader(sender.clone());
+    let mut downloaded = 0;
+    while downloaded < count {
+        match tokio::time::timeout(Duration::from_secs(1), receiver.recv_async()).await {
+            Ok(Ok(SoundAction::PlayVideo(video))) => {
+                println!("Downloaded {} | {}", video.author, video.title);
+                downloaded += 1;
+            }
+            Ok(_) => {}
+            Err(_) => {
+                if download::is_idle() {
+                    break;
+                }
+            }
+        }
+    }
+    downloaded
+}
+
+/**
+ * An entry of a file given to `--import`
+ */
+enum ImportEntry {
+    // A video id or URL, its metadata is looked for
+    Id(String),
+    // A song with its metadata, like the ones written by `--export`
+    Video(Video),
+}
+
+/**
+ * Saves the songs listed in a file as a playlist named after the file. The songs without metadata
+ * are looked for in the downloads, then on YouTube Music. The entries that can't be read are
+ * skipped and listed. When `download` is set, the songs are downloaded.
+ */
+pub async fn import(path: PathBuf, download: bool) {
+    let content = match std::fs::read_to_string(&path) {
+        Ok(e) => e,
+        Err(e) => {
+            println!("Can't read {}: {}", path.display(), e);
+            return;
+        }
+    };
+    let entries = match parse_import(&content) {
+        Ok(e) => e,
+        Err(e) => {
+            println!("Can't read {}: {}", path.display(), e);
+            return;
+        }
+    };
+    *DATABASE.write().unwrap() = crate::read_or_scan();
+    let mut api: Option<Option<YTApi>> = None;
+    let mut videos: Vec<Video> = Vec::new();
+    let mut skipped = 0;
+    for (line, entry) in entries.into_iter().enumerate() {
+        let id = match entry {
+            Ok(ImportEntry::Video(video)) => {
+                videos.push(video);
+                continue;
+            }
+            Ok(ImportEntry::Id(id)) => id,
+            Err(e) => {
+                println!("Entry {} skipped: {}", line + 1, e);
+                skipped += 1;
+                continue;
+            }
+        };
+        if let Some(video) = DATABASE.read().unwrap().iter().find(|x| x.video_id == id) {
+            videos.push(video.clone());
+            continue;
+        }
+        // Connected only once a song has to be looked for
+        let api = match &mut api {
+            Some(e) => e,
+            None => api.insert(
+                match crate::setup::connect(Path::new("headers.txt")).await {
+                    Ok(e) => Some(e),
+                    Err(e) => {
+                        println!("Can't connect to YouTube Music, the songs that aren't downloaded are skipped: {:?}", e);
+                        None
+                    }
+                },
+            ),
+        };
+        // Searching the id returns the song first when it exists
+        let found = match api {
+            Some(api) => crate::api::retried(|| api.search(&id))
+                .await
+                .ok()
+                .and_then(|x| x.into_iter().find(|x| x.video_id == id)),
+            None => None,
+        };
+        match found {
+            Some(video) => videos.push(video),
+            None => {
+                println!("Entry {} skipped: can't find the song {}", line + 1, id);
+                skipped += 1;
+            }
+        }
+    }
+    if videos.is_empty() {
+        println!("No song to import");
+        return;
+    }
+    let name = path
+        .file_stem()
+        .map(|x| x.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "Imported".to_owned());
+    let playlist = LocalPlaylist { name, videos };
+    if let Err(e) = playlists::write(&playlist) {
+        println!("Can't save the playlist: {}", e);
+        return;
+    }
+    println!(
+        "{} songs saved as the playlist {}, {} skipped",
+        playlist.videos.len(),
+        playlist.name,
+        skipped
+    );
+    if download {
+        let missing = playlist
+            .videos
+            .into_iter()
+            .filter(|x| {
+                !CACHE_DIR
+                    .join(format!("downloads/{}.json", x.video_id))
+                    .exists()
+            })
+            .collect::<Vec<_>>();
+        let count = missing.len();
+        let downloaded = download_all(missing).await;
+        println!("{}/{} songs downloaded", downloaded, count);
+    }
+}
+
+/**
+ * Reads the entries of a file given to `--import`, a JSON list or one entry per line.
+ * A CSV file written by `--export` is read line by line, the id being the first column.
+ */
+fn parse_import(content: &str) -> Result<Vec<Result<ImportEntry, String>>, String> {
+    if content.trim_start().starts_with('[') {
+        let values: Vec<serde_json::Value> =
+            serde_json::from_str(content).map_err(|e| e.to_string())?;
+        return Ok(values
+            .into_iter()
+            .map(|value| match value {
+                serde_json::Value::String(e) => links::video_id(&e)
+                    .map(ImportEntry::Id)
+                    .ok_or_else(|| format!("`{}` isn't a video id or URL", e)),
+                value => serde_json::from_value(value)
+                    .map(ImportEntry::Video)
+                    .map_err(|e| e.to_string()),
+            })
+            .collect());
+    }
+    Ok(content
+        .lines()
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty() && !x.starts_with('#') && !x.starts_with("video_id,"))
+        .map(|line| {
+            let first = line.split(',').next().unwrap_or(line);
+            links::video_id(first)
+                .map(ImportEntry::Id)
+                .ok_or_else(|| format!("`{}` isn't a video id or URL", line))
+        })
+        .collect())
+}
+
+/**
+ * A downloaded song in the file written by `--export`
+ */
+#[derive(Serialize)]
+struct ManifestEntry<'a> {
+    #[serde(flatten)]
+    video: &'a Video,
+    path: String,
+    // Size of the audio file in bytes, 0 if it is missing
+    size: u64,
+}
+
+/**
+ * Writes every downloaded song with its metadata and its files to `path`, as JSON or CSV.
+ * The entries are written one by one so a large library isn't copied in memory.
+ */
+pub async fn export(path: PathBuf, format: ExportFormat) {
+    *DATABASE.write().unwrap() = crate::read_or_scan();
+    let result = tokio::task::spawn_blocking(move || -> std::io::Result<(PathBuf, usize)> {
+        let database = DATABASE.read().unwrap();
+        let mut file = BufWriter::new(File::create(&path)?);
+        match format {
+            ExportFormat::Json => writeln!(file, "[")?,
+            ExportFormat::Csv => writeln!(file, "video_id,title,author,album,duration,path,size")?,
+        }
+        for (i, video) in database.iter().enumerate() {
+            let audio = CACHE_DIR.join(format!("downloads/{}.mp4", video.video_id));
+            let entry = ManifestEntry {
+                video,
+                size: std::fs::metadata(&audio).map(|x| x.len()).unwrap_or(0),
+                path: audio.to_string_lossy().into_owned(),
+            };
+            match format {
+                ExportFormat::Json => {
+                    if i != 0 {
+                        writeln!(file, ",")?;
+                    }
+                    serde_json::to_writer(&mut file, &entry)?;
+                }
+                ExportFormat::Csv => writeln!(
+                    file,
+                    "{},{},{},{},{},{},{}",
+                    csv_field(&video.video_id),
+                    csv_field(&video.title),
+                    csv_field(&video.author),
+                    csv_field(&video.album),
+                    csv_field(&video.duration),
+                    csv_field(&entry.path),
+                    entry.size
+                )?,
+            }
+        }
+        if format == ExportFormat::Json {
+            writeln!(file, "\n]")?;
+        }
+        file.flush()?;
+        Ok((path, database.len()))
+    })
+    .await;
+    match result {
+        Ok(Ok((path, count))) => println!("{} songs written to {}", count, path.display()),
+        Ok(Err(e)) => println!("Can't write the export: {}", e),
+        Err(e) => println!("The export failed: {}", e),
+    }
+}
+
+/**
+ * Quotes a CSV field if it contains a separator, a quote or a line break
+ */
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+/**
+ * Opens the file with the decoder and decodes the first samples without playing them
+ */
+fn check_audio(path: &Path) -> Result<(), String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut decoder =
+        player::Decoder::new_decoder(BufReader::new(file)).map_err(|e| format!("{:?}", e))?;
+    decoder
+        .next()
+        .map(|_| ())
+        .ok_or_else(|| "no audio samples".to_owned())
+}
+
+/**
+ * Lists the songs downloaded under several ids, like re-uploads of the same song.
+ * When `fix` is set, only one copy of each song is kept.
+ */
+pub fn find_duplicates(fix: bool) {
+    *DATABASE.write().unwrap() = crate::read_or_scan();
+    let clusters = duplicate_clusters(&DATABASE.read().unwrap());
+    if clusters.is_empty() {
+        println!("No duplicate found");
+        return;
+    }
+    for cluster in clusters.iter() {
+        println!("{} | {}", cluster[0].author, cluster[0].title);
+        for (i, video) in cluster.iter().enumerate() {
+            println!(
+                "  {} {} | {} ({}, {})",
+                if i == 0 { "keep  " } else { "remove" },
+                video.author,
+                video.title,
+                video.video_id,
+                video.duration
+            );
+        }
+    }
+    println!("{} songs downloaded more than once", clusters.len());
+    if !fix {
+        println!("Run with --fix to remove the copies");
+        return;
+    }
+    let mut removed = 0;
+    for video in clusters.iter().flat_map(|x| x.iter().skip(1)) {
+        for extension in ["mp4", "json"] {
+            let path = CACHE_DIR.join(format!("downloads/{}.{}", video.video_id, extension));
+            if path.exists() {
+                if let Err(e) = std::fs::remove_file(&path) {
+                    println!("Can't remove {}: {}", path.display(), e);
+                }
+            }
+        }
+        DATABASE
+            .write()
+            .unwrap()
+            .retain(|x| x.video_id != video.video_id);
+        removed += 1;
+    }
+    crate::write();
+    println!("{} copies removed", removed);
+}
+
+/**
+ * Groups the videos that are likely the same song, the one to keep first.
+ * Only the videos with the same normalized title and artist and a duration at most 2 seconds
+ * apart are grouped, to avoid removing different versions of a song.
+ */
+fn duplicate_clusters(videos: &[Video]) -> Vec<Vec<Video>> {
+    let mut by_name: HashMap<(String, String), Vec<&Video>> = HashMap::new();
+    for video in videos
+        .iter()
+        .filter(|x| !normalize_title(&x.title).is_empty())
+    {
+        by_name
+            .entry((
+                normalize_title(&video.title),
+                normalize_author(&video.author),
+            ))
+            .or_default()
+            .push(video);
+    }
+    let mut clusters = Vec::new();
+    for mut group in by_name.into_values().filter(|x| x.len() > 1) {
+        // The official songs have an album, they are kept over the videos
+        group.sort_by_key(|x| x.album.is_empty());
+        while let Some(first) = group.first().copied() {
+            let first_duration = parse_duration(&first.duration);
+            let (same, other): (Vec<&Video>, Vec<&Video>) =
+                group.into_iter().partition(|x| {
+                    match (first_duration, parse_duration(&x.duration)) {
+                        (Some(a), Some(b)) => a.abs_diff(b) <= 2,
+                        _ => x.video_id == first.video_id,
+                    }
+                });
+            if same.len() > 1 {
+                clusters.push(same.into_iter().cloned().collect());
+            }
+            group = other;
+        }
+    }
+    clusters
+}
+
+/**
+ * Lowercases the title and removes what the re-uploads add, like `(Official Video)`
+ */
+fn normalize_title(title: &str) -> String {
+    let mut normalized = String::with_capacity(title.len());
+    let mut depth = 0usize;
+    for c in title.chars() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            c if depth == 0 && c.is_alphanumeric() => normalized.extend(c.to_lowercase()),
+            c if depth == 0 && c.is_whitespace() && !normalized.ends_with(' ') => {
+                normalized.push(' ')
+            }
+            _ => {}
+        }
+    }
+    normalized.trim().to_owned()
+}
+
+/**
+ * Lowercases the artist and removes the ` - Topic` of the automatic YouTube channels
+ */
+fn normalize_author(author: &str) -> String {
+    let author = author.trim();
+    author
+        .strip_suffix(" - Topic")
+        .unwrap_or(author)
+        .to_lowercase()
+}
+
+/**
+ * Parses a duration written as `m:ss` or `h:mm:ss` into seconds
+ */
+pub fn parse_duration(duration: &str) -> Option<u64> {
+    duration
+        .trim()
+        .split(':')
+        .try_fold(0, |total, x| Some(total * 60 + x.parse::<u64>().ok()?))
+        .filter(|x| *x != 0)
+}
+
+// Songs downloaded by the benchmark, chosen to be available everywhere for a long time
+const BENCHMARK_IDS: [&str; 4] = ["dQw4w9WgXcQ", "kJQP7kiw5Fk", "9bZkp7q19f0", "fJ9rUzIMcZQ"];
+
+/**
+ * Downloads a few songs with the configured downloaders and prints the time taken and the speed.
+ * The songs that weren't already downloaded are removed afterwards.
+ */
+pub async fn benchmark() {
+    let workers = Arc::new(tokio::sync::Semaphore::new(CONFIG.downloader_count.max(1)));
+    let start = Instant::now();
+    let handles = BENCHMARK_IDS
+        .iter()
+        .filter(|id| {
+            let cached = CACHE_DIR.join(format!("downloads/{}.json", id)).exists();
+            if cached {
+                println!("{}: already downloaded, skipped", id);
+            }
+            !cached
+        })
+        .map(|id| {
+            let workers = workers.clone();
+            let video = Video {
+                title: String::new(),
+                author: String::new(),
+                album: String::new(),
+                video_id: (*id).to_owned(),
+                duration: String::new(),
+                chapters: Vec::new(),
+            };
+            tokio::task::spawn(async move {
+                let _permit = workers.acquire().await.unwrap();
+                let start = Instant::now();
+                let result = download::handle_download(&video).await;
+                let elapsed = start.elapsed();
+                let path = CACHE_DIR.join(format!("downloads/{}.mp4", video.video_id));
+                // The streamed songs are kept in memory
+                let size = match stream::get(&video.video_id) {
+                    Some(data) => data.len() as u64,
+                    None => std::fs::metadata(&path).map(|x| x.len()).unwrap_or(0),
+                };
+                stream::remove(&video.video_id);
+                let _ = std::fs::remove_file(&path);
+                let _ = std::fs::remove_file(thumbnails::path(&video.video_id));
+                (video.video_id, result.map(|_| size), elapsed)
+            })
+        })
+        .collect::<Vec<_>>();
+    let (mut downloaded, mut failed, mut total_size) = (0, 0, 0);
+    for handle in handles {
+        let (id, result, elapsed) = match handle.await {
+            Ok(e) => e,
+            Err(e) => {
+                println!("Download task failed: {}", e);
+                failed += 1;
+                continue;
+            }
+        };
+        match result {
+            Ok(size) => {
+                downloaded += 1;
+                total_size += size;
+                println!(
+                    "{}: {:.1} MB in {:.1}s, {:.2} MB/s",
+                    id,
+                    megabytes(size),
+                    elapsed.as_secs_f64(),
+                    megabytes(size) / elapsed.as_secs_f64()
+                );
+            }
+            Err(e) => {
+                failed += 1;
+                println!(
+                    "{}: failed after {:.1}s: {:?}",
+                    id,
+                    elapsed.as_secs_f64(),
+                    e
+                );
+            }
+        }
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+    println!(
+        "{} downloaded, {} failed with {} downloaders: {:.1} MB in {:.1}s, {:.2} MB/s",
+        downloaded,
+        failed,
+        CONFIG.downloader_count,
+        megabytes(total_size),
+        elapsed,
+        megabytes(total_size) / elapsed
+    );
+}
+
+fn megabytes(bytes: u64) -> f64 {
+    bytes as f64 / 1024.0 / 1024.0
+}
@@ -0,0 +1,81 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    RwLock, TryLockError,
+};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::CONFIG,
+    consts::CACHE_DIR,
+    systems::{logger::log_, player::RepeatMode},
+};
+
+/**
+ * User preferences that are changed from inside the app and kept between restarts
+ */
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    // Show the remaining time instead of the `progress_label` of the config, or the elapsed and
+    // total time if the config already shows the remaining time
+    pub show_remaining: bool,
+    // Give the whole width to the playlist instead of showing the volume panel
+    pub hide_volume: bool,
+    // Name of the playlist selected in the playlist selector
+    pub selected_playlist: Option<String>,
+    // Name of the last equalizer preset chosen in the player
+    pub equalizer_preset: Option<String>,
+    // Names of the playlists always listed first in the playlist selector
+    pub pinned_playlists: Vec<String>,
+    // The last volume in percents, `default_volume` of the config is used until it is changed
+    pub volume: Option<u8>,
+    // The repeat mode last chosen in the player
+    pub repeat: RepeatMode,
+}
+
+// A global variable to store the current settings
+pub static SETTINGS: Lazy<RwLock<Settings>> = Lazy::new(|| {
+    RwLock::new(crate::database::read_json(&CACHE_DIR.join("settings.json")).unwrap_or_default())
+});
+
+// Set when the settings changed since they were written
+static DIRTY: AtomicBool = AtomicBool::new(false);
+
+/**
+ * Marks the settings as changed, they are written by the flusher unless `persist_interval_secs` is 0
+ */
+pub fn save() {
+    DIRTY.store(true, Ordering::SeqCst);
+    if CONFIG.persist_interval_secs == 0 {
+        flush();
+    }
+}
+
+/**
+ * Writes the settings to the disk if they changed
+ */
+pub fn flush() {
+    if !DIRTY.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    // Also called by the panic hook, before the stack unwinds: the lock may still be held by
+    // the thread that panicked, so the settings are written by the next flush instead
+    let settings = match SETTINGS.try_read() {
+        Ok(e) => e.clone(),
+        Err(TryLockError::Poisoned(e)) => e.into_inner().clone(),
+        Err(TryLockError::WouldBlock) => {
+            DIRTY.store(true, Ordering::SeqCst);
+            return;
+        }
+    };
+    match serde_json::to_string(&settings) {
+        Ok(e) => {
+            if let Err(e) = std::fs::write(CACHE_DIR.join("settings.json"), e) {
+                log_(format!("Can't write settings: {}", e));
+            }
+        }
+        Err(e) => log_(format!("Can't serialize settings: {}", e)),
+    }
+}
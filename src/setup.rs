@@ -0,0 +1,213 @@
+use std::{
+    io::{BufRead, Write},
+    path::Path,
+};
+
+use ytpapi::YTApi;
+
+use crate::{consts::HEADER_TUTORIAL, systems::logger::log_, term::ellipsize};
+
+// The length of the lines of `headers.txt` shown in the errors, the cookies are long
+const SHOWN_LENGTH: usize = 40;
+// The cookies set once logged in to YouTube, one of them is needed to see the user's playlists
+const LOGIN_COOKIES: &[&str] = &["SAPISID", "__Secure-3PAPISID"];
+
+/**
+ * The headers of a `headers.txt` file, in the order of the file
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderSet {
+    headers: Vec<(String, String)>,
+}
+
+impl HeaderSet {
+    /**
+     * Reads the `Name: value` lines, the blank lines are skipped and the spaces around the names
+     * and the values are removed
+     */
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut headers: Vec<(String, String)> = Vec::new();
+        // Some editors start the file with a byte order mark
+        for (index, line) in content.trim_start_matches('\u{feff}').lines().enumerate() {
+            let line = line.trim();
+            // The HTTP/2 pseudo-headers copied by Chrome, like `:authority`, aren't sent
+            if line.is_empty() || line.starts_with(':') {
+                continue;
+            }
+            let (name, value) = match line.split_once(':') {
+                Some((name, value)) if !name.trim().is_empty() => (name.trim(), value.trim()),
+                _ => {
+                    return Err(format!(
+                        "Line {} of `headers.txt` must be `Name: value`, it is `{}`.",
+                        index + 1,
+                        ellipsize(line, SHOWN_LENGTH)
+                    ))
+                }
+            };
+            if !name
+                .chars()
+                .all(|x| x.is_ascii_alphanumeric() || "-_".contains(x))
+            {
+                return Err(format!(
+                    "Line {} of `headers.txt` has an invalid header name `{}`.",
+                    index + 1,
+                    ellipsize(name, SHOWN_LENGTH)
+                ));
+            }
+            // The values can't hold line breaks nor characters outside of ASCII
+            if !value
+                .chars()
+                .all(|x| x == '\t' || (x.is_ascii() && !x.is_ascii_control()))
+            {
+                return Err(format!(
+                    "The {} header has characters that aren't allowed, copy it again as plain text.",
+                    name
+                ));
+            }
+            if headers.iter().any(|(x, _)| x.eq_ignore_ascii_case(name)) {
+                return Err(format!("The {} header is given twice.", name));
+            }
+            headers.push((name.to_owned(), value.to_owned()));
+        }
+        Ok(Self { headers })
+    }
+
+    /**
+     * Reads and parses a `headers.txt` file
+     */
+    pub fn read(path: &Path) -> Result<Self, ytpapi::Error> {
+        let content = std::fs::read_to_string(path).map_err(ytpapi::Error::Io)?;
+        Self::parse(&content).map_err(|e| ytpapi::Error::InvalidHeaderFormat(path.to_owned(), e))
+    }
+
+    /**
+     * Returns the value of a header, the names are compared ignoring the case
+     */
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(x, _)| x.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /**
+     * Checks that the headers needed by YouTube Music are there, the cookie must be the one of a
+     * logged in user
+     */
+    pub fn check(&self) -> Result<(), String> {
+        let cookie = match self.get("Cookie") {
+            Some(e) if !e.is_empty() => e,
+            Some(_) => return Err("The Cookie header of `headers.txt` is empty.".to_owned()),
+            None => return Err("The Cookie header is missing from `headers.txt`.".to_owned()),
+        };
+        let names = cookie
+            .split(';')
+            .filter_map(|x| x.split_once('='))
+            .map(|(name, _)| name.trim())
+            .collect::<Vec<_>>();
+        if names.is_empty() {
+            return Err(
+                "The Cookie header must be a list of `name=value` separated by `;`.".to_owned(),
+            );
+        }
+        if !LOGIN_COOKIES.iter().any(|x| names.contains(x)) {
+            return Err(
+                "The Cookie header has no SAPISID, copy it from a request made while logged in."
+                    .to_owned(),
+            );
+        }
+        // Only a hash of the SAPISID cookie is accepted, the other ones are from other websites
+        if let Some(authorization) = self.get("Authorization") {
+            if !authorization.starts_with("SAPISIDHASH ") {
+                return Err(
+                    "The Authorization header must start with `SAPISIDHASH`, remove it or copy it again."
+                        .to_owned(),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/**
+ * Checks that the content of a `headers.txt` file has the cookie, without contacting YouTube
+ */
+pub fn check_headers(content: &str) -> Result<(), String> {
+    HeaderSet::parse(content)?.check()
+}
+
+/**
+ * Connects to YouTube Music with the headers of a `headers.txt` file, they are read by `HeaderSet`
+ * so the headers sent are the ones checked at startup. The connection is a request, it goes
+ * through `api::limited` too.
+ */
+pub async fn connect(path: &Path) -> Result<YTApi, ytpapi::Error> {
+    let headers = HeaderSet::read(path)?.headers;
+    crate::api::limited(YTApi::from_header_list(headers)).await
+}
+
+/**
+ * Asks the user to paste their cookie until YouTube accepts it, then writes `headers.txt`.
+ * Returns false if the user gave up by entering an empty line.
+ */
+pub async fn run() -> bool {
+    println!("The `headers.txt` file is not present, let's create it.");
+    println!(
+        "{}",
+        HEADER_TUTORIAL
+            .lines()
+            .take_while(|x| !x.starts_with("6."))
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+    let temporary = Path::new("headers.txt.tmp");
+    loop {
+        print!("\nPaste the cookie (leave empty to quit): ");
+        let _ = std::io::stdout().flush();
+        let mut line = String::new();
+        if std::io::stdin().lock().read_line(&mut line).is_err() {
+            return false;
+        }
+        let cookie = line.trim();
+        // The name of the header is often copied with the value
+        let cookie = match cookie.split_once(':') {
+            Some((name, value)) if name.eq_ignore_ascii_case("cookie") => value.trim(),
+            _ => cookie,
+        };
+        if cookie.is_empty() {
+            return false;
+        }
+        let content = format!("Cookie: {}\n", cookie);
+        if let Err(e) = check_headers(&content) {
+            println!("{}", e);
+            continue;
+        }
+        if let Err(e) = std::fs::write(temporary, &content) {
+            println!("Can't write the headers: {}", e);
+            return false;
+        }
+        println!("Checking the cookie with YouTube Music…");
+        match connect(temporary).await {
+            Ok(_) => {
+                if let Err(e) = std::fs::rename(temporary, "headers.txt") {
+                    println!("Can't write `headers.txt`: {}", e);
+                    return false;
+                }
+                log_("Created `headers.txt` with the setup");
+                println!("The `headers.txt` file is created, starting YTerMusic.");
+                return true;
+            }
+            Err(e) => {
+                let _ = std::fs::remove_file(temporary);
+                log_(format!("The cookie of the setup was refused: {:?}", e));
+                if e.is_auth_error() {
+                    println!("YouTube Music doesn't accept this cookie, it may have expired.");
+                } else if e.is_transient() {
+                    println!("Can't reach YouTube Music, check the connection.");
+                } else {
+                    println!("YouTube Music didn't answer as expected, check the cookie.");
+                }
+            }
+        }
+    }
+}
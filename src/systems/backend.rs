@@ -0,0 +1,280 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use once_cell::sync::Lazy;
+use rustube::{Callback, Error, Id};
+use ytpapi::{parse_chapters, Chapter};
+
+use crate::{
+    config::{AudioQuality, DownloadBackendKind, CONFIG},
+    consts::CACHE_DIR,
+};
+
+use super::{
+    download::{PROGRESS, TRANSFERRING},
+    logger::{log_download, LogLevel},
+};
+
+pub type DownloadFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<Vec<Chapter>, Error>> + Send + 'a>>;
+
+/**
+ * A way to download the audio of a video to `CACHE_DIR/downloads/{id}.mp4`.
+ * The download returns the chapters of the video when the backend can find them.
+ */
+pub trait DownloadBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn download<'a>(&'a self, id: &'a str) -> DownloadFuture<'a>;
+    /**
+     * Returns true if the error means that the backend can't handle the video, not that the
+     * video is unavailable
+     */
+    fn is_extraction_error(&self, _error: &Error) -> bool {
+        false
+    }
+}
+
+/**
+ * Downloads with the rustube crate, used by default
+ */
+pub struct Rustube;
+
+impl DownloadBackend for Rustube {
+    fn name(&self) -> &'static str {
+        "rustube"
+    }
+
+    fn download<'a>(&'a self, id: &'a str) -> DownloadFuture<'a> {
+        Box::pin(async move {
+            let video = rustube::Video::from_id(Id::from_str(id)?.into_owned()).await?;
+            let chapters = parse_chapters(&video.video_details().short_description);
+            let stream = audio_stream(&video, id)?;
+            TRANSFERRING.lock().unwrap().insert(id.to_owned());
+            // rustube can't be throttled, the bytes have to pass through the limit
+            let result = if CONFIG.download_bandwidth_limit == 0 {
+                let owned = id.to_owned();
+                let callback = Callback::new().connect_on_progress_closure(move |progress| {
+                    PROGRESS.lock().unwrap().insert(
+                        owned.clone(),
+                        (progress.current_chunk as u64, progress.content_length),
+                    );
+                });
+                stream
+                    .download_to_dir_with_callback(CACHE_DIR.join("downloads"), callback)
+                    .await
+                    .map(|_| ())
+            } else {
+                let url = stream.signature_cipher.url.to_string();
+                let path = CACHE_DIR.join(format!("downloads/{}.mp4", id));
+                super::stream::save(id, &url, &path).await
+            };
+            TRANSFERRING.lock().unwrap().remove(id);
+            PROGRESS.lock().unwrap().remove(id);
+            result?;
+            Ok(chapters)
+        })
+    }
+
+    fn is_extraction_error(&self, error: &Error) -> bool {
+        matches!(
+            error,
+            Error::NoStreams | Error::UnexpectedResponse(_) | Error::Fatal(_)
+        )
+    }
+}
+
+// The audio streams of the songs downloaded with `debug_streams`, the chosen one first, shown in
+// the details of the songs
+static STREAMS: Lazy<Mutex<HashMap<String, Vec<String>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/**
+ * Returns the audio only stream of the video chosen by `audio_quality` and `max_bitrate_kbps`, or
+ * the one of `force_itag` if the video has it
+ */
+pub fn audio_stream<'a>(video: &'a rustube::Video, id: &str) -> Result<&'a rustube::Stream, Error> {
+    let streams = video
+        .streams()
+        .iter()
+        .filter(|stream| stream.includes_audio_track && !stream.includes_video_track)
+        .collect::<Vec<_>>();
+    let forced = CONFIG
+        .force_itag
+        .and_then(|itag| streams.iter().find(|stream| stream.itag == itag));
+    let stream = *forced
+        .or_else(|| preferred_stream(&streams))
+        .ok_or(Error::NoStreams)?;
+    log_download(
+        LogLevel::Info,
+        format!("Stream of {}: {}", id, describe(stream)),
+    );
+    if CONFIG.debug_streams {
+        let mut lines = vec![format!("{} (chosen)", describe(stream))];
+        for other in streams.iter().filter(|x| x.itag != stream.itag) {
+            lines.push(describe(other));
+        }
+        for line in &lines {
+            log_download(LogLevel::Info, format!("Audio stream of {}: {}", id, line));
+        }
+        STREAMS.lock().unwrap().insert(id.to_owned(), lines);
+    }
+    Ok(stream)
+}
+
+/**
+ * Picks the mp4 stream of `audio_quality`, capped by `max_bitrate_kbps`
+ */
+fn preferred_stream<'a, 'b>(streams: &'b [&'a rustube::Stream]) -> Option<&'b &'a rustube::Stream> {
+    let mp4 = streams.iter().filter(|stream| stream.mime == "audio/mp4");
+    // A stream of unknown bitrate is never taken as the lowest
+    let lowest = mp4
+        .clone()
+        .min_by_key(|stream| stream.bitrate.unwrap_or(u64::MAX));
+    if CONFIG.audio_quality == AudioQuality::Lowest {
+        return lowest;
+    }
+    if CONFIG.max_bitrate_kbps == 0 {
+        return mp4.max_by_key(|stream| stream.bitrate);
+    }
+    let cap = CONFIG.max_bitrate_kbps * 1000;
+    mp4.filter(|stream| matches!(stream.bitrate, Some(x) if x <= cap))
+        .max_by_key(|stream| stream.bitrate)
+        .or(lowest)
+}
+
+/**
+ * The format of the stream downloaded by `yt-dlp`, chosen like `preferred_stream`
+ */
+fn ytdlp_format() -> String {
+    match (CONFIG.audio_quality, CONFIG.max_bitrate_kbps) {
+        (AudioQuality::Lowest, _) => "worstaudio[ext=m4a]/worstaudio".to_owned(),
+        (AudioQuality::Highest, 0) => "bestaudio[ext=m4a]/bestaudio".to_owned(),
+        (AudioQuality::Highest, cap) => format!(
+            "bestaudio[ext=m4a][abr<={0}]/bestaudio[abr<={0}]/worstaudio[ext=m4a]/worstaudio",
+            cap
+        ),
+    }
+}
+
+fn describe(stream: &rustube::Stream) -> String {
+    format!(
+        "itag {}, {} bits/s, {} {}",
+        stream.itag,
+        stream
+            .bitrate
+            .map(|x| x.to_string())
+            .unwrap_or_else(|| "unknown".to_owned()),
+        stream.mime,
+        stream.codecs.join(",")
+    )
+}
+
+/**
+ * Returns the audio streams found for a song downloaded in this session with `debug_streams`
+ */
+pub fn streams_of(video_id: &str) -> Vec<String> {
+    STREAMS
+        .lock()
+        .unwrap()
+        .get(video_id)
+        .cloned()
+        .unwrap_or_default()
+}
+
+/**
+ * Downloads by running the `yt-dlp` binary
+ */
+pub struct YtDlp;
+
+// Whether the `yt-dlp` binary can be started
+pub static YTDLP_AVAILABLE: Lazy<bool> = Lazy::new(|| {
+    std::process::Command::new(&CONFIG.ytdlp_path)
+        .arg("--version")
+        .output()
+        .map(|x| x.status.success())
+        .unwrap_or(false)
+});
+
+impl DownloadBackend for YtDlp {
+    fn name(&self) -> &'static str {
+        "yt-dlp"
+    }
+
+    fn download<'a>(&'a self, id: &'a str) -> DownloadFuture<'a> {
+        Box::pin(async move {
+            let output = CACHE_DIR.join(format!("downloads/{}.mp4", id));
+            let url = format!("https://www.youtube.com/watch?v={}", id);
+            TRANSFERRING.lock().unwrap().insert(id.to_owned());
+            let result = tokio::task::spawn_blocking(move || {
+                std::process::Command::new(&CONFIG.ytdlp_path)
+                    .args(["--quiet", "--no-progress", "--no-playlist"])
+                    .arg("-f")
+                    .arg(ytdlp_format())
+                    .arg("-o")
+                    .arg(&output)
+                    .arg("--")
+                    .arg(url)
+                    .output()
+            })
+            .await;
+            TRANSFERRING.lock().unwrap().remove(id);
+            let result = result
+                .map_err(|e| Error::Fatal(e.to_string()))?
+                .map_err(Error::Io)?;
+            if result.status.success() {
+                Ok(Vec::new())
+            } else {
+                Err(Error::Fatal(format!(
+                    "yt-dlp failed: {}",
+                    String::from_utf8_lossy(&result.stderr).trim()
+                )))
+            }
+        })
+    }
+}
+
+// Set when the downloads use yt-dlp, the backend of the configuration until it is switched
+static USE_YTDLP: Lazy<AtomicBool> =
+    Lazy::new(|| AtomicBool::new(CONFIG.download_backend == DownloadBackendKind::YtDlp));
+
+/**
+ * The backend selected in the configuration, or the one switched to since
+ */
+pub fn primary() -> &'static dyn DownloadBackend {
+    if USE_YTDLP.load(Ordering::SeqCst) {
+        &YtDlp
+    } else {
+        &Rustube
+    }
+}
+
+/**
+ * Makes the next downloads use the other backend, returns the name of the backend now used.
+ * Fails when switching to yt-dlp if it can't be started.
+ */
+pub fn switch() -> Result<&'static str, String> {
+    let ytdlp = !USE_YTDLP.load(Ordering::SeqCst);
+    if ytdlp && !*YTDLP_AVAILABLE {
+        return Err(format!("Can't start yt-dlp at `{}`", CONFIG.ytdlp_path));
+    }
+    USE_YTDLP.store(ytdlp, Ordering::SeqCst);
+    Ok(primary().name())
+}
+
+/**
+ * The backend used when the primary one can't extract the audio of a video
+ */
+pub fn fallback() -> Option<&'static dyn DownloadBackend> {
+    if !USE_YTDLP.load(Ordering::SeqCst) && CONFIG.ytdlp_fallback && *YTDLP_AVAILABLE {
+        Some(&YtDlp)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,47 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use once_cell::sync::Lazy;
+
+use crate::config::CONFIG;
+
+// Bytes that can be transferred right away, negative when the transfers are ahead of the limit
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+// Shared by all the transfers, so the limit applies to their total throughput
+static BUCKET: Lazy<Mutex<Bucket>> = Lazy::new(|| {
+    Mutex::new(Bucket {
+        tokens: CONFIG.download_bandwidth_limit as f64,
+        refilled: Instant::now(),
+    })
+});
+
+/**
+ * Waits until `bytes` more bytes can be transferred without going over
+ * `download_bandwidth_limit`. Returns immediately when there is no limit.
+ */
+pub async fn throttle(bytes: usize) {
+    let limit = CONFIG.download_bandwidth_limit as f64;
+    if limit == 0.0 {
+        return;
+    }
+    let wait = {
+        let mut bucket = BUCKET.lock().unwrap();
+        let now = Instant::now();
+        // At most one second of transfer can be saved up for a burst
+        bucket.tokens =
+            (bucket.tokens + now.duration_since(bucket.refilled).as_secs_f64() * limit).min(limit);
+        bucket.refilled = now;
+        // The bytes are taken even if missing, the next transfers then wait for them too
+        bucket.tokens -= bytes as f64;
+        Duration::from_secs_f64((-bucket.tokens).max(0.0) / limit)
+    };
+    if !wait.is_zero() {
+        tokio::time::sleep(wait).await;
+    }
+}
@@ -0,0 +1,111 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    sync::{Arc, RwLock},
+};
+
+use flume::Sender;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+use crate::{config::CONFIG, SoundAction};
+
+use super::logger::log_;
+
+/**
+ * A snapshot of the player, updated by the player on every tick
+ */
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PlayerStatus {
+    // `playing`, `paused` or `stopped`
+    pub state: &'static str,
+    pub video_id: Option<String>,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub elapsed: u64,
+    pub duration: u64,
+    pub volume: i32,
+    pub queue: usize,
+}
+
+pub static STATUS: Lazy<RwLock<PlayerStatus>> = Lazy::new(|| RwLock::new(PlayerStatus::default()));
+
+/**
+ * Starts listening on the control socket if one is configured
+ */
+pub fn start(sender: Arc<Sender<SoundAction>>) {
+    let path = match &CONFIG.control_socket {
+        Some(e) => e,
+        None => return,
+    };
+    // A socket left by a previous run that didn't exit properly
+    let _ = std::fs::remove_file(path);
+    let listener = match UnixListener::bind(path) {
+        Ok(e) => e,
+        Err(e) => {
+            log_(format!("Can't open the control socket {}: {}", path, e));
+            return;
+        }
+    };
+    std::thread::spawn(move || {
+        log_("Control socket on");
+        for stream in listener.incoming().flatten() {
+            let sender = sender.clone();
+            std::thread::spawn(move || handle_client(stream, sender));
+        }
+    });
+}
+
+/**
+ * Removes the control socket file
+ */
+pub fn cleanup() {
+    if let Some(path) = &CONFIG.control_socket {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+fn handle_client(stream: UnixStream, sender: Arc<Sender<SoundAction>>) {
+    let mut writer = match stream.try_clone() {
+        Ok(e) => e,
+        Err(_) => return,
+    };
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(e) => e,
+            Err(_) => return,
+        };
+        let response = execute(line.trim(), &sender);
+        if writeln!(writer, "{}", response).is_err() {
+            return;
+        }
+    }
+}
+
+/**
+ * Runs a command and returns the line sent back to the client
+ */
+fn execute(command: &str, sender: &Sender<SoundAction>) -> String {
+    let status = STATUS.read().unwrap().clone();
+    let mut words = command.split_whitespace();
+    let action = match (words.next(), words.next()) {
+        (Some("status"), None) => {
+            return serde_json::to_string(&status).unwrap_or_else(|e| format!("error {}", e))
+        }
+        (Some("play"), None) if status.state == "paused" => SoundAction::PlayPause,
+        (Some("pause"), None) if status.state == "playing" => SoundAction::PlayPause,
+        (Some("play" | "pause"), None) => return "ok".to_owned(),
+        (Some("toggle"), None) => SoundAction::PlayPause,
+        (Some("next"), None) => SoundAction::Next(1),
+        (Some("prev"), None) => SoundAction::Previous(1),
+        (Some("vol"), Some(volume)) => match volume.parse() {
+            Ok(e) => SoundAction::SetVolume(e),
+            Err(_) => return format!("error invalid volume `{}`", volume),
+        },
+        _ => return format!("error unknown command `{}`", command),
+    };
+    match sender.send(action) {
+        Ok(()) => "ok".to_owned(),
+        Err(e) => format!("error {}", e),
+    }
+}
@@ -1,31 +1,120 @@
 use std::{
-    collections::VecDeque,
-    path::PathBuf,
-    sync::{atomic::AtomicBool, Arc, Mutex},
-    time::Duration,
+    collections::{HashMap, HashSet, VecDeque},
+    io::Write,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
 };
 
 use flume::Sender;
 use once_cell::sync::Lazy;
-use rustube::{Error, Id};
-use tokio::{task::JoinHandle, time::sleep};
-use ytpapi::Video;
+use rustube::Error;
+use tokio::{
+    sync::Notify,
+    task::{AbortHandle, JoinHandle},
+    time::sleep,
+};
+use ytpapi::{Chapter, Video};
 
-use crate::{consts::CACHE_DIR, SoundAction};
+use crate::{
+    api::NetworkActivity,
+    config::{DownloadOrder, DuplicateAction, CONFIG},
+    consts::CACHE_DIR,
+    errors::SendOrLog,
+    SoundAction,
+};
+
+use super::{
+    backend,
+    logger::{log_download, LogLevel},
+    player::song_path,
+    stream, tags, thumbnails,
+};
 
 pub static IN_DOWNLOAD: Lazy<Mutex<Vec<ytpapi::Video>>> = Lazy::new(|| Mutex::new(Vec::new()));
 static HANDLES: Lazy<Mutex<Vec<JoinHandle<()>>>> = Lazy::new(|| Mutex::new(Vec::new()));
+// Ids of the songs whose audio is being transferred, the others in `IN_DOWNLOAD` are still connecting
+pub static TRANSFERRING: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+// Bytes received and size of a transfer
+pub type Progress = (u64, Option<u64>);
+// The progress of the transfers that know it, by video id
+pub static PROGRESS: Lazy<Mutex<HashMap<String, Progress>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+// The tasks of the running downloads, by video id, to cancel them
+static RUNNING: Lazy<Mutex<HashMap<String, AbortHandle>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+// Ids of the running downloads cancelled by the user, they don't count as failed
+static CANCELLED: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+// The songs of the queue whose download failed, downloaded again by `retry_failed`
+static FAILED: Lazy<Mutex<Vec<Video>>> = Lazy::new(|| Mutex::new(Vec::new()));
 pub static DOWNLOAD_MORE: AtomicBool = AtomicBool::new(true);
 // TODO Maybe switch to a channel
 static DOWNLOAD_QUEUE: Lazy<Mutex<VecDeque<ytpapi::Video>>> =
     Lazy::new(|| Mutex::new(VecDeque::new()));
 
+// Wakes the downloader of `single_downloader` when a song is added to the queue
+static QUEUE_CHANGED: Lazy<Notify> = Lazy::new(Notify::new);
+// Position of each song in the order they were added to the queue, by video id
+static POSITIONS: Lazy<Mutex<HashMap<String, usize>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+// Position of the song being played
+static CURRENT_POSITION: AtomicUsize = AtomicUsize::new(0);
+// Number of songs downloaded since the download queue was last empty
+static BATCH: AtomicUsize = AtomicUsize::new(0);
+// Number of failed downloads tried again during the session, see `retry_budget`
+static RETRIES: AtomicUsize = AtomicUsize::new(0);
+// Ids of the songs of the queue whose download is tried again
+static RETRYING: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
 fn take() -> Option<Video> {
-    DOWNLOAD_QUEUE.lock().unwrap().pop_front()
+    let mut queue = DOWNLOAD_QUEUE.lock().unwrap();
+    let retrying = RETRYING.lock().unwrap();
+    // The retried songs wait while `max_concurrent_retries` of them are downloaded
+    let retries_full = CONFIG.max_concurrent_retries != 0
+        && IN_DOWNLOAD
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|x| retrying.contains(&x.video_id))
+            .count()
+            >= CONFIG.max_concurrent_retries;
+    let eligible = |video: &Video| !retries_full || !retrying.contains(&video.video_id);
+    if CONFIG.download_order == DownloadOrder::Fifo {
+        let index = queue.iter().position(eligible)?;
+        return queue.remove(index);
+    }
+    let positions = POSITIONS.lock().unwrap();
+    let current = CURRENT_POSITION.load(std::sync::atomic::Ordering::SeqCst);
+    // The songs after the current one come first, the closest first
+    let index = queue
+        .iter()
+        .enumerate()
+        .filter(|(_, video)| eligible(video))
+        .min_by_key(|(_, video)| match positions.get(&video.video_id) {
+            Some(&position) => (position < current, position.abs_diff(current)),
+            None => (true, usize::MAX),
+        })
+        .map(|(index, _)| index)?;
+    queue.remove(index)
+}
+
+/**
+ * Tells the downloader which song is played, the closest songs are downloaded first with the
+ * `proximity` download order
+ */
+pub fn set_current(video_id: &str) {
+    if let Some(position) = POSITIONS.lock().unwrap().get(video_id) {
+        CURRENT_POSITION.store(*position, std::sync::atomic::Ordering::SeqCst);
+    }
 }
 
 pub fn clean(sender: Arc<Sender<SoundAction>>) {
     DOWNLOAD_QUEUE.lock().unwrap().clear();
+    POSITIONS.lock().unwrap().clear();
+    CURRENT_POSITION.store(0, std::sync::atomic::Ordering::SeqCst);
+    BATCH.store(0, std::sync::atomic::Ordering::SeqCst);
     {
         let mut handle = HANDLES.lock().unwrap();
         for i in handle.iter() {
@@ -33,37 +122,501 @@ pub fn clean(sender: Arc<Sender<SoundAction>>) {
         }
         handle.clear();
     }
+    for (_, download) in RUNNING.lock().unwrap().drain() {
+        download.abort();
+    }
     IN_DOWNLOAD.lock().unwrap().clear();
+    TRANSFERRING.lock().unwrap().clear();
+    PROGRESS.lock().unwrap().clear();
+    CANCELLED.lock().unwrap().clear();
+    FAILED.lock().unwrap().clear();
+    RETRYING.lock().unwrap().clear();
+    stream::clear();
     DOWNLOAD_MORE.store(true, std::sync::atomic::Ordering::SeqCst);
     downloader(sender);
 }
 
+/**
+ * Returns true when there is nothing left to download
+ */
+pub fn is_idle() -> bool {
+    DOWNLOAD_QUEUE.lock().unwrap().is_empty() && IN_DOWNLOAD.lock().unwrap().is_empty()
+}
+
+/**
+ * Returns the number of songs waiting for a free downloader
+ */
+pub fn queued_count() -> usize {
+    DOWNLOAD_QUEUE.lock().unwrap().len()
+}
+
+/**
+ * Returns the songs waiting for a free downloader, in the order they are added
+ */
+pub fn queued() -> Vec<Video> {
+    DOWNLOAD_QUEUE.lock().unwrap().iter().cloned().collect()
+}
+
+/**
+ * Returns the bytes received of a download and its size when known. The downloads written
+ * directly to the disk only know the size of their file.
+ */
+pub fn progress(video_id: &str) -> Option<Progress> {
+    if let Some(progress) = PROGRESS.lock().unwrap().get(video_id) {
+        return Some(*progress);
+    }
+    if !TRANSFERRING.lock().unwrap().contains(video_id) {
+        return None;
+    }
+    std::fs::metadata(CACHE_DIR.join(format!("downloads/{}.mp4", video_id)))
+        .ok()
+        .map(|x| (x.len(), None))
+}
+
+/**
+ * Removes a song from the download queue or stops its download, returns false if it isn't
+ * downloaded
+ */
+pub fn cancel(video_id: &str) -> bool {
+    {
+        let mut queue = DOWNLOAD_QUEUE.lock().unwrap();
+        let len = queue.len();
+        queue.retain(|x| x.video_id != video_id);
+        if queue.len() != len {
+            return true;
+        }
+    }
+    {
+        let mut failed = FAILED.lock().unwrap();
+        let len = failed.len();
+        failed.retain(|x| x.video_id != video_id);
+        if failed.len() != len {
+            return true;
+        }
+    }
+    match RUNNING.lock().unwrap().get(video_id) {
+        Some(download) => {
+            CANCELLED.lock().unwrap().insert(video_id.to_owned());
+            download.abort();
+            true
+        }
+        None => false,
+    }
+}
+
+/**
+ * Returns the percentage of a download, when its size is known
+ */
+pub fn percent(video_id: &str) -> Option<u64> {
+    match progress(video_id)? {
+        (received, Some(size)) if size != 0 => Some(received * 100 / size),
+        _ => None,
+    }
+}
+
+/**
+ * Returns the songs of the queue whose download failed
+ */
+pub fn failed() -> Vec<Video> {
+    FAILED.lock().unwrap().clone()
+}
+
+/**
+ * Adds the songs whose download failed to the queue again, returns how many were added.
+ * Each song counts in the `retry_budget` of the session, once it is spent the songs stay in the
+ * failed list and an error is returned.
+ */
+pub fn retry_failed(s: &Sender<SoundAction>) -> Result<usize, String> {
+    let mut failed = FAILED.lock().unwrap();
+    if failed.is_empty() {
+        return Ok(0);
+    }
+    let spent = RETRIES.load(std::sync::atomic::Ordering::SeqCst);
+    let count = if CONFIG.retry_budget == 0 {
+        failed.len()
+    } else {
+        failed.len().min(CONFIG.retry_budget.saturating_sub(spent))
+    };
+    if count == 0 {
+        let message = "Too many downloads failed, the retries are paused: check `headers.txt` and the connection";
+        log_download(LogLevel::Error, message);
+        return Err(message.to_owned());
+    }
+    RETRIES.fetch_add(count, std::sync::atomic::Ordering::SeqCst);
+    let retried = failed.drain(..count).collect::<Vec<_>>();
+    drop(failed);
+    RETRYING
+        .lock()
+        .unwrap()
+        .extend(retried.iter().map(|x| x.video_id.clone()));
+    for video in retried {
+        add(video, s);
+    }
+    Ok(count)
+}
+
+/**
+ * Returns the number of lines shown for the downloads at the top of the playlist
+ */
+pub fn download_lines() -> usize {
+    IN_DOWNLOAD.lock().unwrap().len() + usize::from(queued_count() != 0)
+}
+
+// Number of streamed songs kept in memory ahead of the current one
+const STREAM_AHEAD: usize = 3;
+
+/**
+ * Returns false when enough songs are streamed ahead, the downloads always continue
+ */
+fn has_room() -> bool {
+    !CONFIG.streaming || stream::buffered_count() + IN_DOWNLOAD.lock().unwrap().len() < STREAM_AHEAD
+}
+
+/**
+ * Replaces the current queue by the given songs
+ */
+pub fn replace_queue(videos: &[Video], s: Arc<Sender<SoundAction>>) {
+    s.send_or_log(SoundAction::Cleanup);
+    clean(s.clone());
+    for video in videos {
+        add(video.clone(), &s);
+    }
+}
+
 pub fn add(video: Video, s: &Sender<SoundAction>) {
+    {
+        let mut positions = POSITIONS.lock().unwrap();
+        let next = positions.len();
+        positions.entry(video.video_id.clone()).or_insert(next);
+    }
     let download_path_json = CACHE_DIR.join(&format!("downloads/{}.json", &video.video_id));
     if download_path_json.exists() {
-        s.send(SoundAction::PlayVideo(video)).unwrap();
+        s.send_or_log(SoundAction::PlayVideo(video));
     } else {
-        DOWNLOAD_QUEUE.lock().unwrap().push_back(video);
+        let mut queue = DOWNLOAD_QUEUE.lock().unwrap();
+        // The player handles the duplicates once the song is downloaded
+        if CONFIG.on_duplicate != DuplicateAction::Allow
+            && queue.iter().any(|x| x.video_id == video.video_id)
+        {
+            return;
+        }
+        if CONFIG.max_queue_len != 0 && queue.len() >= CONFIG.max_queue_len {
+            log_download(
+                LogLevel::Debug,
+                format!("The download queue is full, {} isn't added", video.video_id),
+            );
+            return;
+        }
+        queue.push_back(video);
+        QUEUE_CHANGED.notify_one();
     }
 }
 
-async fn handle_download(id: &str) -> Result<PathBuf, Error> {
-    rustube::Video::from_id(Id::from_str(id)?.into_owned())
-        .await?
-        .streams()
-        .iter()
-        .filter(|stream| {
-            stream.mime == "audio/mp4"
-                && stream.includes_audio_track
-                && !stream.includes_video_track
-        })
-        .max_by_key(|stream| stream.bitrate)
-        .ok_or(Error::NoStreams)?
-        .download_to_dir(CACHE_DIR.join("downloads"))
-        .await
+/**
+ * Downloads the audio of the video and returns the chapters found in its description.
+ * The download fails if it takes longer than `download_timeout_secs`, so a hung connection
+ * doesn't keep a downloader busy forever.
+ */
+pub async fn handle_download(video: &Video) -> Result<Vec<Chapter>, Error> {
+    let _activity = NetworkActivity::start();
+    // The download runs in its own task so it can be cancelled
+    let task = {
+        let video = video.clone();
+        tokio::task::spawn(async move { download(&video).await })
+    };
+    let abort = task.abort_handle();
+    RUNNING
+        .lock()
+        .unwrap()
+        .insert(video.video_id.clone(), abort.clone());
+    let result = if CONFIG.download_timeout_secs == 0 {
+        Ok(task.await)
+    } else {
+        let timeout = Duration::from_secs(CONFIG.download_timeout_secs);
+        tokio::time::timeout(timeout, task).await
+    };
+    RUNNING.lock().unwrap().remove(&video.video_id);
+    let message = match result {
+        Ok(Ok(e)) => return e,
+        Ok(Err(e)) if e.is_cancelled() => format!(
+            "The download of {} | {} ({}) was cancelled",
+            video.author, video.title, video.video_id
+        ),
+        Ok(Err(e)) => format!(
+            "The download of {} | {} ({}) crashed: {}",
+            video.author, video.title, video.video_id, e
+        ),
+        Err(_) => {
+            abort.abort();
+            format!(
+                "The download of {} | {} ({}) timed out after {}s",
+                video.author, video.title, video.video_id, CONFIG.download_timeout_secs
+            )
+        }
+    };
+    // Stopping the download doesn't run its cleanup
+    TRANSFERRING.lock().unwrap().remove(&video.video_id);
+    PROGRESS.lock().unwrap().remove(&video.video_id);
+    log_download(LogLevel::Error, &message);
+    Err(Error::Fatal(message))
+}
+
+/**
+ * Downloads the audio like `handle_download`, trying again up to `download_attempts` times with a
+ * delay doubled after each failure. The downloads cancelled by the user and the videos without
+ * audio fail right away. The user is told when a song can't be downloaded.
+ */
+async fn download_retried(video: &Video, s: &Sender<SoundAction>) -> Result<Vec<Chapter>, Error> {
+    let mut delay = Duration::from_millis(CONFIG.download_retry_delay_ms);
+    let mut attempt = 1;
+    loop {
+        let error = match handle_download(video).await {
+            Ok(chapters) => {
+                decorate(video).await;
+                return Ok(chapters);
+            }
+            Err(e) => e,
+        };
+        log_download(
+            LogLevel::Error,
+            format!(
+                "Attempt {} of {} to download {} failed: {}",
+                attempt, CONFIG.download_attempts, video.video_id, error
+            ),
+        );
+        if CANCELLED.lock().unwrap().contains(&video.video_id) {
+            return Err(error);
+        }
+        // Retrying can't help when YouTube has no audio for the video
+        let pointless = matches!(
+            error,
+            Error::NoStreams | Error::VideoUnavailable(_) | Error::BadIdFormat
+        );
+        if pointless || attempt >= CONFIG.download_attempts {
+            // A toast rather than `ManagerMessage::Error`, which opens the device lost screen
+            // and would take the user away from the player for a single song
+            s.send_or_log(SoundAction::DownloadError(if pointless {
+                format!("Can't download {}: {}", video.title, error)
+            } else {
+                format!(
+                    "Failed to download {} after {} attempts",
+                    video.title, attempt
+                )
+            }));
+            return Err(error);
+        }
+        remove_partial(video);
+        sleep(delay).await;
+        delay *= 2;
+        attempt += 1;
+    }
+}
+
+/**
+ * Downloads the audio of the video and returns the chapters found in its description.
+ * When the configured backend can't extract the audio, the fallback backend is used if enabled.
+ */
+async fn download(video: &Video) -> Result<Vec<Chapter>, Error> {
+    let id = video.video_id.as_str();
+    if CONFIG.streaming {
+        log_download(
+            LogLevel::Info,
+            format!("Streaming {} | {} ({})", video.author, video.title, id),
+        );
+        let result = stream::fetch(video).await;
+        if let Err(e) = &result {
+            log_download(
+                LogLevel::Error,
+                format!(
+                    "Can't stream {} | {} ({}): {:?}",
+                    video.author, video.title, id, e
+                ),
+            );
+        }
+        return result;
+    }
+    check_free_space().map_err(Error::Fatal)?;
+    let backend = backend::primary();
+    let start = Instant::now();
+    log_download(
+        LogLevel::Info,
+        format!(
+            "Downloading {} | {} ({}) with {}",
+            video.author,
+            video.title,
+            id,
+            backend.name()
+        ),
+    );
+    let result = match backend.download(id).await {
+        Err(e) if backend.is_extraction_error(&e) => {
+            log_download(
+                LogLevel::Error,
+                format!(
+                    "{} can't extract the audio streams of {} ({}), YouTube probably changed \
+                     its format",
+                    backend.name(),
+                    id,
+                    e
+                ),
+            );
+            match backend::fallback() {
+                Some(fallback) => {
+                    log_download(
+                        LogLevel::Info,
+                        format!("Downloading {} with {}", id, fallback.name()),
+                    );
+                    fallback.download(id).await
+                }
+                None => Err(e),
+            }
+        }
+        e => e,
+    };
+    match &result {
+        Ok(_) => {
+            log_download(
+                LogLevel::Info,
+                format!("Downloaded {} in {:.1}s", id, start.elapsed().as_secs_f32()),
+            );
+        }
+        Err(e) => log_download(
+            LogLevel::Error,
+            format!(
+                "Can't download {} | {} ({}): {:?}",
+                video.author, video.title, id, e
+            ),
+        ),
+    }
+    result
 }
 
-const DOWNLOADER_COUNT: usize = 4;
+/**
+ * Caches the thumbnail of a downloaded song and tags its file. It runs after the timed download,
+ * so a slow thumbnail can't make a finished download time out and start again.
+ */
+async fn decorate(video: &Video) {
+    // The streamed songs have no file
+    if CONFIG.streaming {
+        return;
+    }
+    // The thumbnail is fetched first so the tags use the cached one
+    thumbnails::cache(&video.video_id).await;
+    if CONFIG.embed_tags {
+        tags::embed(video).await;
+    }
+}
+
+/**
+ * Fails if the disk is almost full, as the downloads would fail midway
+ */
+fn check_free_space() -> Result<(), String> {
+    if CONFIG.min_free_space_mb == 0 {
+        return Ok(());
+    }
+    match fs2::available_space(CACHE_DIR.join("downloads")) {
+        Ok(space) if space < CONFIG.min_free_space_mb * 1024 * 1024 => Err(format!(
+            "Not enough disk space to download, {} MB left",
+            space / 1024 / 1024
+        )),
+        Ok(_) => Ok(()),
+        // The download is still tried if the space can't be known
+        Err(e) => {
+            log_download(LogLevel::Debug, format!("Can't read the free space: {}", e));
+            Ok(())
+        }
+    }
+}
+
+/**
+ * Writes the metadata of a downloaded song, the song is removed if it can't be written
+ */
+fn save_download(video: &Video) -> Result<(), String> {
+    // The streamed songs are only kept in memory
+    if CONFIG.streaming {
+        return Ok(());
+    }
+    let download_path_json = CACHE_DIR.join(format!("downloads/{}.json", video.video_id));
+    let result = tags::metadata(video)
+        .map_err(|e| e.to_string())
+        .and_then(|x| {
+            // The song is flushed before its metadata, so a power loss can't leave a metadata
+            // file next to a broken song
+            if CONFIG.fsync_downloads {
+                std::fs::File::open(song_path(&video.video_id))
+                    .and_then(|x| x.sync_all())
+                    .map_err(|e| e.to_string())?;
+            }
+            write_durably(&download_path_json, x.as_bytes()).map_err(|e| e.to_string())
+        });
+    if let Err(e) = result {
+        remove_partial(video);
+        return Err(format!("Can't save {}: {}", video.title, e));
+    }
+    crate::append(video.clone());
+    Ok(())
+}
+
+/**
+ * Writes a file of the downloads to a temporary file renamed once complete, so a crash can't leave
+ * a truncated file under its name. With `fsync_downloads` it is also flushed to the disk before
+ * being renamed.
+ */
+pub fn write_durably(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let start = Instant::now();
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let mut file = std::fs::File::create(&temporary)?;
+    file.write_all(data)?;
+    if CONFIG.fsync_downloads {
+        file.sync_all()?;
+    }
+    drop(file);
+    std::fs::rename(&temporary, path)?;
+    if !CONFIG.fsync_downloads {
+        return Ok(());
+    }
+    // The rename is only durable once the directory is flushed, which Windows can't do
+    #[cfg(unix)]
+    if let Some(directory) = path.parent() {
+        std::fs::File::open(directory)?.sync_all()?;
+    }
+    log_download(
+        LogLevel::Debug,
+        format!(
+            "Flushed {} to the disk in {}ms",
+            path.display(),
+            start.elapsed().as_millis()
+        ),
+    );
+    Ok(())
+}
+
+/**
+ * Removes the files of a song whose download failed
+ */
+fn remove_partial(video: &Video) {
+    for extension in ["mp4", "mp4.tmp", "json", "json.tmp"] {
+        let path = CACHE_DIR.join(format!("downloads/{}.{}", video.video_id, extension));
+        if path.exists() {
+            if let Err(e) = std::fs::remove_file(&path) {
+                log_download(
+                    LogLevel::Error,
+                    format!("Can't remove {}: {}", path.display(), e),
+                );
+            }
+        }
+    }
+}
+
+/**
+ * Tells the user why a download failed when the disk is full, other errors are only logged
+ */
+fn report_failure(s: &Sender<SoundAction>) {
+    if let Err(e) = check_free_space() {
+        s.send_or_log(SoundAction::DownloadError(e));
+    }
+}
 
 pub fn start_task(s: Arc<Sender<SoundAction>>) {
     HANDLES.lock().unwrap().push(tokio::task::spawn(async move {
@@ -74,102 +627,154 @@ pub fn start_task(s: Arc<Sender<SoundAction>>) {
             } else {
                 k = false;
             }
-            if !DOWNLOAD_MORE.load(std::sync::atomic::Ordering::SeqCst) {
+            if !DOWNLOAD_MORE.load(std::sync::atomic::Ordering::SeqCst) || !has_room() {
                 continue;
             }
             if let Some(id) = take() {
-                // TODO(#1): handle errors
-                let download_path_mp4 = CACHE_DIR.join(&format!("downloads/{}.mp4", &id.video_id));
-                let download_path_json =
-                    CACHE_DIR.join(&format!("downloads/{}.json", &id.video_id));
-                if download_path_json.exists() {
-                    s.send(SoundAction::PlayVideo(id)).unwrap();
-                    k = true;
-                    continue;
-                }
-                if download_path_mp4.exists() {
-                    std::fs::remove_file(&download_path_mp4).unwrap();
-                }
-                {
-                    IN_DOWNLOAD.lock().unwrap().push(id.clone());
-                }
-                match handle_download(&id.video_id).await {
-                    Ok(_) => {
-                        std::fs::write(download_path_json, serde_json::to_string(&id).unwrap())
-                            .unwrap();
-                        crate::append(id.clone());
-                        {
-                            IN_DOWNLOAD
-                                .lock()
-                                .unwrap()
-                                .retain(|x| x.video_id != id.video_id);
-                        }
-                        s.send(SoundAction::PlayVideo(id)).unwrap();
-                        k = true;
-                    }
-                    Err(_) => {
-                        if download_path_mp4.exists() {
-                            std::fs::remove_file(download_path_mp4).unwrap();
-                        }
-
-                        {
-                            IN_DOWNLOAD
-                                .lock()
-                                .unwrap()
-                                .retain(|x| x.video_id != id.video_id);
-                        }
-                        // TODO(#1): handle errors
-                    }
-                }
+                k = download_one(id, &s).await;
             }
         }
     }));
 }
-pub fn start_task_unary(s: Arc<Sender<SoundAction>>, song: Video) {
+
+/**
+ * The only downloader when `single_downloader` is set, it sleeps until a song is added instead
+ * of checking the queue regularly
+ */
+fn start_single_task(s: Arc<Sender<SoundAction>>) {
     HANDLES.lock().unwrap().push(tokio::task::spawn(async move {
-        let download_path_mp4 = CACHE_DIR.join(&format!("downloads/{}.mp4", &song.video_id));
-        let download_path_json = CACHE_DIR.join(&format!("downloads/{}.json", &song.video_id));
-        if download_path_json.exists() {
-            s.send(SoundAction::PlayVideoUnary(song.clone())).unwrap();
-            return;
-        }
-        if download_path_mp4.exists() {
-            std::fs::remove_file(&download_path_mp4).unwrap();
-        }
-        {
-            IN_DOWNLOAD.lock().unwrap().push(song.clone());
-        }
-        match handle_download(&song.video_id).await {
-            Ok(_) => {
-                std::fs::write(download_path_json, serde_json::to_string(&song).unwrap()).unwrap();
-                crate::append(song.clone());
-                {
-                    IN_DOWNLOAD
-                        .lock()
-                        .unwrap()
-                        .retain(|x| x.video_id != song.video_id);
-                }
-                s.send(SoundAction::PlayVideoUnary(song)).unwrap();
+        loop {
+            if !DOWNLOAD_MORE.load(std::sync::atomic::Ordering::SeqCst) || !has_room() {
+                sleep(Duration::from_millis(200)).await;
+                continue;
             }
-            Err(_) => {
-                if download_path_mp4.exists() {
-                    std::fs::remove_file(download_path_mp4).unwrap();
+            match take() {
+                Some(id) => {
+                    download_one(id, &s).await;
                 }
+                None => QUEUE_CHANGED.notified().await,
+            }
+        }
+    }));
+}
 
-                {
-                    IN_DOWNLOAD
-                        .lock()
-                        .unwrap()
-                        .retain(|x| x.video_id != song.video_id);
+/**
+ * Downloads a song of the queue and sends it to the player, returns true if it was sent
+ */
+async fn download_one(mut id: Video, s: &Sender<SoundAction>) -> bool {
+    let download_path_json = CACHE_DIR.join(&format!("downloads/{}.json", &id.video_id));
+    if download_path_json.exists() {
+        s.send_or_log(SoundAction::PlayVideo(id));
+        return true;
+    }
+    // The audio left by an interrupted download
+    remove_partial(&id);
+    {
+        IN_DOWNLOAD.lock().unwrap().push(id.clone());
+    }
+    let result = download_retried(&id, s).await.map(|chapters| {
+        id.chapters = chapters;
+    });
+    {
+        IN_DOWNLOAD
+            .lock()
+            .unwrap()
+            .retain(|x| x.video_id != id.video_id);
+    }
+    RETRYING.lock().unwrap().remove(&id.video_id);
+    let sent = match result {
+        Ok(()) => match save_download(&id) {
+            Ok(()) => {
+                BATCH.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                s.send_or_log(SoundAction::PlayVideo(id));
+                s.send_or_log(SoundAction::LimitCache);
+                true
+            }
+            Err(e) => {
+                s.send_or_log(SoundAction::DownloadError(e));
+                false
+            }
+        },
+        Err(_) => {
+            remove_partial(&id);
+            report_failure(s);
+            if !CANCELLED.lock().unwrap().remove(&id.video_id) {
+                FAILED.lock().unwrap().push(id);
+            }
+            false
+        }
+    };
+    // The last download of the queue ends the batch, even if it failed
+    if is_idle() && BATCH.swap(0, std::sync::atomic::Ordering::SeqCst) != 0 && !CONFIG.streaming {
+        s.send_or_log(SoundAction::DownloadsFinished);
+    }
+    sent
+}
+
+/**
+ * Downloads a single song if needed and plays it right after the current one.
+ * The rest of the queue is kept and played once the song is finished.
+ */
+pub fn start_task_unary(s: Arc<Sender<SoundAction>>, mut song: Video) {
+    let download_path_json = CACHE_DIR.join(&format!("downloads/{}.json", &song.video_id));
+    if download_path_json.exists() {
+        s.send_or_log(SoundAction::PlayVideoUnary(song));
+        return;
+    }
+    // Marked as downloading before the task starts so the player can wait for it
+    {
+        IN_DOWNLOAD.lock().unwrap().push(song.clone());
+    }
+    HANDLES.lock().unwrap().push(tokio::task::spawn(async move {
+        remove_partial(&song);
+        let result = download_retried(&song, &s).await.map(|chapters| {
+            song.chapters = chapters;
+        });
+        let video_id = song.video_id.clone();
+        match result {
+            Ok(()) => match save_download(&song) {
+                Ok(()) => {
+                    s.send_or_log(SoundAction::PlayVideoUnary(song));
+                    s.send_or_log(SoundAction::LimitCache);
                 }
-                // TODO(#1): handle errors
+                Err(e) => s.send_or_log(SoundAction::DownloadError(e)),
+            },
+            Err(_) => {
+                remove_partial(&song);
+                report_failure(&s);
+                CANCELLED.lock().unwrap().remove(&song.video_id);
             }
         }
+        {
+            IN_DOWNLOAD
+                .lock()
+                .unwrap()
+                .retain(|x| x.video_id != video_id);
+        }
+    }));
+}
+
+/**
+ * Streams again a song whose audio was dropped from memory, the player waits for it
+ */
+pub fn restream(video: Video) {
+    IN_DOWNLOAD.lock().unwrap().push(video.clone());
+    HANDLES.lock().unwrap().push(tokio::task::spawn(async move {
+        // The errors are logged and the song is skipped by the player
+        let _ = handle_download(&video).await;
+        IN_DOWNLOAD
+            .lock()
+            .unwrap()
+            .retain(|x| x.video_id != video.video_id);
     }));
 }
 
 pub fn downloader(s: Arc<Sender<SoundAction>>) {
-    for _ in 0..DOWNLOADER_COUNT {
+    if CONFIG.single_downloader {
+        start_single_task(s);
+        return;
+    }
+    for _ in 0..CONFIG.downloader_count {
         start_task(s.clone());
     }
 }
@@ -0,0 +1,99 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::ErrorKind,
+    time::SystemTime,
+};
+
+use crate::{config::CONFIG, consts::CACHE_DIR, database};
+
+use super::{logger::log_, thumbnails};
+
+/**
+ * Removes the least recently played downloads until the downloads folder is under
+ * `max_cache_mb`, the songs of `keep` are never removed. Returns the ids of the removed songs.
+ */
+pub fn enforce_limit(keep: &HashSet<String>) -> Vec<String> {
+    if CONFIG.max_cache_mb == 0 {
+        return Vec::new();
+    }
+    let limit = CONFIG.max_cache_mb * 1024 * 1024;
+    let entries = match std::fs::read_dir(CACHE_DIR.join("downloads")) {
+        Ok(e) => e,
+        Err(e) => {
+            log_(format!(
+                "Can't read the downloads to limit their size: {}",
+                e
+            ));
+            return Vec::new();
+        }
+    };
+    let mut total = 0;
+    // The size of the files of each song and when its audio was last used
+    let mut songs: HashMap<String, (u64, SystemTime)> = HashMap::new();
+    for entry in entries.flatten() {
+        let metadata = match entry.metadata() {
+            Ok(e) if e.is_file() => e,
+            _ => continue,
+        };
+        total += metadata.len();
+        let path = entry.path();
+        // The partial downloads have another extension, they are never removed here
+        let extension = path.extension().and_then(|x| x.to_str());
+        if !matches!(extension, Some("mp4" | "json")) {
+            continue;
+        }
+        let id = match path.file_stem().and_then(|x| x.to_str()) {
+            Some(e) => e.to_owned(),
+            None => continue,
+        };
+        let song = songs.entry(id).or_insert((0, SystemTime::UNIX_EPOCH));
+        song.0 += metadata.len();
+        if extension == Some("mp4") {
+            // The access time isn't kept by every file system, the download time is used then
+            song.1 = [metadata.accessed(), metadata.modified()]
+                .into_iter()
+                .flatten()
+                .max()
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+        }
+    }
+    if total <= limit {
+        return Vec::new();
+    }
+    let mut candidates = songs
+        .into_iter()
+        .filter(|(id, _)| !keep.contains(id))
+        .collect::<Vec<_>>();
+    candidates.sort_by_key(|(_, (_, used))| *used);
+    let mut removed = Vec::new();
+    for (id, (size, _)) in candidates {
+        if total <= limit {
+            break;
+        }
+        for extension in ["mp4", "json"] {
+            let path = CACHE_DIR.join(format!("downloads/{}.{}", id, extension));
+            match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != ErrorKind::NotFound => {
+                    log_(format!("Can't remove {}: {}", path.display(), e));
+                }
+                _ => {}
+            }
+        }
+        // The thumbnail may be missing, it isn't needed to play the song
+        let _ = std::fs::remove_file(thumbnails::path(&id));
+        total = total.saturating_sub(size);
+        removed.push(id);
+    }
+    if !removed.is_empty() {
+        log_(format!(
+            "Removed {} songs to keep the downloads under {} MB",
+            removed.len(),
+            CONFIG.max_cache_mb
+        ));
+        database::remove(&removed);
+    }
+    if total > limit {
+        log_("The downloads are still over `max_cache_mb`, the songs left are in the queue");
+    }
+    removed
+}
@@ -0,0 +1,62 @@
+use std::{process::Command, sync::Arc, time::Duration};
+
+use flume::Sender;
+
+use crate::{config::CONFIG, errors::SendOrLog, SoundAction};
+
+use super::{control::STATUS, logger::log_};
+
+// The roles of the streams of the calls, the ones PulseAudio corks the music for
+const CALL_ROLES: [&str; 2] = ["\"phone\"", "\"communication\""];
+
+/**
+ * Starts watching the streams of PulseAudio (or PipeWire) if `pause_on_call` is set. The player
+ * is paused while a call is playing and resumed after it, unless it was already paused.
+ */
+pub fn start(sender: Arc<Sender<SoundAction>>) {
+    if !CONFIG.pause_on_call {
+        return;
+    }
+    std::thread::spawn(move || {
+        log_("Audio focus monitor on");
+        // Set when the player was paused for a call
+        let mut paused = false;
+        loop {
+            std::thread::sleep(Duration::from_secs(2));
+            let call = match call_playing() {
+                Some(e) => e,
+                None => {
+                    log_("Can't list the audio streams with `pactl`, audio focus monitor off");
+                    return;
+                }
+            };
+            let playing = STATUS.read().unwrap().state == "playing";
+            if call && playing && !paused {
+                log_("A call started, pausing");
+                sender.send_or_log(SoundAction::ForcePause);
+                paused = true;
+            } else if !call && paused {
+                log_("The call ended, resuming");
+                sender.send_or_log(SoundAction::ForcePlay);
+                paused = false;
+            }
+        }
+    });
+}
+
+/**
+ * Returns true if a stream with the role of a call is playing, `None` if `pactl` can't be run
+ */
+fn call_playing() -> Option<bool> {
+    let output = Command::new("pactl")
+        .args(["list", "sink-inputs"])
+        .output()
+        .ok()
+        .filter(|x| x.status.success())?;
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|x| x.trim().strip_prefix("media.role = "))
+            .any(|role| CALL_ROLES.contains(&role)),
+    )
+}
@@ -0,0 +1,56 @@
+use std::process::{Command, Stdio};
+
+use ytpapi::Video;
+
+use crate::config::CONFIG;
+
+use super::logger::log_;
+
+/**
+ * Runs `on_track_change` for the song that started, the command is split on whitespace and
+ * isn't run in a shell so the placeholders can't inject anything
+ */
+pub fn on_track_change(video: &Video) {
+    let template = match &CONFIG.on_track_change {
+        Some(e) => e,
+        None => return,
+    };
+    let mut args = template.split_whitespace().map(|arg| {
+        arg.replace("{title}", &video.title)
+            .replace("{author}", &video.author)
+            .replace("{album}", &video.album)
+            .replace("{id}", &video.video_id)
+            .replace("{duration}", &video.duration)
+    });
+    let program = match args.next() {
+        Some(e) => e,
+        None => return,
+    };
+    let child = Command::new(&program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    let mut child = match child {
+        Ok(e) => e,
+        Err(e) => {
+            log_(format!("Can't run `on_track_change` ({}): {}", program, e));
+            return;
+        }
+    };
+    // Waited for in another thread so the player is never blocked and no zombie is left
+    std::thread::spawn(move || match child.wait() {
+        Ok(status) if !status.success() => {
+            log_(format!(
+                "`on_track_change` ({}) exited with {}",
+                program, status
+            ));
+        }
+        Err(e) => log_(format!(
+            "Can't wait for `on_track_change` ({}): {}",
+            program, e
+        )),
+        _ => {}
+    });
+}
@@ -28,3 +28,42 @@ static LOG: Lazy<Sender<String>> = Lazy::new(|| {
 pub fn log_(message: impl Into<String>) {
     LOG.send(message.into()).unwrap();
 }
+
+/**
+ * How much is written to the log file, from the least to the most verbose
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    fn from_env(name: &str) -> Option<Self> {
+        match std::env::var(name).ok()?.to_lowercase().as_str() {
+            "error" => Some(Self::Error),
+            "info" => Some(Self::Info),
+            "debug" => Some(Self::Debug),
+            _ => None,
+        }
+    }
+}
+
+// The default log level, set with the `YTERMUSIC_LOG` environment variable. Only the downloads
+// log with a level, `log_` always writes
+pub static LOG_LEVEL: Lazy<LogLevel> =
+    Lazy::new(|| LogLevel::from_env("YTERMUSIC_LOG").unwrap_or(LogLevel::Info));
+
+// The log level of the downloads, set with `YTERMUSIC_DOWNLOAD_LOG`, the app log level by default
+pub static DOWNLOAD_LOG_LEVEL: Lazy<LogLevel> =
+    Lazy::new(|| LogLevel::from_env("YTERMUSIC_DOWNLOAD_LOG").unwrap_or(*LOG_LEVEL));
+
+/**
+ * Logs a message of the download system if its level is enabled
+ */
+pub fn log_download(level: LogLevel, message: impl Into<String>) {
+    if level <= *DOWNLOAD_LOG_LEVEL {
+        log_(message);
+    }
+}
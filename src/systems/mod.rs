@@ -1,3 +1,15 @@
+pub mod backend;
+pub mod bandwidth;
+#[cfg(unix)]
+pub mod control;
 pub mod download;
+pub mod eviction;
+#[cfg(all(feature = "audio-focus", target_os = "linux"))]
+pub mod focus;
+pub mod hooks;
 pub mod logger;
 pub mod player;
+pub mod station;
+pub mod stream;
+pub mod tags;
+pub mod thumbnails;
@@ -1,23 +1,201 @@
-use std::{collections::VecDeque, process::exit, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    path::{Path, PathBuf},
+    process::exit,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 use flume::{unbounded, Receiver, Sender};
-use player::{Guard, PlayError, Player, StreamError};
+use once_cell::sync::Lazy;
+use player::{Guard, OutputConfig, PlayError, Player, StreamError, SymphoniaDecoder};
+use serde::{Deserialize, Serialize};
 use souvlaki::{Error, MediaControls, MediaMetadata, MediaPlayback, MediaPosition, PlatformConfig};
 
-use tui::{style::Style, widgets::ListItem};
+use tui::{style::Style, text::Spans, widgets::ListItem};
 use ytpapi::Video;
 
 use crate::{
+    config::{
+        DecodeErrorAction, DuplicateAction, QueueDensity, QueueEndAction, ResumeSession,
+        SeekEndAction, SeekStartAction, UnaryPlayPolicy, CONFIG,
+    },
     consts::CACHE_DIR,
-    errors::{handle_error, handle_error_option},
+    database::{self, playlists, resume},
+    errors::{handle_error, handle_error_option, SendOrLog},
+    settings::SETTINGS,
     term::{
+        ellipsize,
         music_player::{MusicStatus, MusicStatusAction},
-        ManagerMessage, Screens,
+        title, ManagerMessage, Screens,
     },
     SoundAction, DATABASE,
 };
 
-use super::download::IN_DOWNLOAD;
+use super::{
+    download::{self, start_task_unary, IN_DOWNLOAD},
+    eviction,
+    logger::log_,
+    station::Station,
+    stream, tags, thumbnails,
+};
+
+// Where to start the songs played from a link with a time, by video id
+static START_OFFSETS: Lazy<Mutex<HashMap<String, Duration>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/**
+ * Starts the song at the given time the next time it is played
+ */
+pub fn set_start_offset(video_id: &str, offset: Duration) {
+    START_OFFSETS
+        .lock()
+        .unwrap()
+        .insert(video_id.to_owned(), offset);
+}
+
+// Prefix of the id of the songs played from a file given with `--file`, followed by its path
+pub const LOCAL_FILE_PREFIX: &str = "file:";
+
+/**
+ * Formats a number of seconds as `m:ss`
+ */
+fn format_time(seconds: u64) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/**
+ * The path of the file of a song
+ */
+pub fn song_path(video_id: &str) -> PathBuf {
+    if let Some(path) = video_id.strip_prefix(LOCAL_FILE_PREFIX) {
+        PathBuf::from(path)
+    } else {
+        CACHE_DIR.join(format!("downloads/{}.mp4", video_id))
+    }
+}
+
+/**
+ * Opens a downloaded song, or a streamed one whose audio is in memory
+ */
+fn open_song(video_id: &str) -> Result<SymphoniaDecoder, PlayError> {
+    let path = song_path(video_id);
+    match stream::get(video_id) {
+        Some(data) if !path.exists() => Player::open_buffer(data),
+        _ => Player::open(&path),
+    }
+}
+
+/**
+ * Takes the next song of the queue, the unary songs are at its start
+ */
+fn pop_next(queue: &mut VecDeque<Video>, unary_count: &mut usize) -> Option<Video> {
+    let video = queue.pop_front()?;
+    *unary_count = unary_count.saturating_sub(1);
+    Some(video)
+}
+
+/**
+ * Puts a song played on its own in the queue as set by `policy`, the first `unary_count` songs
+ * of the queue are the unary songs not played yet and the rest of the queue follows them.
+ * Returns true if the song must interrupt the current one.
+ */
+fn queue_unary(
+    queue: &mut VecDeque<Video>,
+    unary_count: &mut usize,
+    video: Video,
+    policy: UnaryPlayPolicy,
+    max_queue_len: usize,
+) -> bool {
+    if policy == UnaryPlayPolicy::Replace {
+        queue.clear();
+        *unary_count = 0;
+    }
+    // The song is moved if it was already in the queue instead of being played twice
+    if let Some(index) = queue.iter().position(|x| x.video_id == video.video_id) {
+        queue.remove(index);
+        if index < *unary_count {
+            *unary_count -= 1;
+        }
+    }
+    // Unary songs requested one after the other are played in the requested order, unless each
+    // one interrupts the previous
+    let index = match policy {
+        UnaryPlayPolicy::Next => *unary_count,
+        UnaryPlayPolicy::Now | UnaryPlayPolicy::Replace => 0,
+    };
+    queue.insert(index, video);
+    *unary_count += 1;
+    // The song furthest from being played makes room for it
+    if max_queue_len != 0 && queue.len() > max_queue_len {
+        queue.pop_back();
+        *unary_count = (*unary_count).min(queue.len());
+    }
+    policy != UnaryPlayPolicy::Next
+}
+
+/**
+ * What a seek does to the current song
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SeekOutcome {
+    // Moves to this time of the song
+    To(Duration),
+    // Plays the previous song
+    Previous,
+    // Plays the next song
+    Next,
+    // Moves to the end of the song and pauses it
+    StopAtEnd,
+    // The song stays where it is
+    Stay,
+}
+
+/**
+ * The outcome of `Forward`: 5 seconds later, or `seek_past_end` past the end of the song. A
+ * song still loading has no duration yet, it isn't moved
+ */
+fn seek_forward(elapsed: Duration, duration: Option<f64>, action: SeekEndAction) -> SeekOutcome {
+    let duration = match duration {
+        Some(e) => e,
+        None => return SeekOutcome::Stay,
+    };
+    match player::forward_position(elapsed, duration) {
+        Some(position) => SeekOutcome::To(position),
+        None => match action {
+            SeekEndAction::Clamp => SeekOutcome::Stay,
+            SeekEndAction::Next => SeekOutcome::Next,
+            SeekEndAction::Stop => SeekOutcome::StopAtEnd,
+        },
+    }
+}
+
+/**
+ * The outcome of `Backward`: 5 seconds earlier, stopping at the start of the song, or
+ * `seek_before_start` once the song is at its start
+ */
+fn seek_backward(elapsed: Duration, action: SeekStartAction) -> SeekOutcome {
+    match (player::backward_position(elapsed), action) {
+        (Some(position), _) => SeekOutcome::To(position),
+        (None, SeekStartAction::Clamp) => SeekOutcome::Stay,
+        (None, SeekStartAction::Previous) => SeekOutcome::Previous,
+    }
+}
+
+/**
+ * The time reached by seeking to `target` seconds, kept between the start and the end of a song
+ * lasting `duration` seconds
+ */
+fn seek_target(target: f64, duration: f64) -> Duration {
+    Duration::from_secs_f64(target.clamp(0.0, duration.max(0.0)))
+}
+
+/**
+ * Returns false for a streamed song whose audio isn't in memory anymore
+ */
+fn is_playable(video_id: &str) -> bool {
+    !CONFIG.streaming || song_path(video_id).exists() || stream::get(video_id).is_some()
+}
 
 #[cfg(not(target_os = "windows"))]
 fn get_handle(updater: &Sender<ManagerMessage>) -> Option<MediaControls> {
@@ -53,12 +231,10 @@ fn get_handle(updater: &Sender<ManagerMessage>) -> Option<MediaControls> {
         {
             Some(h.hwnd)
         } else {
-            updater
-                .send(ManagerMessage::PassTo(
-                    Screens::DeviceLost,
-                    Box::new(ManagerMessage::Error("No window handle found".to_string())),
-                ))
-                .unwrap();
+            updater.send_or_log(ManagerMessage::PassTo(
+                Screens::DeviceLost,
+                Box::new(ManagerMessage::Error("No window handle found".to_string())),
+            ));
             return None;
         },
     };
@@ -70,10 +246,80 @@ fn get_handle(updater: &Sender<ManagerMessage>) -> Option<MediaControls> {
     )
 }
 
+/**
+ * What is played again once a song ends by itself
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepeatMode {
+    #[default]
+    Off,
+    // The song that ended is played again
+    One,
+    // The songs that end go back at the end of the queue, so the queue loops
+    All,
+}
+
+impl RepeatMode {
+    fn next(self) -> Self {
+        match self {
+            RepeatMode::Off => RepeatMode::One,
+            RepeatMode::One => RepeatMode::All,
+            RepeatMode::All => RepeatMode::Off,
+        }
+    }
+    /**
+     * The text shown in the title of the progress bar, `None` when nothing is repeated
+     */
+    pub fn label(self) -> Option<&'static str> {
+        match self {
+            RepeatMode::Off => None,
+            RepeatMode::One => Some("repeat one"),
+            RepeatMode::All => Some("repeat all"),
+        }
+    }
+}
+
 pub struct PlayerState {
     pub queue: VecDeque<Video>,
     pub current: Option<Video>,
     pub previous: Vec<Video>,
+    pub show_remaining: bool,
+    pub hide_volume: bool,
+    // The volume before the mute, set while the player is muted
+    pub muted: Option<i32>,
+    pub waiting_for: Option<String>,
+    // Number of songs at the start of the queue that were added by `PlayVideoUnary`
+    pub unary_count: usize,
+    // Number of lines of the playlist hidden by scrolling
+    pub list_offset: usize,
+    // Keep the current song in the middle of the playlist instead of scrolling freely
+    pub follow_current: bool,
+    // The next song of the queue, opened before the end of the current one
+    pub preloaded: Option<(String, SymphoniaDecoder)>,
+    // The next song of the queue added to the sink, it starts right when the current one ends
+    pub queued: Option<String>,
+    // The A-B loop: the id of the song, the start of the loop and its end once set
+    pub ab_loop: Option<(String, Duration, Option<Duration>)>,
+    // Set until the first song is loaded if it has to be paused
+    pub start_paused: bool,
+    // Set once the current song counts as played
+    pub played: bool,
+    // The id of the song selected by a click when clicks only select, or with the selection keys.
+    // It stays on the song while the lines above it change
+    pub selected: Option<String>,
+    // The line and the time of the last click, to detect double clicks
+    pub last_click: Option<(usize, Instant)>,
+    pub station: Option<Station>,
+    // Index of the equalizer preset of the config in use
+    pub equalizer: Option<usize>,
+    // The position in the queue typed after `j`, the song at this position is played on `Enter`
+    pub jump_input: Option<String>,
+    // The id of the song last sent with `NowPlaying`
+    pub announced: Option<String>,
+    // When the next song starts after the current one ended, see `track_gap_ms`
+    pub gap_until: Option<Instant>,
+    pub repeat: RepeatMode,
     pub controls: Option<MediaControls>,
     pub sink: Player,
     pub guard: Guard,
@@ -90,12 +336,31 @@ impl PlayerState {
         updater: Arc<Sender<ManagerMessage>>,
     ) -> Self {
         let (stream_error_sender, stream_error_receiver) = unbounded();
-        let (sink, guard) = handle_error_option(
+        let (mut sink, guard) = handle_error_option(
             &updater,
             "player creation error",
-            Player::new(Arc::new(stream_error_sender)),
+            Player::new(
+                Arc::new(stream_error_sender),
+                OutputConfig {
+                    buffer_size: CONFIG.audio_buffer_size,
+                    sample_rate: CONFIG.sample_rate,
+                },
+            ),
         )
         .unwrap();
+        let volume = SETTINGS.read().unwrap().volume;
+        sink.set_volume(i32::from(volume.unwrap_or(CONFIG.default_volume)));
+        let equalizer = SETTINGS
+            .read()
+            .unwrap()
+            .equalizer_preset
+            .as_ref()
+            .and_then(|name| {
+                CONFIG
+                    .equalizer_presets
+                    .iter()
+                    .position(|x| &x.name == name)
+            });
         let mut controls = get_handle(&updater);
         if let Some(e) = &mut controls {
             handle_error(
@@ -115,7 +380,43 @@ impl PlayerState {
             queue: Default::default(),
             current: Default::default(),
             previous: Default::default(),
+            repeat: SETTINGS.read().unwrap().repeat,
+            show_remaining: SETTINGS.read().unwrap().show_remaining,
+            hide_volume: SETTINGS.read().unwrap().hide_volume,
+            muted: None,
+            waiting_for: None,
+            unary_count: 0,
+            list_offset: 0,
+            follow_current: false,
+            preloaded: None,
+            queued: None,
+            ab_loop: None,
+            start_paused: CONFIG.start_paused,
+            played: false,
+            selected: None,
+            last_click: None,
+            station: None,
+            equalizer,
+            jump_input: None,
+            announced: None,
+            gap_until: None,
         }
+        .with_equalizer()
+    }
+    /**
+     * Applies the saved equalizer preset, the silence trimming and the fade-in on startup
+     */
+    fn with_equalizer(mut self) -> Self {
+        if CONFIG.trim_silence {
+            self.sink
+                .set_silence_trim(Some(CONFIG.silence_threshold_db));
+        }
+        self.sink
+            .set_fade_in(Duration::from_millis(CONFIG.fade_in_ms));
+        if let Some(preset) = self.equalizer.and_then(|x| CONFIG.equalizer_presets.get(x)) {
+            self.sink.set_equalizer(&preset.gains);
+        }
+        self
     }
 
     pub fn update(&mut self) {
@@ -124,46 +425,415 @@ impl PlayerState {
         while let Ok(e) = self.soundaction_receiver.try_recv() {
             self.apply_sound_action(e);
         }
-        if self.sink.is_finished() {
+        self.check_played();
+        self.update_station();
+        let started = self.take_started();
+        if self.sink.is_finished() || started {
+            // Don't play anything else while a broken song is downloaded again. The song queued
+            // in the sink already plays, none of the checks below can hold it back
+            let mut waited = None;
+            if let Some(id) = self.waiting_for.as_ref().filter(|_| !started) {
+                if IN_DOWNLOAD
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .any(|x| &x.video_id == id)
+                {
+                    return;
+                }
+                waited = self.waiting_for.take();
+            }
+            // Only a song ending by itself is followed by the gap, skipping still plays the next
+            // song right away as the current one is taken
+            if CONFIG.track_gap_ms != 0
+                && !started
+                && self.current.is_some()
+                && !self.queue.is_empty()
+            {
+                let end = *self.gap_until.get_or_insert_with(|| {
+                    Instant::now() + Duration::from_millis(CONFIG.track_gap_ms)
+                });
+                if Instant::now() < end {
+                    return;
+                }
+            }
+            self.gap_until = None;
             self.handle_stream_errors();
             self.update_controls();
-            if let Some(video) = self.queue.pop_front() {
-                let k = CACHE_DIR.join(&format!("downloads/{}.mp4", &video.video_id));
+            if let Some(video) = self.queue.front().cloned() {
+                if !started && !is_playable(&video.video_id) {
+                    if waited.as_ref() == Some(&video.video_id) {
+                        // The song couldn't be streamed again
+                        pop_next(&mut self.queue, &mut self.unary_count);
+                        self.updater.send_or_log(ManagerMessage::Toast(format!(
+                            "Can't stream {}",
+                            video.title
+                        )));
+                    } else {
+                        self.waiting_for = Some(video.video_id.clone());
+                        download::restream(video);
+                    }
+                    return;
+                }
+            }
+            self.repeat_ended();
+            if let Some(video) = pop_next(&mut self.queue, &mut self.unary_count) {
                 if let Some(e) = self.current.replace(video.clone()) {
                     self.previous.push(e);
                 }
-                if let Err(e) = self.sink.play(k.as_path(), &self.guard) {
-                    if matches!(e, PlayError::DecoderError(_)) {
+                self.sink.set_track_gain(tags::track_gain(&video.video_id));
+                let result = match self.preloaded.take() {
+                    // Already playing
+                    _ if started => Ok(()),
+                    Some((id, decoder)) if id == video.video_id => {
+                        self.sink.play_decoder(decoder, &self.guard);
+                        Ok(())
+                    }
+                    _ => open_song(&video.video_id)
+                        .map(|decoder| self.sink.play_decoder(decoder, &self.guard)),
+                };
+                stream::remove(&video.video_id);
+                if result.is_ok() {
+                    // The time of a link comes before the skipped intro
+                    let offset = START_OFFSETS.lock().unwrap().remove(&video.video_id);
+                    if let Some(offset) = offset.or_else(|| crate::markers::intro(&video.video_id))
+                    {
+                        self.sink.seek_to(offset);
+                    }
+                }
+                self.download_ahead();
+                // The first song waits for the user to press play
+                if result.is_ok() && self.start_paused {
+                    self.start_paused = false;
+                    self.sink.pause();
+                }
+                self.played = false;
+                if let Err(e) = result {
+                    if video.video_id.starts_with(LOCAL_FILE_PREFIX) {
+                        self.current = None;
+                        self.updater.send_or_log(ManagerMessage::Toast(format!(
+                            "Can't play {}: {}",
+                            video.title, e
+                        )));
+                    } else if matches!(e, PlayError::DecoderError(_)) {
                         // Cleaning the file
-                        DATABASE
-                            .write()
-                            .unwrap()
-                            .retain(|x| x.video_id != video.video_id);
-                        handle_error(
-                            &self.updater,
-                            "invalid cleaning MP4",
-                            std::fs::remove_file(k),
-                        );
-                        handle_error(
-                            &self.updater,
-                            "invalid cleaning JSON",
-                            std::fs::remove_file(
-                                CACHE_DIR.join(&format!("downloads/{}.json", &video.video_id)),
-                            ),
-                        );
+                        self.remove_from_cache(&video.video_id);
                         self.current = None;
-                        crate::write();
+                        if CONFIG.on_decode_error != DecodeErrorAction::Remove {
+                            if CONFIG.on_decode_error == DecodeErrorAction::Wait {
+                                self.waiting_for = Some(video.video_id.clone());
+                            }
+                            self.updater.send_or_log(ManagerMessage::Toast(format!(
+                                "Can't play {}, downloading it again",
+                                video.title
+                            )));
+                            start_task_unary(self.soundaction_sender.clone(), video);
+                        }
                     } else {
-                        self.updater
-                            .send(ManagerMessage::PassTo(
-                                Screens::DeviceLost,
-                                Box::new(ManagerMessage::Error(format!("{:?}", e))),
-                            ))
-                            .unwrap();
+                        self.updater.send_or_log(ManagerMessage::PassTo(
+                            Screens::DeviceLost,
+                            Box::new(ManagerMessage::Error(format!("{:?}", e))),
+                        ));
                     }
                 }
             } else if let Some(e) = self.current.take() {
-                self.previous.push(e);
+                self.previous.push(e.clone());
+                self.on_queue_end(e);
+            }
+        } else {
+            self.preload_next();
+        }
+        self.update_loop();
+        self.update_outro();
+        if let Some(video) = &self.current {
+            download::set_current(&video.video_id);
+        }
+        let playing = self.current.as_ref().map(|x| x.video_id.clone());
+        if playing != self.announced {
+            self.announced = playing.clone();
+            // The files given with `--file` aren't resumed, the last song of the cache is kept
+            if !matches!(&playing, Some(x) if x.starts_with(LOCAL_FILE_PREFIX)) {
+                resume::set(self.current.as_ref());
+            }
+            self.updater
+                .send_or_log(ManagerMessage::NowPlaying(playing).pass_to(Screens::Playlist));
+            if let Some(video) = &self.current {
+                super::hooks::on_track_change(video);
+            }
+        }
+        if let Some(video) = &self.current {
+            resume::set_position(&video.video_id, self.sink.elapsed());
+        }
+        title::update(self.current.as_ref());
+        #[cfg(unix)]
+        self.update_status();
+    }
+
+    /**
+     * Queues the song that just ended again as set by the repeat mode, the skipped songs were
+     * already taken from `current`
+     */
+    fn repeat_ended(&mut self) {
+        match self.repeat {
+            RepeatMode::Off => (),
+            RepeatMode::One => {
+                if let Some(e) = self.current.take() {
+                    self.queue.push_front(e);
+                    // The song takes the place of the next one in the unary songs
+                    self.unary_count += 1;
+                }
+            }
+            RepeatMode::All => {
+                if let Some(e) = &self.current {
+                    self.queue.push_back(e.clone());
+                }
+            }
+        }
+    }
+
+    /**
+     * Downloads again the next `download_ahead` songs of the queue whose audio is missing, like
+     * the streamed songs dropped from memory, so they don't have to be waited for when played
+     */
+    fn download_ahead(&self) {
+        let in_download = IN_DOWNLOAD.lock().unwrap().clone();
+        for video in self.queue.iter().take(CONFIG.download_ahead) {
+            let id = &video.video_id;
+            if id.starts_with(LOCAL_FILE_PREFIX)
+                || song_path(id).exists()
+                || stream::get(id).is_some()
+                || in_download.iter().any(|x| &x.video_id == id)
+            {
+                continue;
+            }
+            log_(format!("Downloading {} ahead", video.title));
+            download::restream(video.clone());
+        }
+    }
+
+    /**
+     * Returns true if the song queued in the sink started, the song that ended counts as played.
+     * The sink is stopped if the queue changed right before, so the new next song is played.
+     */
+    fn take_started(&mut self) -> bool {
+        // Stopping the sink removes the queued song
+        if !self.sink.has_queued() {
+            self.queued = None;
+            return false;
+        }
+        if !self.sink.advance() {
+            return false;
+        }
+        let queued = self.queued.take();
+        if let Some(video) = self.current.clone().filter(|_| !self.played) {
+            self.played = true;
+            self.track_played(&video);
+        }
+        if self.queue.front().map(|x| &x.video_id) == queued.as_ref() {
+            return true;
+        }
+        handle_error(&self.updater, "sink stop", self.sink.stop(&self.guard));
+        false
+    }
+
+    /**
+     * Returns true if the next song can be queued in the sink, the songs repeated, waited for or
+     * followed by a gap start only once the current one ended
+     */
+    fn is_gapless(&self) -> bool {
+        CONFIG.track_gap_ms == 0 && self.repeat != RepeatMode::One && self.waiting_for.is_none()
+    }
+
+    /**
+     * Opens the next song of the queue when the current one is about to end and queues it in the
+     * sink, so it starts without any delay
+     */
+    fn preload_next(&mut self) {
+        let next = self.queue.front().map(|x| &x.video_id);
+        // The queue changed since the song was opened
+        if self.preloaded.as_ref().map(|(id, _)| Some(id)) != Some(next) {
+            self.preloaded = None;
+        }
+        // If it already started, it is replaced on the next update by `take_started`
+        if self.queued.is_some()
+            && (self.queued.as_ref() != next || !self.is_gapless())
+            && self.sink.unqueue()
+        {
+            self.queued = None;
+        }
+        if CONFIG.preload_secs == 0 || self.preloaded.is_some() || self.queued.is_some() {
+            return;
+        }
+        let next = if let Some(e) = next {
+            e.clone()
+        } else {
+            return;
+        };
+        let remaining = self
+            .sink
+            .duration()
+            .map(|x| x - self.sink.elapsed().as_secs_f64());
+        if !matches!(remaining, Some(x) if x <= CONFIG.preload_secs as f64) {
+            return;
+        }
+        if IN_DOWNLOAD
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|x| x.video_id == next)
+        {
+            return;
+        }
+        // Errors are handled when the song is played
+        if let Ok(decoder) = open_song(&next) {
+            // The songs starting later are moved to their start once they are played
+            let offset = START_OFFSETS.lock().unwrap().contains_key(&next)
+                || crate::markers::intro(&next).is_some();
+            if self.is_gapless() && !offset {
+                self.sink.queue_decoder(decoder, tags::track_gain(&next));
+                self.queued = Some(next);
+            } else {
+                self.preloaded = Some((next, decoder));
+            }
+        }
+    }
+
+    /**
+     * Shares the state of the player with the control socket
+     */
+    #[cfg(unix)]
+    fn update_status(&self) {
+        *super::control::STATUS.write().unwrap() = super::control::PlayerStatus {
+            state: if self.sink.is_finished() {
+                "stopped"
+            } else if self.sink.is_paused() {
+                "paused"
+            } else {
+                "playing"
+            },
+            video_id: self.current.as_ref().map(|x| x.video_id.clone()),
+            title: self.current.as_ref().map(|x| x.title.clone()),
+            author: self.current.as_ref().map(|x| x.author.clone()),
+            elapsed: self.sink.elapsed().as_secs(),
+            duration: self.sink.duration().map(|x| x as u64).unwrap_or(0),
+            volume: self.sink.volume(),
+            queue: self.queue.len(),
+        };
+    }
+
+    /**
+     * Deletes the files of a downloaded song and removes it from the database
+     */
+    fn remove_from_cache(&self, video_id: &str) {
+        database::remove(&[video_id.to_owned()]);
+        for extension in ["mp4", "json"] {
+            let path = CACHE_DIR.join(format!("downloads/{}.{}", video_id, extension));
+            if path.exists() {
+                handle_error(
+                    &self.updater,
+                    "invalid cleaning file",
+                    std::fs::remove_file(path),
+                );
+            }
+        }
+        // The thumbnail may be missing, it isn't needed to play the song
+        let _ = std::fs::remove_file(thumbnails::path(video_id));
+    }
+
+    /**
+     * Recreates the audio output and plays the current song again from where it was, used when
+     * the output may be broken after the computer was suspended or the device was lost.
+     * Returns false if there is still no audio device.
+     */
+    pub fn reopen_output(&mut self) -> bool {
+        let position = self.sink.elapsed();
+        let paused = self.sink.is_paused();
+        let finished = self.sink.is_finished();
+        if let Some((sink, guard)) =
+            handle_error_option(&self.updater, "update player", self.sink.update())
+        {
+            self.sink = sink;
+            self.guard = guard;
+        } else {
+            return false;
+        }
+        self.preloaded = None;
+        let video = match &self.current {
+            Some(e) if !finished => e,
+            _ => return true,
+        };
+        match open_song(&video.video_id) {
+            Ok(decoder) => {
+                self.sink.play_decoder(decoder, &self.guard);
+                self.sink.seek_to(position);
+                if paused {
+                    self.sink.pause();
+                }
+            }
+            Err(e) => log_(format!("Can't play {} again: {}", video.title, e)),
+        }
+        true
+    }
+
+    /**
+     * Queues more songs of the station once the queue is finished
+     */
+    fn update_station(&mut self) {
+        let station = match &self.station {
+            Some(e) => e,
+            None => return,
+        };
+        if !self.queue.is_empty() || !download::is_idle() {
+            return;
+        }
+        if !station.refill(&self.soundaction_sender) {
+            self.updater.send_or_log(ManagerMessage::Toast(format!(
+                "No more songs for the station {}",
+                station.query
+            )));
+            self.station = None;
+        }
+    }
+
+    /**
+     * Called once when the last song of the queue is finished
+     */
+    fn on_queue_end(&mut self, last: Video) {
+        match CONFIG.on_queue_end {
+            QueueEndAction::Stop => (),
+            QueueEndAction::RepeatAll => {
+                self.queue.extend(self.previous.drain(..));
+            }
+            QueueEndAction::Radio => {
+                let api = match crate::api::api() {
+                    Some(e) => e,
+                    None => return,
+                };
+                let sender = self.soundaction_sender.clone();
+                tokio::task::spawn(async move {
+                    // The radio of a song is the mix playlist generated from it
+                    match crate::api::limited(
+                        api.browse_playlist(&format!("RDAMVM{}", last.video_id)),
+                    )
+                    .await
+                    {
+                        Ok(mut videos) => {
+                            // The songs often skipped are played last
+                            videos.sort_by_key(|x| crate::stats::skip_score(&x.video_id));
+                            for video in videos.into_iter().filter(|x| {
+                                x.video_id != last.video_id
+                                    && !crate::stats::is_disliked(&x.video_id)
+                            }) {
+                                download::add(video, &sender);
+                            }
+                        }
+                        Err(e) => log_(format!("Can't start the radio: {:?}", e)),
+                    }
+                });
+            }
+            QueueEndAction::Playlist => {
+                self.updater
+                    .send_or_log(ManagerMessage::ChangeState(Screens::Playlist));
             }
         }
     }
@@ -184,7 +854,10 @@ impl PlayerState {
                 album: self.current.as_ref().map(|video| video.album.as_str()),
                 artist: self.current.as_ref().map(|video| video.author.as_str()),
                 cover_url: None,
-                duration: None,
+                duration: self
+                    .sink
+                    .duration()
+                    .map(|x| Duration::from_secs_f64(x.max(0.0))),
             })?;
             if self.sink.is_finished() {
                 e.set_playback(MediaPlayback::Stopped)?;
@@ -200,33 +873,482 @@ impl PlayerState {
         }
         Ok(())
     }
+    /**
+     * Marks the current song as played once it reaches the played threshold or ends, this is the
+     * only place deciding whether a song counts as played
+     */
+    fn check_played(&mut self) {
+        let video = match &self.current {
+            Some(e) if !self.played => e,
+            _ => return,
+        };
+        let elapsed = self.sink.elapsed().as_secs_f64();
+        let threshold_secs = CONFIG.played_threshold_secs;
+        let threshold_percent = f64::from(CONFIG.played_threshold_percent) / 100.0;
+        if self.sink.is_finished()
+            || (threshold_secs != 0 && elapsed >= threshold_secs as f64)
+            || (threshold_percent != 0.0
+                && matches!(self.sink.duration(), Some(x) if elapsed >= x * threshold_percent))
+        {
+            self.played = true;
+            let video = video.clone();
+            self.track_played(&video);
+        }
+    }
+    /**
+     * Called once when a song counts as played
+     */
+    fn track_played(&self, video: &Video) {
+        crate::stats::record_completion(&video.video_id);
+        // The files given with `--file` can't be played again from the history
+        if !video.video_id.starts_with(LOCAL_FILE_PREFIX) {
+            crate::history::append(video);
+        }
+    }
+    /**
+     * Seeks back to the start of the A-B loop when its end is reached, the loop is removed when
+     * the song changes
+     */
+    fn update_loop(&mut self) {
+        if let Some((id, start, end)) = &self.ab_loop {
+            if self.current.as_ref().map(|x| &x.video_id) != Some(id) {
+                self.ab_loop = None;
+            } else if matches!(end, Some(end) if self.sink.elapsed() >= *end) {
+                self.sink.seek_to(*start);
+            }
+        }
+    }
+    /**
+     * Ends the current song at its skipped outro, as if it was finished
+     */
+    fn update_outro(&mut self) {
+        let video = match &self.current {
+            Some(e) if !self.sink.is_finished() => e,
+            _ => return,
+        };
+        if matches!(crate::markers::outro(&video.video_id), Some(end) if self.sink.elapsed() >= end)
+        {
+            handle_error(&self.updater, "sink stop", self.sink.stop(&self.guard));
+        }
+    }
+    /**
+     * Skips the start of the current song up to the current position each time it is played, or
+     * plays it again if it was skipped. Returns the message to show.
+     */
+    pub fn toggle_intro(&mut self) -> Option<String> {
+        let video = self.current.as_ref()?;
+        let position = self.sink.elapsed();
+        let mut message = String::new();
+        crate::markers::update(&video.video_id, |markers| {
+            if markers.intro_ms.take().is_some() {
+                message = format!("The intro of {} isn't skipped anymore", video.title);
+            } else {
+                markers.intro_ms = Some(position.as_millis() as u64);
+                message = format!(
+                    "{} will start at {}",
+                    video.title,
+                    format_time(position.as_secs())
+                );
+            }
+        });
+        Some(message)
+    }
+    /**
+     * Skips the end of the current song from the current position each time it is played, or
+     * plays it again if it was skipped. Returns the message to show.
+     */
+    pub fn toggle_outro(&mut self) -> Option<String> {
+        let video = self.current.as_ref()?;
+        let position = self.sink.elapsed();
+        let intro = crate::markers::intro(&video.video_id);
+        let mut message = String::new();
+        crate::markers::update(&video.video_id, |markers| {
+            if markers.outro_ms.take().is_some() {
+                message = format!("The outro of {} isn't skipped anymore", video.title);
+            } else if matches!(intro, Some(intro) if position <= intro) {
+                message = "The end of the song must be after its skipped intro".to_owned();
+            } else {
+                markers.outro_ms = Some(position.as_millis() as u64);
+                message = format!(
+                    "{} will end at {}",
+                    video.title,
+                    format_time(position.as_secs())
+                );
+            }
+        });
+        Some(message)
+    }
+    /**
+     * Sets the start of the A-B loop at the current position, returns the message to show
+     */
+    pub fn set_loop_start(&mut self) -> Option<String> {
+        let current = self.current.as_ref()?;
+        let start = self.sink.elapsed();
+        self.ab_loop = Some((current.video_id.clone(), start, None));
+        Some(format!(
+            "Loop start set at {}",
+            format_time(start.as_secs())
+        ))
+    }
+    /**
+     * Sets the end of the A-B loop at the current position, returns the message to show
+     */
+    pub fn set_loop_end(&mut self) -> Option<String> {
+        let (_, start, end) = self.ab_loop.as_mut()?;
+        let position = self.sink.elapsed();
+        if position <= *start {
+            return Some("The end of the loop must be after its start".to_owned());
+        }
+        *end = Some(position);
+        Some(format!(
+            "Looping from {} to {}",
+            format_time(start.as_secs()),
+            format_time(position.as_secs())
+        ))
+    }
+    pub fn clear_loop(&mut self) -> Option<String> {
+        self.ab_loop.take().map(|_| "Loop removed".to_owned())
+    }
+    pub fn toggle_remaining(&mut self) {
+        self.show_remaining = !self.show_remaining;
+        SETTINGS.write().unwrap().show_remaining = self.show_remaining;
+        crate::settings::save();
+    }
+    /**
+     * Switches to the next repeat mode, returns the message telling it
+     */
+    pub fn cycle_repeat(&mut self) -> String {
+        self.repeat = self.repeat.next();
+        SETTINGS.write().unwrap().repeat = self.repeat;
+        crate::settings::save();
+        match self.repeat.label() {
+            Some(label) => format!("Playing with {}", label),
+            None => "Repeat off".to_owned(),
+        }
+    }
+    /**
+     * Keeps the volume for the next start, written by the flusher with the other settings
+     */
+    fn remember_volume(&self) {
+        SETTINGS.write().unwrap().volume = Some(self.sink.volume() as u8);
+        crate::settings::save();
+    }
+    pub fn toggle_volume_panel(&mut self) {
+        self.hide_volume = !self.hide_volume;
+        SETTINGS.write().unwrap().hide_volume = self.hide_volume;
+        crate::settings::save();
+    }
+    /**
+     * Switches to the next equalizer preset of the config, returns its name to show it
+     */
+    pub fn cycle_equalizer(&mut self) -> Option<String> {
+        let presets = &CONFIG.equalizer_presets;
+        if presets.is_empty() {
+            return None;
+        }
+        let index = self.equalizer.map_or(0, |x| (x + 1) % presets.len());
+        self.equalizer = Some(index);
+        self.sink.set_equalizer(&presets[index].gains);
+        SETTINGS.write().unwrap().equalizer_preset = Some(presets[index].name.clone());
+        crate::settings::save();
+        Some(format!("Equalizer: {}", presets[index].name))
+    }
+    /**
+     * Checks that the file of the current song can still be opened, it is downloaded again and
+     * played next if it can't. Returns the message to show.
+     */
+    pub fn check_current(&mut self) -> Option<String> {
+        let video = self.current.clone()?;
+        if video.video_id.starts_with(LOCAL_FILE_PREFIX) {
+            return None;
+        }
+        // The audio of the streamed songs is dropped once they are played
+        if CONFIG.streaming && !song_path(&video.video_id).exists() {
+            return Some(format!(
+                "{} is streamed, it isn't in the cache",
+                video.title
+            ));
+        }
+        if open_song(&video.video_id).is_ok() {
+            return Some(format!("{} is in the cache", video.title));
+        }
+        self.remove_from_cache(&video.video_id);
+        let message = format!("Can't read {}, downloading it again", video.title);
+        start_task_unary(self.soundaction_sender.clone(), video);
+        Some(message)
+    }
+    /**
+     * Likes the current song, or removes the like if it was already liked. The liked songs are
+     * saved in a playlist and come first in the radio. Returns the message to show.
+     */
+    pub fn like_current(&mut self) -> Option<String> {
+        let video = self.current.clone()?;
+        let liked = crate::stats::get(&video.video_id).rating <= 0;
+        crate::stats::set_rating(&video.video_id, if liked { 1 } else { 0 });
+        self.update_favorites(&video, liked);
+        Some(if liked {
+            format!("Liked {}", video.title)
+        } else {
+            format!("Removed the like of {}", video.title)
+        })
+    }
+    /**
+     * Dislikes the current song and skips it, it isn't added by the radio and the stations
+     * anymore. Returns the message to show.
+     */
+    pub fn dislike_current(&mut self) -> Option<String> {
+        let video = self.current.clone()?;
+        crate::stats::set_rating(&video.video_id, -1);
+        self.update_favorites(&video, false);
+        self.apply_sound_action(SoundAction::Next(1));
+        Some(format!("Disliked {}", video.title))
+    }
+    fn update_favorites(&self, video: &Video, favorite: bool) {
+        match playlists::set_favorite(video, favorite) {
+            Ok(playlist) => self.updater.send_or_log(
+                ManagerMessage::AddElementToChooser((playlist.name, playlist.videos))
+                    .pass_to(Screens::Playlist),
+            ),
+            Err(e) => self.updater.send_or_log(ManagerMessage::Toast(format!(
+                "Can't save the liked songs: {}",
+                e
+            ))),
+        }
+    }
+    /**
+     * Moves the current song or changes the song as decided by `seek_forward` or
+     * `seek_backward`
+     */
+    fn apply_seek(&mut self, outcome: SeekOutcome) {
+        match outcome {
+            SeekOutcome::To(position) => self.sink.seek_to(position),
+            SeekOutcome::Previous => self.apply_sound_action(SoundAction::Previous(1)),
+            SeekOutcome::Next => self.apply_sound_action(SoundAction::Next(1)),
+            SeekOutcome::StopAtEnd => {
+                self.sink.seek_to_end();
+                if !self.sink.is_paused() {
+                    self.sink.pause();
+                }
+            }
+            SeekOutcome::Stay => (),
+        }
+    }
     pub fn apply_sound_action(&mut self, e: SoundAction) {
         match e {
-            SoundAction::Backward => self.sink.seek_bw(),
-            SoundAction::Forward => self.sink.seek_fw(),
+            SoundAction::Backward => {
+                if self.current.is_none() || self.sink.is_finished() {
+                    return;
+                }
+                let outcome = seek_backward(self.sink.elapsed(), CONFIG.seek_before_start);
+                self.apply_seek(outcome);
+            }
+            SoundAction::Seek(seconds) => {
+                if self.current.is_none() || self.sink.is_finished() {
+                    return;
+                }
+                // The duration of a stream still loading isn't known
+                if let Some(duration) = self.sink.duration() {
+                    let target = self.sink.elapsed().as_secs_f64() + seconds as f64;
+                    self.sink.seek_to(seek_target(target, duration));
+                }
+            }
+            SoundAction::SeekTo(position) => {
+                if self.current.is_none() || self.sink.is_finished() {
+                    return;
+                }
+                if let Some(duration) = self.sink.duration() {
+                    self.sink
+                        .seek_to(seek_target(position.as_secs_f64(), duration));
+                }
+            }
+            SoundAction::Forward => {
+                if self.current.is_none() || self.sink.is_finished() {
+                    return;
+                }
+                let outcome = seek_forward(
+                    self.sink.elapsed(),
+                    self.sink.duration(),
+                    CONFIG.seek_past_end,
+                );
+                self.apply_seek(outcome);
+            }
+            SoundAction::ChapterForward => {
+                let elapsed = self.sink.elapsed().as_secs() as u32;
+                if let Some(chapter) = self
+                    .current
+                    .as_ref()
+                    .and_then(|x| x.chapters.iter().find(|x| x.start > elapsed))
+                {
+                    self.sink.seek_to(Duration::from_secs(chapter.start as u64));
+                }
+            }
+            SoundAction::ChapterBackward => {
+                // Like the previous track button, go to the start of the current chapter first
+                let elapsed = self.sink.elapsed().as_secs() as u32;
+                if let Some(chapter) = self
+                    .current
+                    .as_ref()
+                    .and_then(|x| x.chapters.iter().rev().find(|x| x.start + 2 < elapsed))
+                {
+                    self.sink.seek_to(Duration::from_secs(chapter.start as u64));
+                }
+            }
             SoundAction::PlayPause => self.sink.toggle_playback(),
             SoundAction::Cleanup => {
                 self.queue.clear();
                 self.previous.clear();
                 self.current = None;
+                self.waiting_for = None;
+                self.unary_count = 0;
+                self.list_offset = 0;
+                self.station = None;
                 handle_error(&self.updater, "sink stop", self.sink.stop(&self.guard));
             }
-            SoundAction::Plus => self.sink.volume_up(),
-            SoundAction::Minus => self.sink.volume_down(),
+            SoundAction::RemoveFromQueue(index) => {
+                let video = match self.queue.remove(index) {
+                    Some(e) => e,
+                    None => return,
+                };
+                if index < self.unary_count {
+                    self.unary_count -= 1;
+                }
+                // The song may be queued twice or be the current one
+                let needed = self.current.iter().chain(&self.queue).any(|x| x == &video);
+                if !needed && download::cancel(&video.video_id) {
+                    log_(format!("Cancelled the download of {}", video.title));
+                }
+            }
+            SoundAction::MoveInQueue { from, to } => {
+                if to >= self.queue.len() {
+                    return;
+                }
+                let video = match self.queue.remove(from) {
+                    Some(e) => e,
+                    None => return,
+                };
+                self.queue.insert(to, video);
+                // The song joins or leaves the unary songs at the start of the queue
+                if from < self.unary_count && to >= self.unary_count {
+                    self.unary_count -= 1;
+                } else if from >= self.unary_count && to < self.unary_count {
+                    self.unary_count += 1;
+                }
+            }
+            SoundAction::Station(query) => self.station = query.map(Station::new),
+            // Changing the volume while muted starts from the volume before the mute
+            SoundAction::Plus => {
+                let volume = self.muted.take().unwrap_or_else(|| self.sink.volume());
+                self.sink.set_volume(volume + i32::from(CONFIG.volume_step));
+                self.remember_volume();
+            }
+            SoundAction::SetVolume(volume) => {
+                self.muted = None;
+                self.sink.set_volume(volume);
+                self.remember_volume();
+            }
+            // The volume before the mute stays the saved one
+            SoundAction::ToggleMute => match self.muted.take() {
+                Some(volume) => self.sink.set_volume(volume),
+                None => {
+                    self.muted = Some(self.sink.volume());
+                    self.sink.set_volume(0);
+                }
+            },
+            SoundAction::DownloadError(e) => self.updater.send_or_log(ManagerMessage::Toast(e)),
+            SoundAction::LimitCache if CONFIG.max_cache_mb != 0 => {
+                // The songs of the queue and the ones being downloaded are kept
+                let mut keep = self
+                    .previous
+                    .iter()
+                    .chain(self.current.iter())
+                    .chain(self.queue.iter())
+                    .map(|x| x.video_id.clone())
+                    .collect::<HashSet<_>>();
+                keep.extend(
+                    IN_DOWNLOAD
+                        .lock()
+                        .unwrap()
+                        .iter()
+                        .map(|x| x.video_id.clone()),
+                );
+                let updater = self.updater.clone();
+                tokio::task::spawn_blocking(move || {
+                    if !eviction::enforce_limit(&keep).is_empty() {
+                        updater.send_or_log(
+                            ManagerMessage::AddElementToChooser((
+                                "Local musics".to_owned(),
+                                DATABASE.read().unwrap().clone(),
+                            ))
+                            .pass_to(Screens::Playlist),
+                        );
+                    }
+                });
+            }
+            SoundAction::LimitCache => {}
+            SoundAction::DownloadsFinished => {
+                if let Some(chime) = &CONFIG.download_chime {
+                    let path = (chime != "beep").then(|| Path::new(chime));
+                    handle_error(
+                        &self.updater,
+                        "download chime",
+                        self.sink.chime(path, &self.guard),
+                    );
+                }
+            }
+            SoundAction::Minus => {
+                let volume = self.muted.take().unwrap_or_else(|| self.sink.volume());
+                self.sink.set_volume(volume - i32::from(CONFIG.volume_step));
+                self.remember_volume();
+            }
             SoundAction::Next(a) => {
+                self.waiting_for = None;
+                if let Some(e) = &self.current {
+                    if !self.played {
+                        crate::stats::record_skip(&e.video_id);
+                    }
+                }
                 handle_error(&self.updater, "sink stop", self.sink.stop(&self.guard));
 
                 if let Some(e) = self.current.take() {
+                    // The skipped songs stay in the loop
+                    if self.repeat == RepeatMode::All {
+                        self.queue.push_back(e.clone());
+                    }
                     self.previous.push(e);
                 }
                 for _ in 1..a {
                     self.previous.push(self.queue.pop_front().unwrap());
                 }
+                self.unary_count = self.unary_count.saturating_sub(a.saturating_sub(1));
             }
             SoundAction::PlayVideo(video) => {
-                self.queue.push_back(video);
+                let existing = self.queue.iter().position(|x| x.video_id == video.video_id);
+                match (CONFIG.on_duplicate, existing) {
+                    (DuplicateAction::Skip, Some(_)) => {
+                        self.updater.send_or_log(ManagerMessage::Toast(format!(
+                            "{} is already in the queue",
+                            video.title
+                        )));
+                        return;
+                    }
+                    (DuplicateAction::Move, Some(index)) => {
+                        self.queue.remove(index);
+                        if index < self.unary_count {
+                            self.unary_count -= 1;
+                        }
+                    }
+                    _ => (),
+                }
+                if CONFIG.max_queue_len != 0 && self.queue.len() >= CONFIG.max_queue_len {
+                    log_(format!("The queue is full, {} isn't added", video.title));
+                } else {
+                    self.queue.push_back(video);
+                }
             }
             SoundAction::Previous(a) => {
+                self.waiting_for = None;
+                // The songs played again come before the pending unary songs
+                self.unary_count = 0;
                 for _ in 0..a {
                     if let Some(e) = self.previous.pop() {
                         if let Some(c) = self.current.take() {
@@ -255,8 +1377,59 @@ impl PlayerState {
                     self.sink.pause();
                 }
             }
+            SoundAction::RemoveFromCache(video) => {
+                // The sink is still reading the file of the current song
+                if matches!(&self.current, Some(e) if e.video_id == video.video_id) {
+                    self.updater.send_or_log(ManagerMessage::Toast(
+                        "Can't remove the song being played".to_owned(),
+                    ));
+                    return;
+                }
+                if let Some(index) = self.queue.iter().position(|x| x.video_id == video.video_id) {
+                    self.queue.remove(index);
+                    if index < self.unary_count {
+                        self.unary_count -= 1;
+                    }
+                }
+                self.previous.retain(|x| x.video_id != video.video_id);
+                self.remove_from_cache(&video.video_id);
+                self.updater
+                    .send_or_log(ManagerMessage::Toast(format!("Removed {}", video.title)));
+                self.updater.send_or_log(
+                    ManagerMessage::AddElementToChooser((
+                        "Local musics".to_owned(),
+                        DATABASE.read().unwrap().clone(),
+                    ))
+                    .pass_to(Screens::Playlist),
+                );
+            }
+            SoundAction::Resume(video, position) => {
+                set_start_offset(&video.video_id, position);
+                if CONFIG.resume_session == ResumeSession::Paused {
+                    self.start_paused = true;
+                }
+                self.updater.send_or_log(ManagerMessage::Toast(format!(
+                    "Resuming {} at {}",
+                    video.title,
+                    format_time(position.as_secs())
+                )));
+                self.apply_sound_action(SoundAction::PlayVideoUnary(video));
+            }
             SoundAction::PlayVideoUnary(video) => {
-                self.queue.push_front(video);
+                if CONFIG.unary_play == UnaryPlayPolicy::Replace {
+                    self.station = None;
+                    download::clean(self.soundaction_sender.clone());
+                }
+                let interrupt = queue_unary(
+                    &mut self.queue,
+                    &mut self.unary_count,
+                    video,
+                    CONFIG.unary_play,
+                    CONFIG.max_queue_len,
+                );
+                if interrupt && self.current.is_some() {
+                    self.apply_sound_action(SoundAction::Next(1));
+                }
             }
         }
     }
@@ -278,30 +1451,39 @@ fn connect(mpris: &mut MediaControls, sender: Arc<Sender<SoundAction>>) -> Resul
         souvlaki::MediaControlEvent::Toggle
         | souvlaki::MediaControlEvent::Play
         | souvlaki::MediaControlEvent::Pause => {
-            sender.send(SoundAction::PlayPause).unwrap();
+            sender.send_or_log(SoundAction::PlayPause);
         }
         souvlaki::MediaControlEvent::Next => {
-            sender.send(SoundAction::Next(1)).unwrap();
+            sender.send_or_log(SoundAction::Next(1));
         }
         souvlaki::MediaControlEvent::Previous => {
-            sender.send(SoundAction::Previous(1)).unwrap();
+            sender.send_or_log(SoundAction::Previous(1));
         }
         souvlaki::MediaControlEvent::Stop => {
-            sender.send(SoundAction::Cleanup).unwrap();
+            sender.send_or_log(SoundAction::Cleanup);
         }
         souvlaki::MediaControlEvent::Seek(a) => match a {
             souvlaki::SeekDirection::Forward => {
-                sender.send(SoundAction::Forward).unwrap();
+                sender.send_or_log(SoundAction::Forward);
             }
             souvlaki::SeekDirection::Backward => {
-                sender.send(SoundAction::Backward).unwrap();
+                sender.send_or_log(SoundAction::Backward);
             }
         },
-        souvlaki::MediaControlEvent::SeekBy(_, _) => todo!(),
-        souvlaki::MediaControlEvent::SetPosition(_) => todo!(),
-        souvlaki::MediaControlEvent::OpenUri(_) => todo!(),
-        souvlaki::MediaControlEvent::Raise => todo!(),
+        souvlaki::MediaControlEvent::SeekBy(direction, offset) => {
+            let seconds = offset.as_secs() as i64;
+            sender.send_or_log(SoundAction::Seek(match direction {
+                souvlaki::SeekDirection::Forward => seconds,
+                souvlaki::SeekDirection::Backward => -seconds,
+            }));
+        }
+        souvlaki::MediaControlEvent::SetPosition(MediaPosition(position)) => {
+            sender.send_or_log(SoundAction::SeekTo(position));
+        }
+        // The terminal can't be raised and the songs are only opened from inside the app
+        souvlaki::MediaControlEvent::OpenUri(_) | souvlaki::MediaControlEvent::Raise => (),
         souvlaki::MediaControlEvent::Quit => {
+            crate::flusher::flush();
             exit(0);
         }
     })
@@ -313,7 +1495,7 @@ pub fn get_action(
     previous: &[Video],
     current: &Option<Video>,
 ) -> Option<MusicStatusAction> {
-    let dw_len = IN_DOWNLOAD.lock().unwrap().len();
+    let dw_len = download::download_lines();
     if index < dw_len {
         return Some(MusicStatusAction::Downloading);
     }
@@ -338,14 +1520,16 @@ pub fn get_action(
 
 pub fn generate_music<'a>(
     lines: usize,
+    width: usize,
     queue: &'a VecDeque<Video>,
     previous: &'a [Video],
     current: &'a Option<Video>,
     sink: &'a Player,
 ) -> Vec<ListItem<'a>> {
-    let download_style: Style = Style::default()
-        .fg(MusicStatus::Downloading.colors().0)
-        .bg(MusicStatus::Downloading.colors().1);
+    let status_style = |status: MusicStatus| {
+        let (fg, bg) = status.colors();
+        Style::default().fg(fg).bg(bg)
+    };
 
     let previous_style: Style = Style::default()
         .fg(MusicStatus::Previous.colors().0)
@@ -363,43 +1547,373 @@ pub fn generate_music<'a>(
         .bg(MusicStatus::Next.colors().1);
     let mut music = Vec::with_capacity(50);
     {
+        let transferring = download::TRANSFERRING.lock().unwrap();
         music.extend(IN_DOWNLOAD.lock().unwrap().iter().map(|e| {
-            ListItem::new(format!(
-                " {} {} | {}",
-                MusicStatus::Downloading.character(),
-                e.author,
-                e.title
-            ))
-            .style(download_style)
-        }));
-        music.extend(previous.iter().rev().take(3).rev().map(|e| {
-            ListItem::new(format!(
-                " {} {} | {}",
-                MusicStatus::Previous.character(),
-                e.author,
-                e.title
-            ))
-            .style(previous_style)
+            if !transferring.contains(&e.video_id) {
+                let status = MusicStatus::Connecting;
+                return song_row(status.character(), e, width).style(status_style(status));
+            }
+            let status = MusicStatus::Downloading;
+            let marker = match download::percent(&e.video_id) {
+                Some(percent) => format!("{} {}%", status.character(), percent),
+                None => status.character().to_string(),
+            };
+            song_row(marker, e, width).style(status_style(status))
         }));
+        let queued = download::queued_count();
+        if queued != 0 {
+            music.push(
+                row(
+                    ellipsize(
+                        &format!(
+                            " {} {} songs waiting to be downloaded",
+                            MusicStatus::Queued.character(),
+                            queued
+                        ),
+                        width,
+                    ),
+                    String::new(),
+                )
+                .style(status_style(MusicStatus::Queued)),
+            );
+        }
+        music.extend(
+            previous.iter().rev().take(3).rev().map(|e| {
+                song_row(MusicStatus::Previous.character(), e, width).style(previous_style)
+            }),
+        );
         if let Some(e) = current {
             let status = if sink.is_paused() {
                 (MusicStatus::Paused.character(), paused_style)
+            } else if sink.elapsed().is_zero() {
+                (
+                    MusicStatus::Buffering.character(),
+                    status_style(MusicStatus::Buffering),
+                )
             } else {
                 (MusicStatus::Playing.character(), playing_style)
             };
-            music.push(
-                ListItem::new(format!(" {} {} | {}", status.0, e.author, e.title)).style(status.1),
-            );
+            music.push(song_row(status.0, e, width).style(status.1));
         }
-        music.extend(queue.iter().take(lines + 4).map(|e| {
-            ListItem::new(format!(
-                " {} {} | {}",
-                MusicStatus::Next.character(),
-                e.author,
-                e.title
-            ))
-            .style(next_style)
-        }));
+        music.extend(
+            queue
+                .iter()
+                .take(lines + 4)
+                .map(|e| song_row(MusicStatus::Next.character(), e, width).style(next_style)),
+        );
     }
     music
 }
+
+/**
+ * Number of lines of each song of the playlist, see `queue_density` in the config
+ */
+pub fn row_height() -> usize {
+    match CONFIG.queue_density {
+        QueueDensity::Compact => 1,
+        QueueDensity::Expanded => 2,
+    }
+}
+
+/**
+ * A row of the playlist, the second line is only shown in the expanded layout
+ */
+fn row<'a>(first: String, second: String) -> ListItem<'a> {
+    match CONFIG.queue_density {
+        QueueDensity::Compact => ListItem::new(first),
+        QueueDensity::Expanded => ListItem::new(vec![Spans::from(first), Spans::from(second)]),
+    }
+}
+
+/**
+ * The row of a song in the playlist, its album and duration are below it in the expanded layout
+ */
+fn song_row<'a>(marker: impl std::fmt::Display, video: &Video, width: usize) -> ListItem<'a> {
+    let details = [video.album.trim(), video.duration.trim()]
+        .into_iter()
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<_>>()
+        .join(" · ");
+    row(
+        ellipsize(
+            &format!(" {} {} | {}", marker, video.author, video.title),
+            width,
+        ),
+        ellipsize(&format!("   {}", details), width),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, time::Duration};
+
+    use ytpapi::Video;
+
+    use super::{pop_next, queue_unary, seek_backward, seek_forward, seek_target, SeekOutcome};
+    use crate::config::{SeekEndAction, SeekStartAction, UnaryPlayPolicy};
+
+    fn video(id: &str) -> Video {
+        Video {
+            title: id.to_owned(),
+            author: String::new(),
+            album: String::new(),
+            video_id: id.to_owned(),
+            duration: String::new(),
+            chapters: Vec::new(),
+        }
+    }
+
+    fn queue(ids: &[&str]) -> VecDeque<Video> {
+        ids.iter().map(|x| video(x)).collect()
+    }
+
+    fn ids(queue: &VecDeque<Video>) -> Vec<&str> {
+        queue.iter().map(|x| x.video_id.as_str()).collect()
+    }
+
+    #[test]
+    fn unary_play_resumes_the_queue() {
+        let mut songs = queue(&["a", "b", "c"]);
+        let mut unary_count = 0;
+        queue_unary(
+            &mut songs,
+            &mut unary_count,
+            video("x"),
+            UnaryPlayPolicy::Next,
+            0,
+        );
+        assert_eq!(ids(&songs), ["x", "a", "b", "c"]);
+        assert_eq!(unary_count, 1);
+        let played = std::iter::from_fn(|| pop_next(&mut songs, &mut unary_count))
+            .map(|x| x.video_id)
+            .collect::<Vec<_>>();
+        assert_eq!(played, ["x", "a", "b", "c"]);
+        assert_eq!(unary_count, 0);
+    }
+
+    #[test]
+    fn unary_plays_keep_their_order() {
+        let mut songs = queue(&["a", "b"]);
+        let mut unary_count = 0;
+        queue_unary(
+            &mut songs,
+            &mut unary_count,
+            video("x"),
+            UnaryPlayPolicy::Next,
+            0,
+        );
+        queue_unary(
+            &mut songs,
+            &mut unary_count,
+            video("y"),
+            UnaryPlayPolicy::Next,
+            0,
+        );
+        assert_eq!(ids(&songs), ["x", "y", "a", "b"]);
+        // A unary song requested while another one plays still comes before the queue
+        assert_eq!(
+            pop_next(&mut songs, &mut unary_count).unwrap().video_id,
+            "x"
+        );
+        queue_unary(
+            &mut songs,
+            &mut unary_count,
+            video("z"),
+            UnaryPlayPolicy::Next,
+            0,
+        );
+        assert_eq!(ids(&songs), ["y", "z", "a", "b"]);
+        assert_eq!(unary_count, 2);
+    }
+
+    #[test]
+    fn unary_play_interrupts_then_resumes_the_queue() {
+        let mut songs = queue(&["a", "b"]);
+        let mut unary_count = 0;
+        let interrupt = queue_unary(
+            &mut songs,
+            &mut unary_count,
+            video("x"),
+            UnaryPlayPolicy::Now,
+            0,
+        );
+        assert!(interrupt);
+        assert_eq!(
+            pop_next(&mut songs, &mut unary_count).unwrap().video_id,
+            "x"
+        );
+        assert_eq!(ids(&songs), ["a", "b"]);
+        assert_eq!(unary_count, 0);
+    }
+
+    #[test]
+    fn unary_play_moves_a_queued_song() {
+        let mut songs = queue(&["a", "b", "c"]);
+        let mut unary_count = 0;
+        queue_unary(
+            &mut songs,
+            &mut unary_count,
+            video("c"),
+            UnaryPlayPolicy::Next,
+            0,
+        );
+        assert_eq!(ids(&songs), ["c", "a", "b"]);
+        assert_eq!(unary_count, 1);
+    }
+
+    #[test]
+    fn unary_play_drops_the_last_song_of_a_full_queue() {
+        let mut songs = queue(&["a", "b"]);
+        let mut unary_count = 0;
+        queue_unary(
+            &mut songs,
+            &mut unary_count,
+            video("x"),
+            UnaryPlayPolicy::Next,
+            2,
+        );
+        assert_eq!(ids(&songs), ["x", "a"]);
+    }
+
+    #[test]
+    fn unary_play_next_waits_for_the_current_song() {
+        let mut songs = queue(&["a", "b"]);
+        let mut unary_count = 0;
+        let interrupt = queue_unary(
+            &mut songs,
+            &mut unary_count,
+            video("x"),
+            UnaryPlayPolicy::Next,
+            0,
+        );
+        assert!(!interrupt);
+        queue_unary(
+            &mut songs,
+            &mut unary_count,
+            video("y"),
+            UnaryPlayPolicy::Next,
+            0,
+        );
+        assert_eq!(ids(&songs), ["x", "y", "a", "b"]);
+    }
+
+    #[test]
+    fn unary_play_now_plays_it_first_and_keeps_the_queue() {
+        let mut songs = queue(&["a", "b"]);
+        let mut unary_count = 0;
+        let interrupt = queue_unary(
+            &mut songs,
+            &mut unary_count,
+            video("x"),
+            UnaryPlayPolicy::Now,
+            0,
+        );
+        assert!(interrupt);
+        // The last request is played first
+        queue_unary(
+            &mut songs,
+            &mut unary_count,
+            video("y"),
+            UnaryPlayPolicy::Now,
+            0,
+        );
+        assert_eq!(ids(&songs), ["y", "x", "a", "b"]);
+        assert_eq!(unary_count, 2);
+    }
+
+    #[test]
+    fn unary_play_replace_forgets_the_queue() {
+        let mut songs = queue(&["a", "b"]);
+        let mut unary_count = 1;
+        let interrupt = queue_unary(
+            &mut songs,
+            &mut unary_count,
+            video("x"),
+            UnaryPlayPolicy::Replace,
+            0,
+        );
+        assert!(interrupt);
+        assert_eq!(ids(&songs), ["x"]);
+        assert_eq!(unary_count, 1);
+        assert_eq!(
+            pop_next(&mut songs, &mut unary_count).unwrap().video_id,
+            "x"
+        );
+        assert!(songs.is_empty());
+    }
+
+    #[test]
+    fn forward_near_the_end_follows_seek_past_end() {
+        let elapsed = Duration::from_secs(58);
+        let stay = seek_forward(elapsed, Some(60.0), SeekEndAction::Clamp);
+        assert_eq!(stay, SeekOutcome::Stay);
+        let next = seek_forward(elapsed, Some(60.0), SeekEndAction::Next);
+        assert_eq!(next, SeekOutcome::Next);
+        let stop = seek_forward(elapsed, Some(60.0), SeekEndAction::Stop);
+        assert_eq!(stop, SeekOutcome::StopAtEnd);
+    }
+
+    #[test]
+    fn forward_inside_the_song_ignores_seek_past_end() {
+        let outcome = seek_forward(Duration::from_secs(10), Some(60.0), SeekEndAction::Next);
+        assert_eq!(outcome, SeekOutcome::To(Duration::from_secs(15)));
+        // Landing exactly on the end is still inside the song
+        let outcome = seek_forward(Duration::from_secs(55), Some(60.0), SeekEndAction::Next);
+        assert_eq!(outcome, SeekOutcome::To(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn forward_waits_for_the_duration() {
+        let outcome = seek_forward(Duration::from_secs(10), None, SeekEndAction::Next);
+        assert_eq!(outcome, SeekOutcome::Stay);
+    }
+
+    #[test]
+    fn seeking_to_the_end_stops_at_the_end() {
+        assert_eq!(seek_target(60.0, 60.0), Duration::from_secs(60));
+        assert_eq!(seek_target(90.0, 60.0), Duration::from_secs(60));
+        assert_eq!(seek_target(-5.0, 60.0), Duration::ZERO);
+        // The duration of a very short song can be below 0 once the end margin is removed
+        assert_eq!(seek_target(10.0, -0.2), Duration::ZERO);
+    }
+
+    #[test]
+    fn backward_at_the_start_follows_seek_before_start() {
+        let elapsed = Duration::from_millis(500);
+        let stay = seek_backward(elapsed, SeekStartAction::Clamp);
+        assert_eq!(stay, SeekOutcome::Stay);
+        let previous = seek_backward(elapsed, SeekStartAction::Previous);
+        assert_eq!(previous, SeekOutcome::Previous);
+    }
+
+    #[test]
+    fn backward_near_the_start_goes_to_the_start() {
+        // Even when the previous song would be played from the start of the song
+        let outcome = seek_backward(Duration::from_secs(3), SeekStartAction::Previous);
+        assert_eq!(outcome, SeekOutcome::To(Duration::ZERO));
+        let outcome = seek_backward(Duration::from_secs(30), SeekStartAction::Clamp);
+        assert_eq!(outcome, SeekOutcome::To(Duration::from_secs(25)));
+    }
+
+    #[test]
+    fn player_forward_stops_past_the_end() {
+        let position = player::forward_position(Duration::from_secs(10), 60.0);
+        assert_eq!(position, Some(Duration::from_secs(15)));
+        let position = player::forward_position(Duration::from_secs(56), 60.0);
+        assert_eq!(position, None);
+        assert_eq!(
+            player::forward_position(Duration::from_secs(60), 60.0),
+            None
+        );
+    }
+
+    #[test]
+    fn player_backward_stops_at_the_start() {
+        let position = player::backward_position(Duration::from_secs(20));
+        assert_eq!(position, Some(Duration::from_secs(15)));
+        let position = player::backward_position(Duration::from_secs(3));
+        assert_eq!(position, Some(Duration::ZERO));
+        assert_eq!(player::backward_position(Duration::from_millis(500)), None);
+        assert_eq!(player::backward_position(Duration::ZERO), None);
+    }
+}
@@ -0,0 +1,95 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
+
+use flume::Sender;
+use ytpapi::SearchContinuation;
+
+use crate::{api, config::CONFIG, SoundAction};
+
+use super::{download, logger::log_};
+
+// The page of results queued next by a station
+enum Page {
+    First,
+    Next(SearchContinuation),
+    // A page is being fetched
+    Fetching,
+    // Every result was queued
+    Done,
+}
+
+/**
+ * A radio playing the results of a search, a new page of results is queued each time the queue
+ * is finished
+ */
+pub struct Station {
+    pub query: String,
+    page: Arc<Mutex<Page>>,
+    // The songs already queued, a song found on several pages is played once
+    queued: Arc<Mutex<HashSet<String>>>,
+}
+
+impl Station {
+    pub fn new(query: String) -> Self {
+        Self {
+            query,
+            page: Arc::new(Mutex::new(Page::First)),
+            queued: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /**
+     * Queues the next page of results, returns false once there are no more results
+     */
+    pub fn refill(&self, sender: &Arc<Sender<SoundAction>>) -> bool {
+        let continuation = match std::mem::replace(&mut *self.page.lock().unwrap(), Page::Fetching)
+        {
+            Page::First => None,
+            Page::Next(e) => Some(e),
+            Page::Fetching => return true,
+            Page::Done => {
+                *self.page.lock().unwrap() = Page::Done;
+                return false;
+            }
+        };
+        let api = match api::api() {
+            Some(e) => e,
+            None => {
+                *self.page.lock().unwrap() = Page::Done;
+                return false;
+            }
+        };
+        let query = self.query.clone();
+        let page = self.page.clone();
+        let queued = self.queued.clone();
+        let sender = sender.clone();
+        tokio::task::spawn(async move {
+            let result = match &continuation {
+                None => api::limited(api.search_page_with_type(&query, CONFIG.search_type)).await,
+                Some(e) => api::limited(api.search_continuation(e)).await,
+            };
+            *page.lock().unwrap() = match result {
+                Ok((videos, next)) => {
+                    let mut queued = queued.lock().unwrap();
+                    for video in videos {
+                        if crate::stats::is_disliked(&video.video_id) {
+                            continue;
+                        }
+                        if queued.insert(video.video_id.clone()) {
+                            download::add(video, &sender);
+                        }
+                    }
+                    next.map_or(Page::Done, Page::Next)
+                }
+                Err(e) => {
+                    log_(format!("Can't fetch the station {}", query));
+                    api::report_error(&e);
+                    Page::Done
+                }
+            };
+        });
+        true
+    }
+}
@@ -0,0 +1,132 @@
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use once_cell::sync::Lazy;
+use reqwest::header::RANGE;
+use rustube::{Error, Id};
+use ytpapi::{parse_chapters, Chapter, Video};
+
+use super::{
+    backend::audio_stream,
+    bandwidth,
+    download::{write_durably, PROGRESS, TRANSFERRING},
+    logger::{log_download, LogLevel},
+};
+
+// The audio of the streamed songs waiting to be played, by video id
+static BUFFERS: Lazy<Mutex<HashMap<String, Arc<[u8]>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Number of times a transfer is resumed after the connection is lost
+const RESUME_ATTEMPTS: usize = 5;
+
+/**
+ * Fetches the audio of the video in memory instead of writing it in the downloads, and returns
+ * the chapters found in its description. The whole song is buffered before being played.
+ */
+pub async fn fetch(video: &Video) -> Result<Vec<Chapter>, Error> {
+    let id = video.video_id.as_str();
+    let video = rustube::Video::from_id(Id::from_str(id)?.into_owned()).await?;
+    let chapters = parse_chapters(&video.video_details().short_description);
+    let url = audio_stream(&video, id)?.signature_cipher.url.to_string();
+    TRANSFERRING.lock().unwrap().insert(id.to_owned());
+    let result = buffer(id, &url).await;
+    TRANSFERRING.lock().unwrap().remove(id);
+    PROGRESS.lock().unwrap().remove(id);
+    BUFFERS
+        .lock()
+        .unwrap()
+        .insert(id.to_owned(), result?.into());
+    Ok(chapters)
+}
+
+/**
+ * Downloads the stream to a file through the bandwidth limit. The audio is written once complete,
+ * so a failed transfer doesn't leave a partial song in the downloads.
+ */
+pub async fn save(id: &str, url: &str, path: &Path) -> Result<(), Error> {
+    let data = buffer(id, url).await;
+    PROGRESS.lock().unwrap().remove(id);
+    write_durably(path, &data?).map_err(Error::Io)
+}
+
+/**
+ * Reads the whole stream, the transfer is resumed where it stopped when the connection is lost
+ */
+async fn buffer(id: &str, url: &str) -> Result<Vec<u8>, Error> {
+    let client = reqwest::Client::new();
+    let mut data = Vec::new();
+    let mut attempts = 0;
+    loop {
+        match read_from(&client, id, url, &mut data).await {
+            Ok(()) => return Ok(data),
+            Err(e) if attempts < RESUME_ATTEMPTS => {
+                attempts += 1;
+                log_download(
+                    LogLevel::Debug,
+                    format!(
+                        "Stream of {} interrupted after {} bytes, resuming: {}",
+                        id,
+                        data.len(),
+                        e
+                    ),
+                );
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            Err(e) => return Err(Error::Fatal(e.to_string())),
+        }
+    }
+}
+
+async fn read_from(
+    client: &reqwest::Client,
+    id: &str,
+    url: &str,
+    data: &mut Vec<u8>,
+) -> Result<(), reqwest::Error> {
+    let mut response = client
+        .get(url)
+        .header(RANGE, format!("bytes={}-", data.len()))
+        .send()
+        .await?
+        .error_for_status()?;
+    // The length of the response is what is left after the resumed part
+    let size = response.content_length().map(|x| x + data.len() as u64);
+    while let Some(chunk) = response.chunk().await? {
+        bandwidth::throttle(chunk.len()).await;
+        data.extend_from_slice(&chunk);
+        PROGRESS
+            .lock()
+            .unwrap()
+            .insert(id.to_owned(), (data.len() as u64, size));
+    }
+    Ok(())
+}
+
+/**
+ * Returns the audio of a streamed song
+ */
+pub fn get(video_id: &str) -> Option<Arc<[u8]>> {
+    BUFFERS.lock().unwrap().get(video_id).cloned()
+}
+
+/**
+ * Forgets the audio of a song once its playback started, it is streamed again if played later
+ */
+pub fn remove(video_id: &str) {
+    BUFFERS.lock().unwrap().remove(video_id);
+}
+
+/**
+ * Returns the number of streamed songs waiting to be played
+ */
+pub fn buffered_count() -> usize {
+    BUFFERS.lock().unwrap().len()
+}
+
+pub fn clear() {
+    BUFFERS.lock().unwrap().clear();
+}
@@ -0,0 +1,148 @@
+use std::path::Path;
+
+use mp4ameta::{FreeformIdent, Img, Tag};
+use ytpapi::Video;
+
+use crate::{
+    config::CONFIG,
+    consts::CACHE_DIR,
+    systems::{logger::log_, player::song_path},
+};
+
+use super::{
+    download::write_durably,
+    logger::{log_download, LogLevel},
+    thumbnails,
+};
+
+/**
+ * Writes the title, artist, album and thumbnail of a downloaded song in its file so other players
+ * can show them. Failures are only logged, the `.json` file next to it still has the metadata.
+ */
+pub async fn embed(video: &Video) {
+    let thumbnail = match thumbnails::get(&video.video_id).await {
+        Ok(e) => Some(e),
+        Err(e) => {
+            log_download(
+                LogLevel::Debug,
+                format!("Can't fetch the thumbnail of {}: {}", video.video_id, e),
+            );
+            None
+        }
+    };
+    let path = CACHE_DIR.join(format!("downloads/{}.mp4", video.video_id));
+    let (title, author, album) = (
+        video.title.clone(),
+        video.author.clone(),
+        video.album.clone(),
+    );
+    let result = tokio::task::spawn_blocking(move || {
+        let mut tag = Tag::read_from_path(&path)?;
+        tag.set_title(title);
+        tag.set_artist(author);
+        if !album.is_empty() {
+            tag.set_album(album);
+        }
+        if let Some(thumbnail) = thumbnail {
+            tag.set_artwork(Img::jpeg(thumbnail));
+        }
+        tag.write_to_path(&path)
+    })
+    .await;
+    match result {
+        Ok(Ok(())) => log_download(
+            LogLevel::Debug,
+            format!("Tagged the file of {}", video.video_id),
+        ),
+        Ok(Err(e)) => log_download(
+            LogLevel::Error,
+            format!("Can't tag the file of {}: {}", video.video_id, e),
+        ),
+        Err(e) => log_download(
+            LogLevel::Error,
+            format!("Can't tag the file of {}: {}", video.video_id, e),
+        ),
+    }
+}
+
+// The ReplayGain tag of the song, written by most taggers like `-6.20 dB`
+const TRACK_GAIN: FreeformIdent = FreeformIdent::new("com.apple.iTunes", "replaygain_track_gain");
+
+// The key of the gain in the `.json` file of a downloaded song
+const GAIN_KEY: &str = "gain";
+
+/**
+ * Returns the metadata written next to a downloaded song: the video and the gain of the tag of its
+ * file, so the player doesn't have to parse the file
+ */
+pub fn metadata(video: &Video) -> serde_json::Result<String> {
+    let mut metadata = serde_json::to_value(video)?;
+    if let serde_json::Value::Object(fields) = &mut metadata {
+        let gain = read_gain(&song_path(&video.video_id)).unwrap_or(0.0);
+        fields.insert(GAIN_KEY.to_owned(), gain.into());
+    }
+    Ok(metadata.to_string())
+}
+
+/**
+ * Returns the gain in dB to apply to the song, as saved in its `.json` file when it was
+ * downloaded, 0 if it has none
+ */
+pub fn track_gain(video_id: &str) -> f32 {
+    if !CONFIG.use_gain_tags {
+        return 0.0;
+    }
+    std::fs::read_to_string(CACHE_DIR.join(format!("downloads/{}.json", video_id)))
+        .ok()
+        .and_then(|x| serde_json::from_str::<serde_json::Value>(&x).ok())
+        .and_then(|x| x.get(GAIN_KEY)?.as_f64())
+        .unwrap_or(0.0) as f32
+}
+
+/**
+ * Saves the gain of the songs downloaded before it was saved with them. Run on startup by the
+ * cleaning thread, the downloader only writes the `.json` files of new songs.
+ */
+pub fn store_missing_gains() {
+    let entries = match std::fs::read_dir(CACHE_DIR.join("downloads")) {
+        Ok(e) => e,
+        Err(_) => return,
+    };
+    for path in entries.flatten().map(|x| x.path()) {
+        if path.extension().and_then(|x| x.to_str()) != Some("json") {
+            continue;
+        }
+        let mut metadata = match std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|x| serde_json::from_str::<serde_json::Value>(&x).ok())
+        {
+            Some(serde_json::Value::Object(e)) if !e.contains_key(GAIN_KEY) => e,
+            _ => continue,
+        };
+        let song = path.with_extension("mp4");
+        if !song.exists() {
+            continue;
+        }
+        metadata.insert(GAIN_KEY.to_owned(), read_gain(&song).unwrap_or(0.0).into());
+        let content = serde_json::Value::Object(metadata).to_string();
+        if let Err(e) = write_durably(&path, content.as_bytes()) {
+            log_(format!("Can't save the gain in {}: {}", path.display(), e));
+        }
+    }
+}
+
+/**
+ * Reads the ReplayGain tag of a file, limited to 15 dB so a wrong tag can't be deafening
+ */
+fn read_gain(path: &Path) -> Option<f32> {
+    let tag = Tag::read_from_path(path).ok()?;
+    let value = tag.strings_of(&TRACK_GAIN).next()?;
+    let gain = value
+        .trim()
+        .trim_end_matches("dB")
+        .trim_end_matches("db")
+        .trim()
+        .parse::<f32>()
+        .ok()?;
+    gain.is_finite().then(|| gain.clamp(-15.0, 15.0))
+}
@@ -0,0 +1,67 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::consts::CACHE_DIR;
+
+use super::logger::{log_download, LogLevel};
+
+// Time after which fetching a thumbnail is given up, the songs play without it
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/**
+ * The path of the cached thumbnail of a song
+ */
+pub fn path(video_id: &str) -> PathBuf {
+    CACHE_DIR.join(format!("thumbnails/{}.jpg", video_id))
+}
+
+/**
+ * Returns the thumbnail of a song, it is fetched and cached if it isn't already
+ */
+pub async fn get(video_id: &str) -> Result<Vec<u8>, String> {
+    let path = path(video_id);
+    if let Ok(data) = std::fs::read(&path) {
+        return Ok(data);
+    }
+    let data = fetch(video_id).await.map_err(|e| e.to_string())?;
+    if let Err(e) = save(&path, &data) {
+        log_download(
+            LogLevel::Debug,
+            format!("Can't cache the thumbnail of {}: {}", video_id, e),
+        );
+    }
+    Ok(data)
+}
+
+/**
+ * Caches the thumbnail of a downloaded song, failures are only logged
+ */
+pub async fn cache(video_id: &str) {
+    if let Err(e) = get(video_id).await {
+        log_download(
+            LogLevel::Debug,
+            format!("Can't fetch the thumbnail of {}: {}", video_id, e),
+        );
+    }
+}
+
+fn save(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, data)
+}
+
+async fn fetch(video_id: &str) -> Result<Vec<u8>, reqwest::Error> {
+    let url = format!("https://i.ytimg.com/vi/{}/hqdefault.jpg", video_id);
+    let response = reqwest::Client::builder()
+        .timeout(FETCH_TIMEOUT)
+        .build()?
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(response.bytes().await?.to_vec())
+}
@@ -0,0 +1,168 @@
+use std::{
+    io::Stdout,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use tui::{
+    backend::CrosstermBackend,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+use ytpapi::Video;
+
+use crate::{
+    maintenance::parse_duration,
+    systems::{backend::streams_of, logger::log_, player::song_path},
+};
+
+use super::{history::time_ago, music_player::copy_to_clipboard};
+
+/**
+ * Lists the metadata of a song and of its file, the missing values are shown as `Unknown`
+ */
+pub fn details(video: &Video) -> Vec<(&'static str, String)> {
+    let unknown = || "Unknown".to_owned();
+    let or_unknown = |x: &str| {
+        if x.trim().is_empty() {
+            unknown()
+        } else {
+            x.to_owned()
+        }
+    };
+    let path = song_path(&video.video_id);
+    let metadata = std::fs::metadata(&path).ok();
+    let size = metadata.as_ref().map(|x| x.len());
+    let duration = parse_duration(&video.duration);
+    let now = SystemTime::now();
+    let mut details = vec![
+        ("Title", or_unknown(&video.title)),
+        ("Artist", or_unknown(&video.author)),
+        ("Album", or_unknown(&video.album)),
+        ("Duration", or_unknown(&video.duration)),
+        ("Video ID", video.video_id.clone()),
+        (
+            "File",
+            if metadata.is_some() {
+                path.display().to_string()
+            } else {
+                "Not downloaded".to_owned()
+            },
+        ),
+        (
+            "Size",
+            size.map_or_else(unknown, |x| format!("{:.1} MB", x as f64 / 1024.0 / 1024.0)),
+        ),
+        (
+            "Bitrate",
+            match (size, duration) {
+                (Some(size), Some(duration)) => format!("~{} kbit/s", size * 8 / duration / 1000),
+                _ => unknown(),
+            },
+        ),
+        (
+            "Downloaded",
+            metadata
+                .and_then(|x| x.modified().ok())
+                .and_then(|x| {
+                    Some((
+                        now.duration_since(x).ok()?.as_secs(),
+                        x.duration_since(UNIX_EPOCH).ok()?.as_secs(),
+                    ))
+                })
+                .map_or_else(unknown, |(ago, timestamp)| {
+                    format!("{} (timestamp {})", time_ago(ago), timestamp)
+                }),
+        ),
+        ("Chapters", video.chapters.len().to_string()),
+    ];
+    for (index, stream) in streams_of(&video.video_id).into_iter().enumerate() {
+        details.push((if index == 0 { "Streams" } else { "" }, stream));
+    }
+    details
+}
+
+/**
+ * Renders the details of a song in a box in the middle of the screen
+ */
+pub fn render(frame: &mut Frame<CrosstermBackend<Stdout>>, details: &[(&'static str, String)]) {
+    let size = frame.size();
+    let label_width = details.iter().map(|(x, _)| x.len()).max().unwrap_or(0);
+    let width = (details
+        .iter()
+        .map(|(_, x)| x.chars().count() + label_width + 2)
+        .max()
+        .unwrap_or(0) as u16
+        + 4)
+    .min(size.width);
+    let height = (details.len() as u16 + 2).min(size.height);
+    if width < 5 || height < 3 {
+        return;
+    }
+    let area = Rect::new(
+        (size.width - width) / 2,
+        (size.height - height) / 2,
+        width,
+        height,
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(
+            details
+                .iter()
+                .map(|(label, value)| {
+                    Spans::from(vec![
+                        Span::styled(
+                            format!("{:>width$}  ", label, width = label_width),
+                            Style::default().add_modifier(Modifier::BOLD),
+                        ),
+                        Span::raw(value.as_str()),
+                    ])
+                })
+                .collect::<Vec<_>>(),
+        )
+        .style(Style::default().fg(Color::White))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Details (press any key to close) "),
+        ),
+        area,
+    );
+}
+
+/**
+ * Logs the path of the file of a song, copies it and opens its folder in the file manager.
+ * Returns the message shown to the user.
+ */
+pub fn reveal(video: &Video) -> String {
+    let path = song_path(&video.video_id);
+    if !path.exists() {
+        return format!("{} isn't downloaded yet", video.title);
+    }
+    let path = path.canonicalize().unwrap_or(path);
+    log_(format!("File of {}: {}", video.title, path.display()));
+    // The path is still shown when the clipboard isn't available
+    let _ = copy_to_clipboard(&path.display().to_string());
+    if let Some(folder) = path.parent() {
+        if let Err(e) = std::process::Command::new(FILE_MANAGER)
+            .arg(folder)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+        {
+            log_(format!("Can't open {}: {}", folder.display(), e));
+        }
+    }
+    path.display().to_string()
+}
+
+// The command opening a folder in the file manager of the platform
+#[cfg(target_os = "windows")]
+const FILE_MANAGER: &str = "explorer";
+#[cfg(target_os = "macos")]
+const FILE_MANAGER: &str = "open";
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const FILE_MANAGER: &str = "xdg-open";
@@ -18,9 +18,16 @@ impl Screen for DeviceLost {
 
     fn on_key_press(&mut self, key: KeyEvent, _: &Rect) -> EventResponse {
         match key.code {
-            KeyCode::Enter | KeyCode::Char(' ') => ManagerMessage::RestartPlayer
-                .pass_to(Screens::MusicPlayer)
-                .event(),
+            // The audio device is looked for again and the player goes back on success
+            KeyCode::Enter | KeyCode::Char(' ') | KeyCode::Char('r') => {
+                ManagerMessage::RestartPlayer
+                    .pass_to(Screens::MusicPlayer)
+                    .event()
+            }
+            KeyCode::Char('c') => {
+                self.0.clear();
+                EventResponse::None
+            }
             KeyCode::Esc => ManagerMessage::Quit.event(),
             _ => EventResponse::None,
         }
@@ -29,8 +36,13 @@ impl Screen for DeviceLost {
     fn render(&mut self, frame: &mut Frame<tui::backend::CrosstermBackend<std::io::Stdout>>) {
         frame.render_widget(
             Paragraph::new(format!(
-                "{}\nPress [Enter] or [Space] to retry.\nOr [Esc] to exit",
-                self.0.join("\n")
+                "{}\nPress [Enter] or [R] to look for the audio device again.\n[C] to clear the errors, [Esc] to exit",
+                self.0
+                    .iter()
+                    .enumerate()
+                    .map(|(i, e)| format!("{}. {}", i + 1, e))
+                    .collect::<Vec<_>>()
+                    .join("\n")
             ))
             .style(Style::default().fg(Color::Red))
             .alignment(Alignment::Center)
@@ -38,7 +50,7 @@ impl Screen for DeviceLost {
                 Block::default()
                     .borders(Borders::ALL)
                     .style(Style::default().fg(Color::White))
-                    .title(" Error ")
+                    .title(format!(" Errors ({}) ", self.0.len()))
                     .border_type(BorderType::Plain),
             ),
             frame.size(),
@@ -55,12 +67,25 @@ impl Screen for DeviceLost {
         }
     }
 
-    fn close(&mut self, _: Screens) -> EventResponse {
-        self.0.clear();
+    fn close(&mut self, new_screen: Screens) -> EventResponse {
+        // A new error opens this screen again, the previous ones are kept
+        if new_screen != Screens::DeviceLost {
+            self.0.clear();
+        }
         EventResponse::None
     }
 
     fn open(&mut self) -> EventResponse {
         EventResponse::None
     }
+
+    fn refresh(&mut self) -> EventResponse {
+        ManagerMessage::RestartPlayer
+            .pass_to(Screens::MusicPlayer)
+            .event()
+    }
+
+    fn footer_hints(&self) -> &'static str {
+        "enter/r look for the device · c clear · esc quit"
+    }
 }
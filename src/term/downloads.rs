@@ -0,0 +1,207 @@
+use std::sync::Arc;
+
+use crossterm::event::{KeyCode, KeyEvent, MouseEventKind};
+use flume::Sender;
+use tui::{
+    layout::Rect,
+    style::{Color, Style},
+    widgets::{Block, Borders, List, ListItem, ListState},
+    Frame,
+};
+use ytpapi::Video;
+
+use crate::{
+    systems::{
+        backend,
+        download::{self, IN_DOWNLOAD},
+    },
+    SoundAction,
+};
+
+use super::{rect_contains, relative_pos, EventResponse, ManagerMessage, Screen, Screens};
+
+// The songs being downloaded, the ones waiting for a downloader and the ones that failed
+pub struct Downloads {
+    pub selected: usize,
+    pub action_sender: Arc<Sender<SoundAction>>,
+}
+
+impl Downloads {
+    /**
+     * Returns the songs with the state of their download, read again on each frame
+     */
+    fn items(&self) -> Vec<(Video, String)> {
+        let running = IN_DOWNLOAD.lock().unwrap().clone();
+        running
+            .into_iter()
+            .map(|video| {
+                let state = match download::progress(&video.video_id) {
+                    Some((received, Some(size))) if size != 0 => format!(
+                        "{:>3}% {:.1}/{:.1} MB",
+                        received * 100 / size,
+                        megabytes(received),
+                        megabytes(size)
+                    ),
+                    Some((received, _)) => format!("{:.1} MB", megabytes(received)),
+                    None => "connecting".to_owned(),
+                };
+                (video, state)
+            })
+            .chain(
+                download::queued()
+                    .into_iter()
+                    .map(|video| (video, "queued".to_owned())),
+            )
+            .chain(
+                download::failed()
+                    .into_iter()
+                    .map(|video| (video, "failed".to_owned())),
+            )
+            .collect()
+    }
+
+    /**
+     * Downloads with the other backend from now on, the failed downloads are tried again with it
+     */
+    fn switch_backend(&mut self) -> EventResponse {
+        let message = match backend::switch() {
+            Ok(name) => match download::retry_failed(&self.action_sender) {
+                Ok(0) => format!("Downloading with {}", name),
+                Ok(count) => format!("Downloading with {}, retrying {} songs", name, count),
+                Err(e) => format!("Downloading with {}. {}", name, e),
+            },
+            Err(e) => e,
+        };
+        ManagerMessage::Toast(message).event()
+    }
+
+    fn cancel_selected(&mut self) -> EventResponse {
+        match self.items().into_iter().nth(self.selected) {
+            Some((video, _)) if download::cancel(&video.video_id) => {
+                ManagerMessage::Toast(format!("Cancelled the download of {}", video.title)).event()
+            }
+            _ => EventResponse::None,
+        }
+    }
+}
+
+fn megabytes(bytes: u64) -> f64 {
+    bytes as f64 / 1024.0 / 1024.0
+}
+
+impl Screen for Downloads {
+    fn on_mouse_press(
+        &mut self,
+        mouse_event: crossterm::event::MouseEvent,
+        frame_data: &Rect,
+    ) -> EventResponse {
+        if let MouseEventKind::Down(_) = mouse_event.kind {
+            let x = mouse_event.column;
+            let y = mouse_event.row;
+            if rect_contains(frame_data, x, y, 1) {
+                let (_, y) = relative_pos(frame_data, x, y, 1);
+                let y = y as usize + self.selected.saturating_sub(1);
+                if y < self.items().len() {
+                    self.selected = y;
+                }
+            }
+        }
+        EventResponse::None
+    }
+
+    fn on_key_press(&mut self, key: KeyEvent, _: &Rect) -> EventResponse {
+        let len = self.items().len();
+        match key.code {
+            KeyCode::Esc => return ManagerMessage::ChangeState(Screens::Playlist).event(),
+            KeyCode::Delete | KeyCode::Char('x') => return self.cancel_selected(),
+            KeyCode::Char('b') => return self.switch_backend(),
+            KeyCode::Char('+') | KeyCode::Up => {
+                self.selected = if self.selected == 0 {
+                    len.saturating_sub(1)
+                } else {
+                    self.selected - 1
+                };
+            }
+            KeyCode::Char('-') | KeyCode::Down => {
+                self.selected = if self.selected + 1 >= len {
+                    0
+                } else {
+                    self.selected + 1
+                };
+            }
+            _ => {}
+        }
+        EventResponse::None
+    }
+
+    fn render(&mut self, frame: &mut Frame<tui::backend::CrosstermBackend<std::io::Stdout>>) {
+        let items = self.items();
+        // The finished downloads leave the list
+        if self.selected >= items.len() {
+            self.selected = items.len().saturating_sub(1);
+        }
+        let backend = backend::primary().name();
+        let title = if items.is_empty() {
+            format!(
+                " Downloads with {} (nothing to download, b to switch, Esc to go back) ",
+                backend
+            )
+        } else {
+            format!(
+                " Downloads with {} ({}, x to cancel, b to switch, Esc to go back) ",
+                backend,
+                items.len()
+            )
+        };
+        frame.render_stateful_widget(
+            List::new(
+                items
+                    .into_iter()
+                    .enumerate()
+                    .skip(self.selected.saturating_sub(1))
+                    .map(|(index, (video, state))| {
+                        ListItem::new(format!("[{}] {} | {}", state, video.author, video.title))
+                            .style(
+                                Style::default()
+                                    .fg(if index == self.selected {
+                                        Color::Black
+                                    } else {
+                                        Color::White
+                                    })
+                                    .bg(if index != self.selected {
+                                        Color::Black
+                                    } else {
+                                        Color::White
+                                    }),
+                            )
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .block(Block::default().borders(Borders::ALL).title(title)),
+            frame.size(),
+            &mut ListState::default(),
+        );
+    }
+
+    fn handle_global_message(&mut self, _: ManagerMessage) -> EventResponse {
+        EventResponse::None
+    }
+
+    fn close(&mut self, _: Screens) -> EventResponse {
+        EventResponse::None
+    }
+
+    fn open(&mut self) -> EventResponse {
+        self.selected = 0;
+        EventResponse::None
+    }
+
+    fn refresh(&mut self) -> EventResponse {
+        // The downloads are read again on each frame
+        EventResponse::None
+    }
+
+    fn footer_hints(&self) -> &'static str {
+        "x/del cancel · b switch backend · esc back"
+    }
+}
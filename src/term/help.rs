@@ -0,0 +1,91 @@
+use std::io::Stdout;
+
+use tui::{
+    backend::CrosstermBackend,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use super::keymap::KEYMAP;
+
+// The keys handled by the manager and the screens, they can't be changed in the `[keys]` table
+const FIXED_KEYS: &[(&str, &str)] = &[
+    ("tab", "Open the player, or go back to the previous screen"),
+    ("shift+tab", "Next screen"),
+    ("esc", "Go back"),
+    ("f5", "Scan the downloads and fetch the playlists again"),
+    ("ctrl+c", "Quit, see `interrupt_action`"),
+];
+
+/**
+ * The keys of every action with what they do, the actions bound in the `[keys]` table first
+ */
+pub fn lines() -> Vec<(String, &'static str)> {
+    KEYMAP
+        .help()
+        .into_iter()
+        .chain(
+            FIXED_KEYS
+                .iter()
+                .map(|(keys, description)| ((*keys).to_owned(), *description)),
+        )
+        .collect()
+}
+
+/**
+ * Renders the keys in a box in the middle of the screen, starting at the `scroll` line
+ */
+pub fn render(frame: &mut Frame<CrosstermBackend<Stdout>>, scroll: usize) {
+    let lines = lines();
+    let size = frame.size();
+    let keys_width = lines
+        .iter()
+        .map(|(x, _)| x.chars().count())
+        .max()
+        .unwrap_or(0);
+    let width = (lines
+        .iter()
+        .map(|(_, x)| x.chars().count() + keys_width + 2)
+        .max()
+        .unwrap_or(0) as u16
+        + 4)
+    .min(size.width);
+    let height = (lines.len() as u16 + 2).min(size.height);
+    if width < 5 || height < 3 {
+        return;
+    }
+    let area = Rect::new(
+        (size.width - width) / 2,
+        (size.height - height) / 2,
+        width,
+        height,
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(
+            lines
+                .iter()
+                .skip(scroll)
+                .map(|(keys, description)| {
+                    Spans::from(vec![
+                        Span::styled(
+                            format!("{:>width$}  ", keys, width = keys_width),
+                            Style::default().add_modifier(Modifier::BOLD),
+                        ),
+                        Span::raw(*description),
+                    ])
+                })
+                .collect::<Vec<_>>(),
+        )
+        .style(Style::default().fg(Color::White))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Keys (up/down to scroll, ? or esc to close) "),
+        ),
+        area,
+    );
+}
@@ -0,0 +1,159 @@
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEventKind};
+use flume::Sender;
+use tui::{
+    layout::Rect,
+    style::{Color, Style},
+    widgets::{Block, Borders, List, ListItem, ListState},
+    Frame,
+};
+
+use crate::{
+    database::history::{self, HistoryEntry},
+    systems::download::start_task_unary,
+    SoundAction,
+};
+
+use super::{rect_contains, relative_pos, EventResponse, ManagerMessage, Screen, Screens};
+
+// The songs played in this session and the previous ones
+pub struct History {
+    pub selected: usize,
+    pub items: Vec<HistoryEntry>,
+    pub action_sender: Arc<Sender<SoundAction>>,
+}
+
+impl Screen for History {
+    fn on_mouse_press(
+        &mut self,
+        mouse_event: crossterm::event::MouseEvent,
+        frame_data: &Rect,
+    ) -> EventResponse {
+        if let MouseEventKind::Down(_) = mouse_event.kind {
+            let x = mouse_event.column;
+            let y = mouse_event.row;
+            if rect_contains(frame_data, x, y, 1) {
+                let (_, y) = relative_pos(frame_data, x, y, 1);
+                let y = y as usize + self.selected.saturating_sub(1);
+                if y < self.items.len() {
+                    self.selected = y;
+                    return self.on_key_press(
+                        KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE),
+                        frame_data,
+                    );
+                }
+            }
+        }
+        EventResponse::None
+    }
+
+    fn on_key_press(&mut self, key: KeyEvent, _: &Rect) -> EventResponse {
+        match key.code {
+            KeyCode::Esc => return ManagerMessage::ChangeState(Screens::Playlist).event(),
+            KeyCode::Enter => {
+                if let Some(entry) = self.items.get(self.selected) {
+                    start_task_unary(self.action_sender.clone(), entry.video.clone());
+                    return ManagerMessage::ChangeState(Screens::MusicPlayer).event();
+                }
+            }
+            KeyCode::Char('+') | KeyCode::Up => {
+                self.selected = if self.selected == 0 {
+                    self.items.len().saturating_sub(1)
+                } else {
+                    self.selected - 1
+                };
+            }
+            KeyCode::Char('-') | KeyCode::Down => {
+                self.selected = if self.selected + 1 >= self.items.len() {
+                    0
+                } else {
+                    self.selected + 1
+                };
+            }
+            _ => {}
+        }
+        EventResponse::None
+    }
+
+    fn render(&mut self, frame: &mut Frame<tui::backend::CrosstermBackend<std::io::Stdout>>) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|x| x.as_secs())
+            .unwrap_or(0);
+        frame.render_stateful_widget(
+            List::new(
+                self.items
+                    .iter()
+                    .enumerate()
+                    .skip(self.selected.saturating_sub(1))
+                    .map(|(index, entry)| {
+                        ListItem::new(format!(
+                            "{:>9} | {} | {}",
+                            time_ago(now.saturating_sub(entry.time)),
+                            entry.video.author,
+                            entry.video.title
+                        ))
+                        .style(
+                            Style::default()
+                                .fg(if index == self.selected {
+                                    Color::Black
+                                } else {
+                                    Color::White
+                                })
+                                .bg(if index != self.selected {
+                                    Color::Black
+                                } else {
+                                    Color::White
+                                }),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" History (Enter to play next) "),
+            ),
+            frame.size(),
+            &mut ListState::default(),
+        );
+    }
+
+    fn handle_global_message(&mut self, _: ManagerMessage) -> EventResponse {
+        EventResponse::None
+    }
+
+    fn close(&mut self, _: Screens) -> EventResponse {
+        EventResponse::None
+    }
+
+    fn open(&mut self) -> EventResponse {
+        self.items = history::read();
+        self.selected = 0;
+        EventResponse::None
+    }
+
+    fn refresh(&mut self) -> EventResponse {
+        self.open()
+    }
+
+    fn footer_hints(&self) -> &'static str {
+        "enter play next · esc back"
+    }
+}
+
+/**
+ * Formats a number of seconds as `5 min ago`
+ */
+pub fn time_ago(seconds: u64) -> String {
+    match seconds {
+        0..=59 => "just now".to_owned(),
+        60..=3599 => format!("{} min ago", seconds / 60),
+        3600..=86399 => format!("{} h ago", seconds / 3600),
+        _ => format!("{} d ago", seconds / 86400),
+    }
+}
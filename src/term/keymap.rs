@@ -0,0 +1,533 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use once_cell::sync::Lazy;
+
+use crate::{
+    config::{KeyList, CONFIG},
+    systems::logger::log_,
+};
+
+/**
+ * The actions of the player and the search that can be bound to other keys
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    PlayPause,
+    PlaySelected,
+    Next,
+    Previous,
+    Forward,
+    Backward,
+    SeekForward,
+    SeekBackward,
+    ChapterForward,
+    ChapterBackward,
+    VolumeUp,
+    VolumeDown,
+    ToggleMute,
+    VolumePanel,
+    OpenSearch,
+    OpenHistory,
+    OpenDownloads,
+    ShowRelated,
+    CycleRepeat,
+    SaveQueue,
+    ShowDetails,
+    Reveal,
+    ToggleRemaining,
+    LoopStart,
+    LoopEnd,
+    ClearLoop,
+    ToggleIntro,
+    ToggleOutro,
+    CycleEqualizer,
+    Like,
+    Dislike,
+    Jump,
+    FollowCurrent,
+    CopyLink,
+    StartStation,
+    CycleSearchType,
+    AddToQueue,
+    SelectUp,
+    SelectDown,
+    MoveUp,
+    MoveDown,
+    RemoveFromQueue,
+    Help,
+}
+
+// The name of each action in the `[keys]` table of `config.toml`, its default keys and what it does
+// in the help, the first action bound to a key wins
+const ACTIONS: &[(Action, &str, &[&str], &str)] = &[
+    (Action::PlayPause, "play_pause", &["space"], "Play or pause"),
+    (
+        Action::PlaySelected,
+        "play_selected",
+        &["enter"],
+        "Play the selected song of the queue",
+    ),
+    (Action::Next, "next", &["ctrl+right", "ctrl+>"], "Next song"),
+    (
+        Action::Previous,
+        "previous",
+        &["ctrl+left", "ctrl+<"],
+        "Previous song",
+    ),
+    (
+        Action::Forward,
+        "forward",
+        &["right", ">"],
+        "Skip 5 seconds",
+    ),
+    (
+        Action::Backward,
+        "backward",
+        &["left", "<"],
+        "Go back 5 seconds",
+    ),
+    (
+        Action::SeekForward,
+        "seek_forward",
+        &["shift+right"],
+        "Skip 10 seconds",
+    ),
+    (
+        Action::SeekBackward,
+        "seek_backward",
+        &["shift+left"],
+        "Go back 10 seconds",
+    ),
+    (
+        Action::ChapterForward,
+        "chapter_forward",
+        &["]"],
+        "Next chapter",
+    ),
+    (
+        Action::ChapterBackward,
+        "chapter_backward",
+        &["["],
+        "Previous chapter",
+    ),
+    (Action::VolumeUp, "volume_up", &["up", "+"], "Volume up"),
+    (
+        Action::VolumeDown,
+        "volume_down",
+        &["down", "-"],
+        "Volume down",
+    ),
+    (Action::ToggleMute, "toggle_mute", &["m"], "Mute or unmute"),
+    (
+        Action::VolumePanel,
+        "volume_panel",
+        &["v"],
+        "Hide or show the volume panel",
+    ),
+    (Action::OpenSearch, "open_search", &["f"], "Search"),
+    (
+        Action::OpenHistory,
+        "open_history",
+        &["h"],
+        "Recently played songs",
+    ),
+    (Action::OpenDownloads, "open_downloads", &["w"], "Downloads"),
+    (
+        Action::ShowRelated,
+        "show_related",
+        &["r"],
+        "Songs related to the current one",
+    ),
+    (
+        Action::CycleRepeat,
+        "cycle_repeat",
+        &["R"],
+        "Switch the repeat mode",
+    ),
+    (
+        Action::SaveQueue,
+        "save_queue",
+        &["s"],
+        "Save the queue as a playlist",
+    ),
+    (
+        Action::ShowDetails,
+        "show_details",
+        &["i"],
+        "Details of the song",
+    ),
+    (
+        Action::Reveal,
+        "reveal",
+        &["o"],
+        "Open the folder of the song",
+    ),
+    (
+        Action::ToggleRemaining,
+        "toggle_remaining",
+        &["t"],
+        "Show the remaining time",
+    ),
+    (Action::LoopStart, "loop_start", &["a"], "Start of the loop"),
+    (Action::LoopEnd, "loop_end", &["b"], "End of the loop"),
+    (Action::ClearLoop, "clear_loop", &["c"], "Stop the loop"),
+    (
+        Action::ToggleIntro,
+        "toggle_intro",
+        &["("],
+        "Always start the song here",
+    ),
+    (
+        Action::ToggleOutro,
+        "toggle_outro",
+        &[")"],
+        "Always end the song here",
+    ),
+    (
+        Action::CycleEqualizer,
+        "cycle_equalizer",
+        &["e"],
+        "Next equalizer preset",
+    ),
+    (Action::Like, "like", &["l"], "Like the song"),
+    (Action::Dislike, "dislike", &["d"], "Dislike the song"),
+    (
+        Action::Jump,
+        "jump",
+        &["j"],
+        "Play the song at a position of the queue",
+    ),
+    (
+        Action::FollowCurrent,
+        "follow_current",
+        &["g"],
+        "Follow the current song in the queue",
+    ),
+    (
+        Action::CopyLink,
+        "copy_link",
+        &["y"],
+        "Copy the link of the song",
+    ),
+    (
+        Action::StartStation,
+        "start_station",
+        &["ctrl+r"],
+        "Play a station of the results (search)",
+    ),
+    (
+        Action::CycleSearchType,
+        "cycle_search_type",
+        &["ctrl+t"],
+        "Search albums, playlists or artists (search)",
+    ),
+    (
+        Action::AddToQueue,
+        "add_to_queue",
+        &["ctrl+a"],
+        "Add the result to the queue (search)",
+    ),
+    (
+        Action::SelectUp,
+        "select_up",
+        &["alt+up"],
+        "Select the previous song of the queue",
+    ),
+    (
+        Action::SelectDown,
+        "select_down",
+        &["alt+down"],
+        "Select the next song of the queue",
+    ),
+    (
+        Action::MoveUp,
+        "move_up",
+        &["shift+up"],
+        "Move the selected song up",
+    ),
+    (
+        Action::MoveDown,
+        "move_down",
+        &["shift+down"],
+        "Move the selected song down",
+    ),
+    (
+        Action::RemoveFromQueue,
+        "remove_from_queue",
+        &["delete"],
+        "Remove the selected song from the queue",
+    ),
+    (Action::Help, "help", &["?", "f1"], "Show or hide this help"),
+];
+
+/**
+ * A key with the modifiers that must be held, `Shift` is part of the character for the letters
+ * and the symbols
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Binding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Binding {
+    fn of(key: &KeyEvent) -> Self {
+        let mut modifiers =
+            key.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        if matches!(key.code, KeyCode::Char(_)) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Self {
+            code: key.code,
+            modifiers,
+        }
+    }
+
+    /**
+     * The key as written in the `[keys]` table, like `ctrl+right`
+     */
+    fn name(&self) -> String {
+        let key = match self.code {
+            KeyCode::Char(' ') => "space".to_owned(),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::F(n) => format!("f{}", n),
+            KeyCode::Enter => "enter".to_owned(),
+            KeyCode::Tab => "tab".to_owned(),
+            KeyCode::Backspace => "backspace".to_owned(),
+            KeyCode::Delete => "delete".to_owned(),
+            KeyCode::Insert => "insert".to_owned(),
+            KeyCode::Home => "home".to_owned(),
+            KeyCode::End => "end".to_owned(),
+            KeyCode::PageUp => "pageup".to_owned(),
+            KeyCode::PageDown => "pagedown".to_owned(),
+            KeyCode::Up => "up".to_owned(),
+            KeyCode::Down => "down".to_owned(),
+            KeyCode::Left => "left".to_owned(),
+            KeyCode::Right => "right".to_owned(),
+            code => format!("{:?}", code).to_lowercase(),
+        };
+        let modifiers = [
+            (KeyModifiers::CONTROL, "ctrl+"),
+            (KeyModifiers::ALT, "alt+"),
+            (KeyModifiers::SHIFT, "shift+"),
+        ];
+        modifiers
+            .iter()
+            .filter(|(modifier, _)| self.modifiers.contains(*modifier))
+            .map(|(_, name)| *name)
+            .chain([key.as_str()])
+            .collect()
+    }
+}
+
+/**
+ * The name of the action in the `[keys]` table
+ */
+fn action_name(action: Action) -> &'static str {
+    ACTIONS
+        .iter()
+        .find(|(x, _, _, _)| *x == action)
+        .map_or("", |(_, name, _, _)| name)
+}
+
+/**
+ * Returns true for the actions of the search, the other ones are the actions of the player
+ */
+fn is_search_action(action: Action) -> bool {
+    matches!(
+        action,
+        Action::CycleSearchType | Action::AddToQueue | Action::StartStation
+    )
+}
+
+/**
+ * Parses a key like `space`, `ctrl+right` or `j`, the modifiers are `ctrl`, `alt` and `shift`.
+ * `shift` with a letter is the upper case letter, the other characters must be written shifted.
+ */
+fn parse_key(spec: &str) -> Result<Binding, String> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = spec.trim();
+    // `+` alone is a key, so only the parts before the last `+` can be modifiers
+    while let Some((modifier, key)) = rest.split_once('+').filter(|(_, key)| !key.is_empty()) {
+        modifiers |= match modifier.to_lowercase().as_str() {
+            "ctrl" | "control" => KeyModifiers::CONTROL,
+            "alt" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => return Err(format!("unknown modifier `{}`", modifier)),
+        };
+        rest = key;
+    }
+    let mut chars = rest.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match rest.to_lowercase().as_str() {
+            "space" => KeyCode::Char(' '),
+            "enter" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "delete" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            x => match x.strip_prefix('f').and_then(|x| x.parse::<u8>().ok()) {
+                Some(n) if (1..=12).contains(&n) => KeyCode::F(n),
+                _ => return Err(format!("unknown key `{}`", rest)),
+            },
+        },
+    };
+    // The terminal sends the shifted character, `Binding::of` drops the modifier
+    let code = match code {
+        KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+            let mut upper = c.to_uppercase();
+            match (upper.next(), upper.next()) {
+                (Some(upper), None) if c.is_alphabetic() => KeyCode::Char(upper),
+                _ => {
+                    return Err(format!(
+                        "`shift` can't be used with `{}`, write the shifted character",
+                        c
+                    ))
+                }
+            }
+        }
+        code => code,
+    };
+    Ok(Binding::of(&KeyEvent::new(code, modifiers)))
+}
+
+/**
+ * The keys of every action, the defaults completed by the `[keys]` table of `config.toml`
+ */
+pub struct Keymap {
+    bindings: Vec<(Action, Vec<Binding>)>,
+}
+
+impl Keymap {
+    /**
+     * Builds the keymap, the invalid keys are logged and the action keeps its default keys
+     */
+    fn new() -> Self {
+        for name in CONFIG.keys.keys() {
+            if !ACTIONS.iter().any(|(_, x, _, _)| x == name) {
+                log_(format!("Unknown action `{}` in the `[keys]` table", name));
+            }
+        }
+        // The actions whose keys come from the `[keys]` table
+        let mut custom = Vec::new();
+        let bindings: Vec<(Action, Vec<Binding>)> = ACTIONS
+            .iter()
+            .map(|(action, name, defaults, _)| {
+                let keys = match CONFIG.keys.get(*name) {
+                    Some(KeyList::One(key)) => vec![key.clone()],
+                    Some(KeyList::Many(keys)) => keys.clone(),
+                    None => Vec::new(),
+                };
+                let parsed = keys
+                    .iter()
+                    .map(|x| parse_key(x))
+                    .collect::<Result<Vec<_>, _>>();
+                let bindings = match parsed {
+                    Ok(bindings) if !keys.is_empty() => {
+                        custom.push(*action);
+                        bindings
+                    }
+                    Ok(_) => defaults.iter().filter_map(|x| parse_key(x).ok()).collect(),
+                    Err(e) => {
+                        log_(format!(
+                            "Invalid keys for `{}` ({}), the default keys are used",
+                            name, e
+                        ));
+                        defaults.iter().filter_map(|x| parse_key(x).ok()).collect()
+                    }
+                };
+                (*action, bindings)
+            })
+            .collect();
+        for (i, (first, keys)) in bindings.iter().enumerate() {
+            for (second, other) in &bindings[i + 1..] {
+                // The search actions are only looked for in the search, the defaults don't collide
+                if is_search_action(*first) != is_search_action(*second)
+                    || !custom.contains(first) && !custom.contains(second)
+                {
+                    continue;
+                }
+                for key in keys.iter().filter(|x| other.contains(x)) {
+                    log_(format!(
+                        "`{}` is bound to `{}` and `{}`, only `{}` is used",
+                        key.name(),
+                        action_name(*first),
+                        action_name(*second),
+                        action_name(*first)
+                    ));
+                }
+            }
+        }
+        Self { bindings }
+    }
+
+    /**
+     * Returns the action bound to the key
+     */
+    pub fn action(&self, key: &KeyEvent) -> Option<Action> {
+        let binding = Binding::of(key);
+        self.bindings
+            .iter()
+            .find(|(_, x)| x.contains(&binding))
+            .map(|(action, _)| *action)
+    }
+
+    /**
+     * The keys of every action and what it does, in the order of the `[keys]` table
+     */
+    pub fn help(&self) -> Vec<(String, &'static str)> {
+        self.bindings
+            .iter()
+            .zip(ACTIONS)
+            .map(|((_, bindings), (_, _, _, description))| {
+                let keys = bindings.iter().map(Binding::name).collect::<Vec<_>>();
+                (keys.join(", "), *description)
+            })
+            .collect()
+    }
+
+    /**
+     * Builds the footer hints from the actions and what they do, with the first key of each action
+     * as it is bound. The keys of the actions of a hint are joined by `/`.
+     */
+    pub fn hints(&self, hints: &[(&[Action], &str)]) -> String {
+        hints
+            .iter()
+            .map(|(actions, description)| {
+                let keys = actions
+                    .iter()
+                    .filter_map(|action| {
+                        self.bindings
+                            .iter()
+                            .find(|(x, _)| x == action)
+                            .and_then(|(_, bindings)| bindings.first())
+                            .map(Binding::name)
+                    })
+                    .collect::<Vec<_>>();
+                format!("{} {}", keys.join("/"), description)
+            })
+            .collect::<Vec<_>>()
+            .join(" · ")
+    }
+
+    /**
+     * Returns true if the key is bound to the action
+     */
+    pub fn matches(&self, action: Action, key: &KeyEvent) -> bool {
+        let binding = Binding::of(key);
+        self.bindings
+            .iter()
+            .any(|(x, bindings)| *x == action && bindings.contains(&binding))
+    }
+}
+
+// The keys of the actions, built on startup so the invalid keys are logged once
+pub static KEYMAP: Lazy<Keymap> = Lazy::new(Keymap::new);
@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEventKind};
+use tui::{
+    layout::Rect,
+    style::{Color, Style},
+    widgets::{Block, Borders, List, ListItem, ListState},
+    Frame,
+};
+use ytpapi::Video;
+
+use crate::DATABASE;
+
+use super::{rect_contains, relative_pos, EventResponse, ManagerMessage, Screen, Screens};
+
+// The downloaded songs grouped by artist
+pub struct Library {
+    pub selected: usize,
+    pub artists: Vec<(String, Vec<Video>)>,
+    // The index of the artist whose songs are shown
+    pub opened: Option<usize>,
+    pub track_selected: usize,
+}
+
+impl Library {
+    pub fn new() -> Self {
+        Self {
+            selected: 0,
+            artists: Vec::new(),
+            opened: None,
+            track_selected: 0,
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        if let Some(artist) = self.opened.and_then(|x| self.artists.get(x)) {
+            artist
+                .1
+                .iter()
+                .map(|video| {
+                    if video.album.trim().is_empty() {
+                        video.title.clone()
+                    } else {
+                        format!("{} | {}", video.album, video.title)
+                    }
+                })
+                .collect()
+        } else {
+            self.artists
+                .iter()
+                .map(|(name, videos)| format!("{} ({})", name, videos.len()))
+                .collect()
+        }
+    }
+
+    fn selected_mut(&mut self) -> &mut usize {
+        if self.opened.is_some() {
+            &mut self.track_selected
+        } else {
+            &mut self.selected
+        }
+    }
+}
+
+impl Screen for Library {
+    fn on_mouse_press(
+        &mut self,
+        mouse_event: crossterm::event::MouseEvent,
+        frame_data: &Rect,
+    ) -> EventResponse {
+        if let MouseEventKind::Down(_) = mouse_event.kind {
+            let x = mouse_event.column;
+            let y = mouse_event.row;
+            if rect_contains(frame_data, x, y, 1) {
+                let (_, y) = relative_pos(frame_data, x, y, 1);
+                let len = self.lines().len();
+                let selected = self.selected_mut();
+                let y = y as usize + selected.saturating_sub(1);
+                if y < len {
+                    *selected = y;
+                    return self.on_key_press(
+                        KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE),
+                        frame_data,
+                    );
+                }
+            }
+        }
+        EventResponse::None
+    }
+
+    fn on_key_press(&mut self, key: KeyEvent, _: &Rect) -> EventResponse {
+        let len = self.lines().len();
+        match key.code {
+            KeyCode::Esc if self.opened.is_none() => {
+                return ManagerMessage::ChangeState(Screens::Playlist).event();
+            }
+            KeyCode::Esc | KeyCode::Left => self.opened = None,
+            KeyCode::Enter | KeyCode::Right => {
+                if let Some(artist) = self.opened.and_then(|x| self.artists.get(x)) {
+                    // Plays the artist's songs starting from the selected one
+                    if key.code == KeyCode::Enter && self.track_selected < artist.1.len() {
+                        return ManagerMessage::ReplaceQueue(
+                            artist.1[self.track_selected..].to_vec(),
+                        )
+                        .event();
+                    }
+                } else if self.selected < self.artists.len() {
+                    self.opened = Some(self.selected);
+                    self.track_selected = 0;
+                }
+            }
+            KeyCode::Char('+') | KeyCode::Up => {
+                let selected = self.selected_mut();
+                *selected = if *selected == 0 {
+                    len.saturating_sub(1)
+                } else {
+                    *selected - 1
+                };
+            }
+            KeyCode::Char('-') | KeyCode::Down => {
+                let selected = self.selected_mut();
+                *selected = if *selected + 1 >= len {
+                    0
+                } else {
+                    *selected + 1
+                };
+            }
+            _ => {}
+        }
+        EventResponse::None
+    }
+
+    fn render(&mut self, frame: &mut Frame<tui::backend::CrosstermBackend<std::io::Stdout>>) {
+        let selected = *self.selected_mut();
+        let title = if let Some(artist) = self.opened.and_then(|x| self.artists.get(x)) {
+            format!(" {} (Enter to play, Esc to go back) ", artist.0)
+        } else {
+            " Library (Enter to see the songs of an artist) ".to_owned()
+        };
+        frame.render_stateful_widget(
+            List::new(
+                self.lines()
+                    .into_iter()
+                    .enumerate()
+                    .skip(selected.saturating_sub(1))
+                    .map(|(index, line)| {
+                        ListItem::new(line).style(
+                            Style::default()
+                                .fg(if index == selected {
+                                    Color::Black
+                                } else {
+                                    Color::White
+                                })
+                                .bg(if index != selected {
+                                    Color::Black
+                                } else {
+                                    Color::White
+                                }),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .block(Block::default().borders(Borders::ALL).title(title)),
+            frame.size(),
+            &mut ListState::default(),
+        );
+    }
+
+    fn handle_global_message(&mut self, _: ManagerMessage) -> EventResponse {
+        EventResponse::None
+    }
+
+    fn close(&mut self, _: Screens) -> EventResponse {
+        EventResponse::None
+    }
+
+    fn open(&mut self) -> EventResponse {
+        self.artists = group_by_artist(&DATABASE.read().unwrap());
+        if self.selected >= self.artists.len() {
+            self.selected = 0;
+        }
+        self.opened = None;
+        EventResponse::None
+    }
+
+    fn refresh(&mut self) -> EventResponse {
+        self.open()
+    }
+
+    fn footer_hints(&self) -> &'static str {
+        "enter open/play · esc back"
+    }
+}
+
+/**
+ * Groups the songs by artist, the songs of an artist are sorted by album then by title
+ */
+pub fn group_by_artist(videos: &[Video]) -> Vec<(String, Vec<Video>)> {
+    // The artists are sorted case insensitively but shown with their first spelling
+    let mut artists: BTreeMap<String, (String, Vec<Video>)> = BTreeMap::new();
+    for video in videos {
+        let name = video.author.trim();
+        let name = if name.is_empty() {
+            "Unknown artist"
+        } else {
+            name
+        };
+        artists
+            .entry(name.to_lowercase())
+            .or_insert_with(|| (name.to_owned(), Vec::new()))
+            .1
+            .push(video.clone());
+    }
+    artists
+        .into_values()
+        .map(|(name, mut videos)| {
+            videos.sort_by(|a, b| (&a.album, &a.title).cmp(&(&b.album, &b.title)));
+            (name, videos)
+        })
+        .collect()
+}
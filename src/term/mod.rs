@@ -1,12 +1,20 @@
+pub mod details;
 pub mod device_lost;
+pub mod downloads;
+pub mod help;
+pub mod history;
+pub mod keymap;
+pub mod library;
 pub mod music_player;
 pub mod playlist;
 pub mod search;
+pub mod title;
 
 use std::{
+    collections::HashSet,
     io::{self, Stdout},
     sync::Arc,
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime},
 };
 
 use crossterm::{
@@ -17,12 +25,32 @@ use crossterm::{
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use flume::{Receiver, Sender};
-use tui::{backend::CrosstermBackend, layout::Rect, Frame, Terminal};
+use once_cell::sync::Lazy;
+use tui::{
+    backend::CrosstermBackend,
+    layout::{Alignment, Rect},
+    style::{Color, Style},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame, Terminal,
+};
 use ytpapi::Video;
 
-use crate::{systems::player::PlayerState, SoundAction};
+use crate::{
+    config::{Glyphs, InterruptAction, CONFIG},
+    database::playlists,
+    systems::{backend, download, logger::log_, player::PlayerState},
+    SoundAction,
+};
 
-use self::{device_lost::DeviceLost, playlist::Chooser, search::Search};
+use self::{
+    device_lost::DeviceLost,
+    downloads::Downloads,
+    history::History,
+    keymap::{Action, KEYMAP},
+    library::Library,
+    playlist::Chooser,
+    search::Search,
+};
 
 // A trait to handle the different screens
 pub trait Screen {
@@ -32,6 +60,10 @@ pub trait Screen {
     fn handle_global_message(&mut self, message: ManagerMessage) -> EventResponse;
     fn close(&mut self, new_screen: Screens) -> EventResponse;
     fn open(&mut self) -> EventResponse;
+    // Reads the data of the screen again, called when `F5` is pressed
+    fn refresh(&mut self) -> EventResponse;
+    // The main keys of the screen, shown on its bottom border
+    fn footer_hints(&self) -> &'static str;
 }
 
 #[derive(Debug, Clone)]
@@ -49,6 +81,24 @@ pub enum ManagerMessage {
     RestartPlayer,
     Quit,
     AddElementToChooser((String, Vec<Video>)),
+    // A short message shown on top of the current screen for a few seconds
+    Toast(String),
+    // Replaces the queue by the songs and opens the player, asks for a confirmation if enabled
+    ReplaceQueue(Vec<Video>),
+    // Replaces the queue like `ReplaceQueue` but stays on the current screen
+    ReplaceQueueInBackground(Vec<Video>),
+    // Scans the downloads and fetches the playlists again
+    Refresh,
+    // Saves the queue as a playlist with the given name, asks for the name if there is none
+    SaveQueue(Option<String>),
+    // Number of files read while scanning the downloads, `None` once the scan is done
+    ScanProgress(Option<usize>),
+    // Shows the metadata of the song on top of the current screen
+    ShowDetails(Video),
+    // The id of the song being played, sent when it changes
+    NowPlaying(Option<String>),
+    // Lists the songs related to this one in the search
+    ShowRelated(Video),
 }
 
 impl ManagerMessage {
@@ -68,6 +118,21 @@ pub enum Screens {
     Playlist = 0x1,
     Search = 0x2,
     DeviceLost = 0x3,
+    History = 0x4,
+    Library = 0x5,
+    Downloads = 0x6,
+}
+
+// What is shown on the screen and changes by itself, see `Manager::animation`
+#[derive(PartialEq)]
+struct Animation {
+    progress: Option<u128>,
+    paused: bool,
+    current: Option<String>,
+    queue: usize,
+    busy: Option<u128>,
+    session_expired: bool,
+    size: Rect,
 }
 
 // The screen manager that handles the different screens
@@ -76,21 +141,70 @@ pub struct Manager {
     chooser: Chooser,
     search: Search,
     device_lost: DeviceLost,
+    history: History,
+    library: Library,
+    downloads: Downloads,
     current_screen: Screens,
+    toast: Option<(String, Instant)>,
+    // A question shown on top of the screen and the message handled if the user accepts
+    confirm: Option<(String, ManagerMessage)>,
+    // The name typed to save the queue as a playlist
+    prompt: Option<String>,
+    // The screen left with `Tab` to see the player, opened again by the next `Tab`
+    previous_screen: Option<Screens>,
+    // The metadata of a song shown until a key is pressed
+    details: Option<Vec<(&'static str, String)>>,
+    // The first line of the keys shown on top of the screen, until `?` or `Esc` is pressed
+    help: Option<usize>,
+    // When `CTRL + C` last stopped a song, a second press soon after quits
+    last_interrupt: Option<Instant>,
 }
 
 impl Manager {
-    pub async fn new(action_sender: Arc<Sender<SoundAction>>, music_player: PlayerState) -> Self {
+    pub async fn new(
+        action_sender: Arc<Sender<SoundAction>>,
+        music_player: PlayerState,
+        start_screen: Screens,
+    ) -> Self {
+        // The invalid keys of the configuration are logged on startup
+        Lazy::force(&keymap::KEYMAP);
         Self {
             music_player,
             chooser: Chooser {
                 selected: 0,
+                moved: false,
                 items: vec![],
+                filter: None,
+                opened: None,
+                track_selected: 0,
+                track_filter: None,
+                confirm_delete: false,
+                scanning: None,
+                playing: None,
+                cached: HashSet::new(),
+                loaded: Vec::new(),
+                action_sender: action_sender.clone(),
+            },
+            history: History {
+                selected: 0,
+                items: Vec::new(),
+                action_sender: action_sender.clone(),
+            },
+            library: Library::new(),
+            downloads: Downloads {
+                selected: 0,
                 action_sender: action_sender.clone(),
             },
             search: Search::new(action_sender).await,
-            current_screen: Screens::Playlist,
+            current_screen: start_screen,
             device_lost: DeviceLost(Vec::new()),
+            toast: None,
+            confirm: None,
+            prompt: None,
+            previous_screen: None,
+            details: None,
+            help: None,
+            last_interrupt: None,
         }
     }
     pub fn current_screen(&mut self) -> &mut dyn Screen {
@@ -102,6 +216,9 @@ impl Manager {
             Screens::Playlist => &mut self.chooser,
             Screens::Search => &mut self.search,
             Screens::DeviceLost => &mut self.device_lost,
+            Screens::History => &mut self.history,
+            Screens::Library => &mut self.library,
+            Screens::Downloads => &mut self.downloads,
         }
     }
     pub fn set_current_screen(&mut self, screen: Screens) {
@@ -137,6 +254,59 @@ impl Manager {
                 self.current_screen().close(e);
                 self.set_current_screen(e);
             }
+            ManagerMessage::Toast(e) => {
+                log_(&e);
+                self.toast = Some((e, Instant::now()));
+            }
+            ManagerMessage::Refresh => {
+                crate::refresh(self.music_player.updater.clone());
+                return self.handle_manager_message(ManagerMessage::Toast(
+                    "Refreshing the playlists".to_owned(),
+                ));
+            }
+            ManagerMessage::ShowDetails(video) => self.details = Some(details::details(&video)),
+            ManagerMessage::SaveQueue(None) => {
+                let player = &self.music_player;
+                if player.previous.is_empty() && player.current.is_none() && player.queue.is_empty()
+                {
+                    return self.handle_manager_message(ManagerMessage::Toast(
+                        "The queue is empty, there is nothing to save".to_owned(),
+                    ));
+                }
+                self.prompt = Some(String::new());
+            }
+            ManagerMessage::SaveQueue(Some(name)) => {
+                let player = &self.music_player;
+                let videos = player
+                    .previous
+                    .iter()
+                    .chain(player.current.iter())
+                    .chain(player.queue.iter())
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let playlist = playlists::LocalPlaylist { name, videos };
+                let toast = match playlists::write(&playlist) {
+                    Ok(()) => format!("Saved the queue as {}", playlist.name),
+                    // The playlist wouldn't be there on the next start
+                    Err(e) => {
+                        return self.handle_manager_message(ManagerMessage::Toast(format!(
+                            "Can't save the queue: {}",
+                            e
+                        )))
+                    }
+                };
+                self.handle_manager_message(
+                    ManagerMessage::AddElementToChooser((playlist.name, playlist.videos))
+                        .pass_to(Screens::Playlist),
+                );
+                return self.handle_manager_message(ManagerMessage::Toast(toast));
+            }
+            ManagerMessage::ReplaceQueue(videos) => {
+                return self.confirm_replace_queue(videos, true);
+            }
+            ManagerMessage::ReplaceQueueInBackground(videos) => {
+                return self.confirm_replace_queue(videos, false);
+            }
             e => {
                 return self.handle_manager_message(ManagerMessage::PassTo(
                     Screens::DeviceLost,
@@ -149,10 +319,208 @@ impl Manager {
         }
         false
     }
+    /**
+     * Handles a message once the user accepted it
+     */
+    fn handle_confirmed(&mut self, message: ManagerMessage) -> bool {
+        match message {
+            ManagerMessage::ReplaceQueue(videos) => self.replace_queue(videos, true),
+            ManagerMessage::ReplaceQueueInBackground(videos) => self.replace_queue(videos, false),
+            e => self.handle_manager_message(e),
+        }
+    }
+    /**
+     * What changes on the screen without an event or a message, the screen is only redrawn when
+     * it changed. The progress of the song and the spinner change on every tick while active.
+     */
+    fn animation(&self, elapsed: Duration, size: Rect) -> Animation {
+        let sink = &self.music_player.sink;
+        let playing = !sink.is_paused() && !sink.is_finished();
+        let busy = !download::is_idle() || crate::api::is_network_busy();
+        Animation {
+            progress: playing.then(|| sink.elapsed().as_millis() / 250),
+            paused: sink.is_paused(),
+            current: self
+                .music_player
+                .current
+                .as_ref()
+                .map(|x| x.video_id.clone()),
+            queue: self.music_player.queue.len(),
+            busy: busy.then_some(elapsed.as_millis() / 250),
+            session_expired: crate::api::is_session_expired(),
+            size,
+        }
+    }
+    /**
+     * Handles `CTRL + C` and `CTRL + D` as set by `interrupt_action`, returns true to quit
+     */
+    fn on_interrupt(&mut self) -> bool {
+        match CONFIG.interrupt_action {
+            InterruptAction::Quit => true,
+            InterruptAction::Confirm => {
+                // Pressed again while asked
+                if matches!(self.confirm, Some((_, ManagerMessage::Quit))) {
+                    return true;
+                }
+                self.confirm = Some(("Quit YTerMusic? (y/n)".to_owned(), ManagerMessage::Quit));
+                false
+            }
+            InterruptAction::StopTrack => {
+                let twice =
+                    matches!(self.last_interrupt, Some(x) if x.elapsed() < Duration::from_secs(1));
+                if twice || self.music_player.current.is_none() {
+                    return true;
+                }
+                self.last_interrupt = Some(Instant::now());
+                self.music_player.apply_sound_action(SoundAction::Next(1));
+                self.handle_manager_message(ManagerMessage::Toast(
+                    "Song stopped, press CTRL + C again to quit".to_owned(),
+                ))
+            }
+        }
+    }
+    /**
+     * Writes what the app is doing to `state_dump.txt` for the bug reports, with the end of the log
+     */
+    fn dump_state(&self) -> Result<(), io::Error> {
+        let player = &self.music_player;
+        let log = std::fs::read_to_string("log.txt").unwrap_or_default();
+        let log = log.lines().collect::<Vec<_>>();
+        let lines = [
+            format!("YTerMusic {}", env!("CARGO_PKG_VERSION")),
+            format!("Screen: {:?}", self.current_screen),
+            format!(
+                "Current song: {}",
+                player
+                    .current
+                    .as_ref()
+                    .map_or("none".to_owned(), |x| format!(
+                        "{} | {} ({})",
+                        x.author, x.title, x.video_id
+                    ))
+            ),
+            format!(
+                "Queue: {} songs, {} played before, {} added on their own",
+                player.queue.len(),
+                player.previous.len(),
+                player.unary_count
+            ),
+            format!(
+                "Player: volume {}%, {}, {:?} elapsed, station {}",
+                player.sink.volume(),
+                if player.sink.is_paused() {
+                    "paused"
+                } else {
+                    "playing"
+                },
+                player.sink.elapsed(),
+                if player.station.is_some() {
+                    "on"
+                } else {
+                    "off"
+                }
+            ),
+            format!(
+                "Downloads: {} queued, {} running, {} failed, backend {}",
+                download::queued_count(),
+                download::IN_DOWNLOAD.lock().unwrap().len(),
+                download::failed().len(),
+                backend::primary().name()
+            ),
+            String::new(),
+            format!("{:#?}", *CONFIG),
+            String::new(),
+            "Log:".to_owned(),
+            log[log.len().saturating_sub(STATE_DUMP_LOG_LINES)..].join("\n"),
+        ];
+        std::fs::write("state_dump.txt", lines.join("\n") + "\n")
+    }
+    /**
+     * Returns true if the key is typed in a text of the current screen, so it doesn't open the help
+     */
+    fn is_typing(&self, key: &KeyEvent) -> bool {
+        if !matches!(key.code, event::KeyCode::Char(_)) {
+            return false;
+        }
+        match self.current_screen {
+            Screens::Search => true,
+            Screens::Playlist => {
+                self.chooser.filter.is_some() || self.chooser.track_filter.is_some()
+            }
+            Screens::MusicPlayer => self.music_player.jump_input.is_some(),
+            _ => false,
+        }
+    }
+    /**
+     * Opens the player, or goes back to the screen that was left to open it
+     */
+    fn toggle_music_player(&mut self) -> bool {
+        let screen = match (self.current_screen, self.previous_screen.take()) {
+            (Screens::DeviceLost, _) => return false,
+            (Screens::MusicPlayer, Some(previous)) => previous,
+            (Screens::MusicPlayer, None) => return false,
+            (current, _) => {
+                self.previous_screen = Some(current);
+                Screens::MusicPlayer
+            }
+        };
+        self.handle_manager_message(ManagerMessage::ChangeState(screen))
+    }
+    /**
+     * Opens the next screen of the cycle playlist selector, search and player
+     */
+    fn cycle_screens(&mut self) -> bool {
+        let screen = match self.current_screen {
+            Screens::DeviceLost => return false,
+            Screens::Playlist => Screens::Search,
+            Screens::Search => Screens::MusicPlayer,
+            Screens::MusicPlayer | Screens::History | Screens::Library | Screens::Downloads => {
+                Screens::Playlist
+            }
+        };
+        self.previous_screen = None;
+        self.handle_manager_message(ManagerMessage::ChangeState(screen))
+    }
+    /**
+     * Replaces the queue, after asking the user if `confirm_clear_queue` is set and the queue isn't empty
+     */
+    fn confirm_replace_queue(&mut self, videos: Vec<Video>, open_player: bool) -> bool {
+        let player = &self.music_player;
+        if CONFIG.confirm_clear_queue && (player.current.is_some() || !player.queue.is_empty()) {
+            let message = if open_player {
+                ManagerMessage::ReplaceQueue(videos)
+            } else {
+                ManagerMessage::ReplaceQueueInBackground(videos)
+            };
+            self.confirm = Some(("Replace the current queue? (y/n)".to_owned(), message));
+            false
+        } else {
+            self.replace_queue(videos, open_player)
+        }
+    }
+    fn replace_queue(&mut self, videos: Vec<Video>, open_player: bool) -> bool {
+        download::replace_queue(&videos, self.music_player.soundaction_sender.clone());
+        if CONFIG.max_queue_len != 0 && videos.len() > CONFIG.max_queue_len {
+            self.handle_manager_message(ManagerMessage::Toast(format!(
+                "The queue is full, only {} of the {} songs are added",
+                CONFIG.max_queue_len,
+                videos.len()
+            )));
+        }
+        if open_player {
+            self.handle_manager_message(ManagerMessage::ChangeState(Screens::MusicPlayer))
+        } else {
+            self.handle_manager_message(ManagerMessage::Toast(format!(
+                "Playing {} songs",
+                videos.len()
+            )))
+        }
+    }
     /**
      * The main loop of the manager
      */
     pub fn run(&mut self, updater: &Receiver<ManagerMessage>) -> Result<(), io::Error> {
+        install_panic_hook();
         // setup terminal
         enable_raw_mode()?;
         let mut stdout = io::stdout();
@@ -164,34 +532,218 @@ impl Manager {
         let tick_rate = Duration::from_millis(250);
 
         let mut last_tick = Instant::now();
+        // Drives the animation of the activity indicator
+        let start = Instant::now();
+        // Last time a key was pressed or a song was playing
+        let mut last_activity = Instant::now();
+        // The monotonic clock stops while the computer is suspended but the system clock doesn't
+        let mut last_loop = (Instant::now(), SystemTime::now());
+        // Set when an event or a message may have changed what is shown
+        let mut dirty = true;
+        let mut last_animation = None;
         'a: loop {
+            let suspended = matches!(
+                last_loop.1.elapsed(),
+                Ok(x) if x > last_loop.0.elapsed() + SUSPEND_GAP
+            );
+            last_loop = (Instant::now(), SystemTime::now());
+            if suspended {
+                log_("Resumed after a suspend, opening the audio output again");
+                self.music_player.reopen_output();
+            }
             while let Ok(e) = updater.try_recv() {
+                dirty = true;
                 if self.handle_manager_message(e) {
                     break 'a;
                 }
             }
+            if !self.music_player.sink.is_paused() && !self.music_player.sink.is_finished() {
+                last_activity = Instant::now();
+            }
+            if CONFIG.idle_quit_secs != 0
+                && last_activity.elapsed() > Duration::from_secs(CONFIG.idle_quit_secs)
+            {
+                log_("Quitting after being idle");
+                break;
+            }
             let rectsize = terminal.size()?;
-            terminal.draw(|f| {
-                self.current_screen().render(f);
-            })?;
+            if matches!(&self.toast, Some((_, time)) if time.elapsed() > TOAST_DURATION) {
+                self.toast = None;
+                dirty = true;
+            }
+            let too_small = rectsize.width < MIN_WIDTH || rectsize.height < MIN_HEIGHT;
+            let animation = Some(self.animation(start.elapsed(), rectsize));
+            let redraw = dirty || !CONFIG.redraw_on_change || animation != last_animation;
+            dirty = false;
+            last_animation = animation;
+            if redraw {
+                terminal.draw(|f| {
+                    if too_small {
+                        f.render_widget(
+                            Paragraph::new("Terminal too small").alignment(Alignment::Center),
+                            f.size(),
+                        );
+                        return;
+                    }
+                    self.current_screen().render(f);
+                    if CONFIG.footer_hints {
+                        render_footer(f, self.current_screen().footer_hints());
+                    }
+                    if crate::api::is_session_expired() {
+                        render_banner(
+                        f,
+                        "Session expired, refresh headers.txt and press r in the playlist selector",
+                    );
+                    }
+                    if crate::api::is_network_busy() {
+                        render_activity(f, start.elapsed());
+                    }
+                    if let Some(details) = &self.details {
+                        details::render(f, details);
+                    }
+                    if let Some(scroll) = self.help {
+                        help::render(f, scroll);
+                    }
+                    if let Some((toast, _)) = &self.toast {
+                        render_toast(f, toast);
+                    }
+                    if let Some((question, _)) = &self.confirm {
+                        render_modal(f, " Confirm ", question);
+                    }
+                    if let Some(name) = &self.prompt {
+                        render_modal(
+                            f,
+                            " Save the queue as (Enter to save, Esc to cancel) ",
+                            &format!("{}_", name),
+                        );
+                    }
+                })?;
+            } else if self.current_screen == Screens::MusicPlayer {
+                // The player is updated by its rendering
+                self.music_player.update();
+            }
 
+            // Nothing changes on the screen while idle, the keys are still handled right away
+            let idle = last_activity.elapsed() > LOW_POWER_DELAY
+                && download::is_idle()
+                && !crate::api::is_network_busy()
+                && self.toast.is_none();
+            let tick_rate = if CONFIG.idle_tick_ms != 0 && idle {
+                Duration::from_millis(CONFIG.idle_tick_ms).max(tick_rate)
+            } else {
+                tick_rate
+            };
             let timeout = tick_rate
                 .checked_sub(last_tick.elapsed())
                 .unwrap_or_else(|| Duration::from_secs(0));
             if crossterm::event::poll(timeout)? {
-                match event::read()? {
+                let event = event::read()?;
+                dirty = true;
+                if matches!(event, Event::Key(_) | Event::Mouse(_)) {
+                    last_activity = Instant::now();
+                }
+                match event {
                     Event::Key(key) => {
                         if (key.code == event::KeyCode::Char('c')
                             || key.code == event::KeyCode::Char('d'))
                             && key.modifiers == KeyModifiers::CONTROL
                         {
-                            break;
+                            if self.on_interrupt() {
+                                break;
+                            }
+                            continue;
+                        }
+                        if self.details.take().is_some() {
+                            continue;
+                        }
+                        if let Some(scroll) = &mut self.help {
+                            let last = help::lines().len().saturating_sub(1);
+                            match key.code {
+                                event::KeyCode::Esc => self.help = None,
+                                _ if KEYMAP.matches(Action::Help, &key) => self.help = None,
+                                event::KeyCode::Up => *scroll = scroll.saturating_sub(1),
+                                event::KeyCode::Down => *scroll = (*scroll + 1).min(last),
+                                event::KeyCode::PageUp => *scroll = scroll.saturating_sub(10),
+                                event::KeyCode::PageDown => *scroll = (*scroll + 10).min(last),
+                                _ => {}
+                            }
+                            continue;
+                        }
+                        // The confirmation takes every key until it is answered
+                        if let Some((_, message)) = self.confirm.take() {
+                            if let event::KeyCode::Char('y') | event::KeyCode::Enter = key.code {
+                                if self.handle_confirmed(message) {
+                                    break;
+                                }
+                            }
+                            continue;
+                        }
+                        if let Some(name) = &mut self.prompt {
+                            match key.code {
+                                event::KeyCode::Esc => self.prompt = None,
+                                event::KeyCode::Enter if !name.trim().is_empty() => {
+                                    let name = name.trim().to_owned();
+                                    self.prompt = None;
+                                    if self.handle_manager_message(ManagerMessage::SaveQueue(Some(
+                                        name,
+                                    ))) {
+                                        break;
+                                    }
+                                }
+                                event::KeyCode::Backspace => {
+                                    name.pop();
+                                }
+                                event::KeyCode::Char(c) => name.push(c),
+                                _ => {}
+                            }
+                            continue;
+                        }
+                        if KEYMAP.matches(Action::Help, &key) && !self.is_typing(&key) {
+                            self.help = Some(0);
+                            continue;
+                        }
+                        // Most terminals send `Ctrl + Tab` as a plain `Tab`, `Shift + Tab` works everywhere
+                        if key.code == event::KeyCode::BackTab
+                            || key.code == event::KeyCode::Tab
+                                && key.modifiers.contains(KeyModifiers::CONTROL)
+                        {
+                            if self.cycle_screens() {
+                                break;
+                            }
+                            continue;
+                        }
+                        if key.code == event::KeyCode::Tab {
+                            if self.toggle_music_player() {
+                                break;
+                            }
+                            continue;
+                        }
+                        if CONFIG.state_dump_key != 0
+                            && key.code == event::KeyCode::F(CONFIG.state_dump_key)
+                        {
+                            let message = match self.dump_state() {
+                                Ok(()) => "State written to state_dump.txt".to_owned(),
+                                Err(e) => format!("Can't write state_dump.txt: {}", e),
+                            };
+                            if self.handle_manager_message(ManagerMessage::Toast(message)) {
+                                break;
+                            }
+                            continue;
+                        }
+                        if key.code == event::KeyCode::F(5) {
+                            let k = self.current_screen().refresh();
+                            if self.handle_event(k) {
+                                break;
+                            }
+                            continue;
                         }
                         let k = self.current_screen().on_key_press(key, &rectsize);
                         if self.handle_event(k) {
                             break;
                         }
                     }
+                    // The help is closed with the keyboard only
+                    Event::Mouse(_) if too_small || self.help.is_some() => (),
                     Event::Mouse(mouse) => {
                         let k = self.current_screen().on_mouse_press(mouse, &rectsize);
                         if self.handle_event(k) {
@@ -214,14 +766,183 @@ impl Manager {
             DisableMouseCapture
         )?;
         terminal.show_cursor()?;
+        title::restore();
 
         Ok(())
     }
 }
 
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+// Difference between the system clock and the monotonic clock meaning the computer was suspended
+const SUSPEND_GAP: Duration = Duration::from_secs(10);
+// The smallest terminal the screens are drawn in
+const MIN_WIDTH: u16 = 20;
+const MIN_HEIGHT: u16 = 6;
+// Time without playback, download nor key press before redrawing less often
+const LOW_POWER_DELAY: Duration = Duration::from_secs(10);
+// Number of lines at the end of the log copied to `state_dump.txt`
+const STATE_DUMP_LOG_LINES: usize = 100;
+
+/**
+ * Renders the toast message in the top right corner of the screen
+ */
+fn render_toast(frame: &mut Frame<CrosstermBackend<Stdout>>, toast: &str) {
+    let size = frame.size();
+    let width = (toast.chars().count() as u16 + 4).min(size.width);
+    if width < 5 || size.height < 3 {
+        return;
+    }
+    frame.render_widget(Clear, Rect::new(size.width - width, 0, width, 3));
+    frame.render_widget(
+        Paragraph::new(toast)
+            .style(Style::default().fg(Color::Yellow))
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL)),
+        Rect::new(size.width - width, 0, width, 3),
+    );
+}
+
+/**
+ * Restores the terminal before the panic message is printed and writes the settings and the
+ * stats, so a crash doesn't leave the terminal unusable
+ */
+fn install_panic_hook() {
+    let default = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        // The interface runs on the main thread, a panic in a task doesn't stop it
+        if std::thread::current().name() == Some("main") {
+            let _ = disable_raw_mode();
+            let _ = execute!(
+                io::stdout(),
+                LeaveAlternateScreen,
+                DisableMouseCapture,
+                crossterm::cursor::Show
+            );
+        }
+        crate::flusher::flush();
+        default(info);
+    }));
+}
+
+/**
+ * Renders the hints on the bottom border of the screen
+ */
+fn render_footer(frame: &mut Frame<CrosstermBackend<Stdout>>, hints: &str) {
+    let size = frame.size();
+    let text = format!(" {} ", hints);
+    let width = (text.chars().count() as u16).min(size.width.saturating_sub(4));
+    if hints.is_empty() || width == 0 || size.height == 0 {
+        return;
+    }
+    frame.render_widget(
+        Paragraph::new(text).style(Style::default().fg(Color::DarkGray)),
+        Rect::new(2, size.height - 1, width, 1),
+    );
+}
+
+// Whether the symbols are replaced by ASCII characters, see `glyphs` in the config
+static ASCII_GLYPHS: Lazy<bool> = Lazy::new(|| match CONFIG.glyphs {
+    Glyphs::Unicode => false,
+    Glyphs::Ascii => true,
+    Glyphs::Auto => {
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .find_map(|x| std::env::var(x).ok().filter(|x| !x.is_empty()))
+            .unwrap_or_default()
+            .to_lowercase();
+        let utf8 = locale.contains("utf-8") || locale.contains("utf8");
+        // Windows terminals don't set the locale variables
+        (!utf8 && !cfg!(windows)) || matches!(std::env::var("TERM").as_deref(), Ok("linux"))
+    }
+});
+
+pub fn ascii_glyphs() -> bool {
+    *ASCII_GLYPHS
+}
+
+// The frames of the activity indicator
+const SPINNER: [&str; 8] = ["⠋", "⠙", "⠸", "⠴", "⠦", "⠇", "⠏", "⠹"];
+const ASCII_SPINNER: [&str; 4] = ["|", "/", "-", "\\"];
+
+/**
+ * Renders a spinner in the top right corner of the screen while the network is used
+ */
+fn render_activity(frame: &mut Frame<CrosstermBackend<Stdout>>, elapsed: Duration) {
+    let size = frame.size();
+    if size.width < 4 || size.height == 0 {
+        return;
+    }
+    let frames: &[&str] = if ascii_glyphs() {
+        &ASCII_SPINNER
+    } else {
+        &SPINNER
+    };
+    let index = (elapsed.as_millis() / 250) as usize % frames.len();
+    frame.render_widget(
+        Paragraph::new(format!(" {} ", frames[index])).style(Style::default().fg(Color::Cyan)),
+        Rect::new(size.width - 4, 0, 3, 1),
+    );
+}
+
+/**
+ * Renders a warning in the top left corner of the screen, shown until its cause is fixed
+ */
+fn render_banner(frame: &mut Frame<CrosstermBackend<Stdout>>, text: &str) {
+    let size = frame.size();
+    let width = (text.chars().count() as u16 + 4).min(size.width);
+    if width < 5 || size.height < 3 {
+        return;
+    }
+    frame.render_widget(Clear, Rect::new(0, 0, width, 3));
+    frame.render_widget(
+        Paragraph::new(text)
+            .style(Style::default().fg(Color::Red))
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL)),
+        Rect::new(0, 0, width, 3),
+    );
+}
+
+/**
+ * Renders a box with a text in the middle of the screen
+ */
+fn render_modal(frame: &mut Frame<CrosstermBackend<Stdout>>, title: &str, text: &str) {
+    let size = frame.size();
+    let width = (text.chars().count().max(title.chars().count()) as u16 + 4).min(size.width);
+    if width < 5 || size.height < 3 {
+        return;
+    }
+    let area = Rect::new((size.width - width) / 2, (size.height - 3) / 2, width, 3);
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(text)
+            .style(Style::default().fg(Color::White))
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL).title(title)),
+        area,
+    );
+}
+
+/**
+ * Cuts the text to fit in the given number of characters, ending with `…` when it is cut
+ */
+pub fn ellipsize(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_owned();
+    }
+    let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+    if width != 0 {
+        cut.push('…');
+    }
+    cut
+}
+
 // UTILS SECTION TO SPLIT THE TERMINAL INTO DIFFERENT PARTS
 
+// The parts are cut to the size of the area when it is too small, the last part may be empty
+
 pub fn split_y_start(f: Rect, start_size: u16) -> [Rect; 2] {
+    let start_size = start_size.min(f.height);
     let mut rectlistvol = f;
     rectlistvol.height = start_size;
     let mut rectprogress = f;
@@ -230,6 +951,7 @@ pub fn split_y_start(f: Rect, start_size: u16) -> [Rect; 2] {
     [rectlistvol, rectprogress]
 }
 pub fn split_y(f: Rect, end_size: u16) -> [Rect; 2] {
+    let end_size = end_size.min(f.height);
     let mut rectlistvol = f;
     rectlistvol.height -= end_size;
     let mut rectprogress = f;
@@ -238,6 +960,7 @@ pub fn split_y(f: Rect, end_size: u16) -> [Rect; 2] {
     [rectlistvol, rectprogress]
 }
 pub fn split_x(f: Rect, end_size: u16) -> [Rect; 2] {
+    let end_size = end_size.min(f.width);
     let mut rectlistvol = f;
     rectlistvol.width -= end_size;
     let mut rectprogress = f;
@@ -248,9 +971,9 @@ pub fn split_x(f: Rect, end_size: u16) -> [Rect; 2] {
 
 pub fn rect_contains(rect: &Rect, x: u16, y: u16, margin: u16) -> bool {
     rect.x + margin <= x
-        && x <= rect.x + rect.width - margin
+        && x <= (rect.x + rect.width).saturating_sub(margin)
         && rect.y + margin <= y
-        && y <= rect.y + rect.height - margin
+        && y <= (rect.y + rect.height).saturating_sub(margin)
 }
 
 pub fn relative_pos(rect: &Rect, x: u16, y: u16, margin: u16) -> (u16, u16) {
@@ -1,17 +1,30 @@
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEventKind};
+use std::time::{Duration, Instant};
+
+use crossterm::event::{KeyCode, KeyEvent, MouseEventKind};
 
 use tui::{
-    style::{Color, Style},
-    widgets::{Block, Borders, Gauge, List, ListState},
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Gauge, List, ListState, Widget},
 };
+use ytpapi::Video;
 
 use crate::{
-    systems::player::{generate_music, get_action, PlayerState},
+    config::{EscAction, IdleSpaceAction, ProgressClick, ProgressLabel, QueueClick, CONFIG},
+    systems::{
+        download::download_lines,
+        logger::log_,
+        player::{generate_music, get_action, row_height, PlayerState},
+    },
     SoundAction,
 };
 
 use super::{
-    rect_contains, relative_pos, split_x, split_y, EventResponse, ManagerMessage, Screen, Screens,
+    details,
+    keymap::{Action, KEYMAP},
+    playlist, rect_contains, relative_pos, split_x, split_y, EventResponse, ManagerMessage, Screen,
+    Screens,
 };
 
 #[derive(Debug, Clone, PartialEq)]
@@ -45,17 +58,43 @@ pub enum MusicStatus {
     Paused,
     Previous,
     Next,
+    // Waiting for a free downloader
+    Queued,
+    // Fetching the video information before the download
+    Connecting,
+    // The audio is being transferred
     Downloading,
+    // Given to the player but no sound was played yet
+    Buffering,
 }
 
 impl MusicStatus {
     pub fn character(&self) -> char {
+        if super::ascii_glyphs() {
+            return self.ascii_character();
+        }
         match self {
             MusicStatus::Playing => '▶',
             MusicStatus::Paused => '⏸',
             MusicStatus::Previous => ' ',
             MusicStatus::Next => ' ',
+            MusicStatus::Queued => '◌',
+            MusicStatus::Connecting => '⇄',
             MusicStatus::Downloading => '⭳',
+            MusicStatus::Buffering => '◔',
+        }
+    }
+
+    fn ascii_character(&self) -> char {
+        match self {
+            MusicStatus::Playing => '>',
+            MusicStatus::Paused => '=',
+            MusicStatus::Previous => ' ',
+            MusicStatus::Next => ' ',
+            MusicStatus::Queued => '.',
+            MusicStatus::Connecting => '~',
+            MusicStatus::Downloading => 'v',
+            MusicStatus::Buffering => 'o',
         }
     }
 
@@ -65,7 +104,10 @@ impl MusicStatus {
             MusicStatus::Paused => (Color::Yellow, Color::Black),
             MusicStatus::Previous => (Color::White, Color::Black),
             MusicStatus::Next => (Color::White, Color::Black),
+            MusicStatus::Queued => (Color::DarkGray, Color::Black),
+            MusicStatus::Connecting => (Color::LightBlue, Color::Black),
             MusicStatus::Downloading => (Color::Blue, Color::Black),
+            MusicStatus::Buffering => (Color::Cyan, Color::Black),
         }
     }
 }
@@ -76,24 +118,61 @@ impl Screen for PlayerState {
         mouse_event: crossterm::event::MouseEvent,
         frame_data: &tui::layout::Rect,
     ) -> EventResponse {
+        let x = mouse_event.column;
+        let y = mouse_event.row;
+        let [top_rect, progress_rect] = split_y(*frame_data, 3);
+        let (list_rect, volume_rect) = self.top_layout(top_rect);
+        if let MouseEventKind::ScrollUp | MouseEventKind::ScrollDown = &mouse_event.kind {
+            let up = mouse_event.kind == MouseEventKind::ScrollUp;
+            if matches!(&volume_rect, Some(e) if rect_contains(e, x, y, 0)) {
+                for _ in 0..CONFIG.scroll_sensitivity {
+                    self.apply_sound_action(if up {
+                        SoundAction::Plus
+                    } else {
+                        SoundAction::Minus
+                    });
+                }
+            } else if rect_contains(&list_rect, x, y, 0) {
+                self.list_offset = if up {
+                    self.list_offset.saturating_sub(CONFIG.scroll_sensitivity)
+                } else {
+                    (self.list_offset + CONFIG.scroll_sensitivity)
+                        .min(self.list_len().saturating_sub(1))
+                };
+            }
+        }
         if let MouseEventKind::Down(_) = &mouse_event.kind {
-            let x = mouse_event.column;
-            let y = mouse_event.row;
-            let [top_rect, _] = split_y(*frame_data, 3);
-            let [list_rect, _] = split_x(top_rect, 10);
-            if rect_contains(&list_rect, x, y, 1) {
+            if rect_contains(&progress_rect, x, y, 1) {
+                // The label is centered inside the borders of the gauge
+                let label_width = progress_label(self).chars().count() as u16;
+                let label_start =
+                    progress_rect.x + 1 + (progress_rect.width - 2).saturating_sub(label_width) / 2;
+                if x >= label_start && x < label_start + label_width {
+                    self.toggle_remaining();
+                } else if CONFIG.progress_click == ProgressClick::Seek {
+                    let (x, _) = relative_pos(&progress_rect, x, y, 1);
+                    self.seek_to_ratio(
+                        f64::from(x) / f64::from(progress_rect.width.saturating_sub(2).max(1)),
+                    );
+                }
+            } else if rect_contains(&list_rect, x, y, 1) {
                 let (_, y) = relative_pos(&list_rect, x, y, 1);
-                match get_action(y as usize, &self.queue, &self.previous, &self.current) {
-                    Some(MusicStatusAction::Skip(a)) => {
-                        self.apply_sound_action(SoundAction::Next(a));
-                    }
-                    Some(MusicStatusAction::Current) => {
-                        self.apply_sound_action(SoundAction::PlayPause);
-                    }
-                    Some(MusicStatusAction::Before(a)) => {
-                        self.apply_sound_action(SoundAction::Previous(a));
-                    }
-                    None | Some(MusicStatusAction::Downloading) => (),
+                let line = y as usize / row_height() + self.list_offset;
+                // The empty space below the queue
+                if line >= self.list_len() {
+                    self.selected = None;
+                    self.last_click = None;
+                    return EventResponse::None;
+                }
+                let double_click = matches!(
+                    self.last_click.take(),
+                    Some((last, time)) if last == line && time.elapsed() < DOUBLE_CLICK
+                );
+                if CONFIG.queue_click == QueueClick::Play || double_click {
+                    self.play_line(line);
+                } else {
+                    self.selected = self.video_at_line(line).map(|x| x.video_id.clone());
+                    self.last_click = Some((line, Instant::now()));
                 }
             }
         }
@@ -101,62 +180,209 @@ impl Screen for PlayerState {
     }
 
     fn on_key_press(&mut self, key: KeyEvent, _: &tui::layout::Rect) -> EventResponse {
+        if self.jump_input.is_some() {
+            return self.on_jump_key_press(key);
+        }
         match key.code {
-            KeyCode::Esc => ManagerMessage::ChangeState(Screens::Playlist).event(),
-            KeyCode::Char('f') => ManagerMessage::ChangeState(Screens::Search).event(),
-            KeyCode::Char(' ') => {
+            KeyCode::Esc if CONFIG.esc_music_player == EscAction::Nothing => {
+                return EventResponse::None
+            }
+            KeyCode::Esc => return ManagerMessage::ChangeState(Screens::Playlist).event(),
+            _ => {}
+        }
+        let action = match KEYMAP.action(&key) {
+            Some(action) => action,
+            None => return EventResponse::None,
+        };
+        match action {
+            Action::OpenSearch => ManagerMessage::ChangeState(Screens::Search).event(),
+            Action::OpenHistory => ManagerMessage::ChangeState(Screens::History).event(),
+            Action::ShowRelated => self.current.clone().map_or(EventResponse::None, |x| {
+                EventResponse::Message(vec![
+                    ManagerMessage::ChangeState(Screens::Search),
+                    ManagerMessage::ShowRelated(x).pass_to(Screens::Search),
+                ])
+            }),
+            Action::CycleRepeat => ManagerMessage::Toast(self.cycle_repeat()).event(),
+            Action::OpenDownloads => ManagerMessage::ChangeState(Screens::Downloads).event(),
+            Action::SaveQueue => ManagerMessage::SaveQueue(None).event(),
+            Action::ShowDetails => self.current.clone().map_or(EventResponse::None, |x| {
+                ManagerMessage::ShowDetails(x).event()
+            }),
+            Action::Reveal => self.current.as_ref().map_or(EventResponse::None, |x| {
+                ManagerMessage::Toast(details::reveal(x)).event()
+            }),
+            Action::PlayPause if self.current.is_none() && self.status() == AppStatus::NoMusic => {
+                self.on_idle_space()
+            }
+            Action::PlayPause => {
                 self.apply_sound_action(SoundAction::PlayPause);
                 EventResponse::None
             }
-            KeyCode::Char('+') | KeyCode::Up => {
-                self.apply_sound_action(SoundAction::Plus);
+            Action::PlaySelected => {
+                if let Some(line) = self.selected_line() {
+                    self.play_line(line);
+                }
                 EventResponse::None
             }
-            KeyCode::Char('-') | KeyCode::Down => {
-                self.apply_sound_action(SoundAction::Minus);
+            Action::SelectUp => {
+                self.move_selection(-1);
                 EventResponse::None
             }
-            KeyCode::Char('<') | KeyCode::Left => {
-                if key.modifiers.contains(KeyModifiers::CONTROL) {
-                    self.apply_sound_action(SoundAction::Previous(1));
+            Action::SelectDown => {
+                self.move_selection(1);
+                EventResponse::None
+            }
+            Action::MoveUp | Action::MoveDown => {
+                let from = match self.selected_queue_index() {
+                    Some(e) => e,
+                    None => return EventResponse::None,
+                };
+                let to = if action == Action::MoveUp {
+                    from.checked_sub(1)
                 } else {
-                    self.apply_sound_action(SoundAction::Backward);
+                    Some(from + 1).filter(|x| *x < self.queue.len())
+                };
+                // The selection follows the song as it is kept by id
+                if let Some(to) = to {
+                    self.apply_sound_action(SoundAction::MoveInQueue { from, to });
                 }
                 EventResponse::None
             }
-            KeyCode::Char('>') | KeyCode::Right => {
-                if key.modifiers.contains(KeyModifiers::CONTROL) {
-                    self.apply_sound_action(SoundAction::Next(1));
-                } else {
-                    self.apply_sound_action(SoundAction::Forward);
+            Action::RemoveFromQueue => {
+                if let Some(index) = self.selected_queue_index() {
+                    self.apply_sound_action(SoundAction::RemoveFromQueue(index));
+                    // The next song is selected, or the previous one if it was the last
+                    self.selected = self
+                        .queue
+                        .get(index)
+                        .or_else(|| self.queue.back())
+                        .map(|x| x.video_id.clone());
                 }
                 EventResponse::None
             }
-            _ => EventResponse::None,
+            Action::ChapterForward => {
+                self.apply_sound_action(SoundAction::ChapterForward);
+                EventResponse::None
+            }
+            Action::ChapterBackward => {
+                self.apply_sound_action(SoundAction::ChapterBackward);
+                EventResponse::None
+            }
+            Action::ToggleRemaining => {
+                self.toggle_remaining();
+                EventResponse::None
+            }
+            Action::LoopStart => self
+                .set_loop_start()
+                .map_or(EventResponse::None, |x| ManagerMessage::Toast(x).event()),
+            Action::LoopEnd => self
+                .set_loop_end()
+                .map_or(EventResponse::None, |x| ManagerMessage::Toast(x).event()),
+            Action::ClearLoop => self
+                .clear_loop()
+                .map_or(EventResponse::None, |x| ManagerMessage::Toast(x).event()),
+            Action::ToggleIntro => self
+                .toggle_intro()
+                .map_or(EventResponse::None, |x| ManagerMessage::Toast(x).event()),
+            Action::ToggleOutro => self
+                .toggle_outro()
+                .map_or(EventResponse::None, |x| ManagerMessage::Toast(x).event()),
+            Action::VolumePanel => {
+                self.toggle_volume_panel();
+                EventResponse::None
+            }
+            Action::CycleEqualizer => self
+                .cycle_equalizer()
+                .map_or(EventResponse::None, |x| ManagerMessage::Toast(x).event()),
+            Action::Like => self
+                .like_current()
+                .map_or(EventResponse::None, |x| ManagerMessage::Toast(x).event()),
+            Action::Dislike => self
+                .dislike_current()
+                .map_or(EventResponse::None, |x| ManagerMessage::Toast(x).event()),
+            Action::Jump => {
+                self.jump_input = Some(String::new());
+                EventResponse::None
+            }
+            Action::FollowCurrent => {
+                self.follow_current = !self.follow_current;
+                EventResponse::None
+            }
+            Action::CopyLink => {
+                if let Some(video) = &self.current {
+                    let url = format!("https://music.youtube.com/watch?v={}", video.video_id);
+                    match copy_to_clipboard(&url) {
+                        Ok(()) => {
+                            return ManagerMessage::Toast(format!("Copied {}", url)).event();
+                        }
+                        Err(e) => log_(format!("Can't copy to the clipboard: {}", e)),
+                    }
+                }
+                EventResponse::None
+            }
+            Action::VolumeUp => {
+                self.apply_sound_action(SoundAction::Plus);
+                EventResponse::None
+            }
+            Action::VolumeDown => {
+                self.apply_sound_action(SoundAction::Minus);
+                EventResponse::None
+            }
+            Action::ToggleMute => {
+                self.apply_sound_action(SoundAction::ToggleMute);
+                EventResponse::None
+            }
+            Action::SeekBackward => {
+                self.apply_sound_action(SoundAction::Seek(-SHIFT_SEEK_SECONDS));
+                EventResponse::None
+            }
+            Action::SeekForward => {
+                self.apply_sound_action(SoundAction::Seek(SHIFT_SEEK_SECONDS));
+                EventResponse::None
+            }
+            Action::Previous => {
+                self.apply_sound_action(SoundAction::Previous(1));
+                EventResponse::None
+            }
+            Action::Next => {
+                self.apply_sound_action(SoundAction::Next(1));
+                EventResponse::None
+            }
+            Action::Backward => {
+                self.apply_sound_action(SoundAction::Backward);
+                EventResponse::None
+            }
+            Action::Forward => {
+                self.apply_sound_action(SoundAction::Forward);
+                EventResponse::None
+            }
+            // Only bound in the search, the help is opened by the manager
+            Action::StartStation | Action::CycleSearchType | Action::AddToQueue | Action::Help => {
+                EventResponse::None
+            }
         }
     }
 
     fn render(&mut self, f: &mut tui::Frame<tui::backend::CrosstermBackend<std::io::Stdout>>) {
         self.update();
         let [top_rect, progress_rect] = split_y(f.size(), 3);
-        let [list_rect, volume_rect] = split_x(top_rect, 10);
-        let colors = if self.sink.is_paused() {
-            AppStatus::Paused
-        } else if self.sink.is_finished() {
-            AppStatus::NoMusic
-        } else {
-            AppStatus::Playing
+        let (list_rect, volume_rect) = self.top_layout(top_rect);
+        let colors = self.status().colors();
+        if let Some(volume_rect) = volume_rect {
+            f.render_widget(
+                Gauge::default()
+                    .block(Block::default().title(" Volume ").borders(Borders::ALL))
+                    .gauge_style(Style::default().fg(colors.0).bg(colors.1))
+                    .ratio((self.sink.volume() as f64 / 100.).clamp(0.0, 1.0))
+                    .label(if self.muted.is_some() {
+                        "Muted".to_owned()
+                    } else {
+                        format!("{}%", self.sink.volume())
+                    }),
+                volume_rect,
+            );
         }
-        .colors();
-        f.render_widget(
-            Gauge::default()
-                .block(Block::default().title(" Volume ").borders(Borders::ALL))
-                .gauge_style(Style::default().fg(colors.0).bg(colors.1))
-                .ratio((self.sink.volume() as f64 / 100.).clamp(0.0, 1.0)),
-            volume_rect,
-        );
-        let current_time = self.sink.elapsed().as_secs();
-        let total_time = self.sink.duration().map(|x| x as u32).unwrap_or(0);
         f.render_widget(
             Gauge::default()
                 .block(
@@ -165,7 +391,13 @@ impl Screen for PlayerState {
                             self.current
                                 .as_ref()
                                 .map(|x| format!(" {} | {} ", x.author, x.title))
-                                .unwrap_or_else(|| " No music playing ".to_owned()),
+                                .unwrap_or_else(|| " No music playing ".to_owned())
+                                + &self
+                                    .repeat
+                                    .label()
+                                    .map(|x| format!("[{}] ", x))
+                                    .unwrap_or_default()
+                                + if self.muted.is_some() { "[muted] " } else { "" },
                         )
                         .borders(Borders::ALL),
                 )
@@ -178,25 +410,75 @@ impl Screen for PlayerState {
                     }
                     .clamp(0.0, 1.0),
                 )
-                .label(format!(
-                    "{}:{:02} / {}:{:02}",
-                    current_time / 60,
-                    current_time % 60,
-                    total_time / 60,
-                    total_time % 60
-                )),
+                .label(progress_label(self)),
             progress_rect,
         );
+        if let (Some(video), Some(duration)) = (&self.current, self.sink.duration()) {
+            f.render_widget(
+                ChapterTicks {
+                    starts: video.chapters.iter().map(|x| x.start).collect(),
+                    duration,
+                },
+                progress_rect,
+            );
+        }
+        if let (Some((_, start, end)), Some(duration)) = (&self.ab_loop, self.sink.duration()) {
+            f.render_widget(
+                LoopMarkers {
+                    start: start.as_secs_f64(),
+                    end: end.map(|x| x.as_secs_f64()),
+                    duration,
+                },
+                progress_rect,
+            );
+        }
+        if self.follow_current && self.current.is_some() {
+            let current_line = download_lines() + self.previous.len().min(3);
+            let visible = list_rect.height.saturating_sub(2) as usize / row_height();
+            self.list_offset = current_line.saturating_sub(visible / 2);
+        }
         // Create a List from all list items and highlight the currently selected one
+        let selected = self.selected_line();
         f.render_stateful_widget(
-            List::new(generate_music(
-                f.size().height as usize,
-                &self.queue,
-                &self.previous,
-                &self.current,
-                &self.sink,
-            ))
-            .block(Block::default().borders(Borders::ALL).title(" Playlist ")),
+            List::new(
+                generate_music(
+                    f.size().height as usize / row_height() + self.list_offset,
+                    // Without the borders
+                    list_rect.width.saturating_sub(2) as usize,
+                    &self.queue,
+                    &self.previous,
+                    &self.current,
+                    &self.sink,
+                )
+                .into_iter()
+                .enumerate()
+                .map(|(index, item)| {
+                    if Some(index) == selected {
+                        item.style(Style::default().add_modifier(Modifier::REVERSED))
+                    } else {
+                        item
+                    }
+                })
+                .skip(self.list_offset)
+                .collect::<Vec<_>>(),
+            )
+            .block(Block::default().borders(Borders::ALL).title({
+                let jump = self
+                    .jump_input
+                    .as_ref()
+                    .map(|x| format!("| Go to song: {}_ ", x))
+                    .unwrap_or_default();
+                let equalizer = self
+                    .equalizer
+                    .and_then(|x| CONFIG.equalizer_presets.get(x))
+                    .map(|x| format!("| EQ {} ", x.name))
+                    .unwrap_or_default();
+                if self.follow_current {
+                    format!(" Playlist (following) {}{}", equalizer, jump)
+                } else {
+                    format!(" Playlist {}{}", equalizer, jump)
+                }
+            })),
             list_rect,
             &mut ListState::default(),
         );
@@ -205,8 +487,12 @@ impl Screen for PlayerState {
     fn handle_global_message(&mut self, message: ManagerMessage) -> EventResponse {
         match message {
             ManagerMessage::RestartPlayer => {
-                self.apply_sound_action(SoundAction::RestartPlayer);
-                ManagerMessage::ChangeState(Screens::MusicPlayer).event()
+                // The error stays on the screen if the device is still missing
+                if self.reopen_output() {
+                    ManagerMessage::ChangeState(Screens::MusicPlayer).event()
+                } else {
+                    EventResponse::None
+                }
             }
             _ => EventResponse::None,
         }
@@ -221,4 +507,324 @@ impl Screen for PlayerState {
         //self.apply_sound_action(SoundAction::ForcePlay);
         EventResponse::None
     }
+
+    fn refresh(&mut self) -> EventResponse {
+        self.check_current()
+            .map_or(EventResponse::None, |x| ManagerMessage::Toast(x).event())
+    }
+
+    fn footer_hints(&self) -> &'static str {
+        &HINTS
+    }
+}
+
+// Maximum time between the two clicks of a double click
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+// Seconds skipped by `Shift` and the arrows, the arrows alone skip 5 seconds
+const SHIFT_SEEK_SECONDS: i64 = 10;
+
+/**
+ * The text shown on the progress gauge, the `progress_label` of the config or the remaining time
+ * once switched with `t`
+ */
+fn progress_label(player: &PlayerState) -> String {
+    let current_time = player.sink.elapsed().as_secs();
+    let total_time = player.sink.duration().map(|x| x as u64).unwrap_or(0);
+    let style = match (player.show_remaining, CONFIG.progress_label) {
+        (false, style) => style,
+        (true, ProgressLabel::Remaining) => ProgressLabel::ElapsedTotal,
+        (true, _) => ProgressLabel::Remaining,
+    };
+    match style {
+        ProgressLabel::None => String::new(),
+        ProgressLabel::Elapsed => format!("{}:{:02}", current_time / 60, current_time % 60),
+        ProgressLabel::ElapsedTotal => format!(
+            "{}:{:02} / {}:{:02}",
+            current_time / 60,
+            current_time % 60,
+            total_time / 60,
+            total_time % 60
+        ),
+        ProgressLabel::Percentage => format!(
+            "{}%",
+            (current_time * 100)
+                .checked_div(total_time)
+                .unwrap_or(0)
+                .min(100)
+        ),
+        ProgressLabel::Remaining => {
+            let remaining_time = total_time.saturating_sub(current_time);
+            format!(
+                "-{}:{:02} / {}:{:02}",
+                remaining_time / 60,
+                remaining_time % 60,
+                total_time / 60,
+                total_time % 60
+            )
+        }
+    }
+}
+
+// The keys of the main actions as they are bound, shown in the footer
+static HINTS: once_cell::sync::Lazy<String> = once_cell::sync::Lazy::new(|| {
+    KEYMAP.hints(&[
+        (&[Action::PlayPause], "play/pause"),
+        (&[Action::Backward, Action::Forward], "seek"),
+        (&[Action::Previous, Action::Next], "skip"),
+        (&[Action::VolumeUp, Action::VolumeDown], "volume"),
+        (&[Action::ToggleMute], "mute"),
+        (&[Action::OpenSearch], "search"),
+        (&[Action::ShowDetails], "details"),
+        (&[Action::Help], "help"),
+    ]) + " · esc back"
+});
+
+// Kept alive because on some platforms the copied text disappears with the clipboard handle
+#[cfg(feature = "clipboard")]
+static CLIPBOARD: once_cell::sync::Lazy<std::sync::Mutex<Option<arboard::Clipboard>>> =
+    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(None));
+
+#[cfg(feature = "clipboard")]
+pub fn copy_to_clipboard(text: &str) -> Result<(), String> {
+    let mut clipboard = CLIPBOARD.lock().unwrap();
+    if clipboard.is_none() {
+        *clipboard = Some(arboard::Clipboard::new().map_err(|e| e.to_string())?);
+    }
+    clipboard
+        .as_mut()
+        .unwrap()
+        .set_text(text.to_owned())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub fn copy_to_clipboard(_: &str) -> Result<(), String> {
+    Err("ytermusic was built without the `clipboard` feature".to_owned())
+}
+
+/**
+ * Draws a tick on the bottom border of the progress gauge at the start of each chapter
+ */
+struct ChapterTicks {
+    starts: Vec<u32>,
+    duration: f64,
+}
+
+impl Widget for ChapterTicks {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.width < 3 || area.height < 2 || self.duration <= 0.0 {
+            return;
+        }
+        let width = area.width - 2;
+        for start in self.starts.into_iter().filter(|x| *x != 0) {
+            let offset = (start as f64 / self.duration * width as f64) as u16;
+            if offset < width {
+                buf.get_mut(area.x + 1 + offset, area.y + area.height - 1)
+                    .set_symbol("┴");
+            }
+        }
+    }
+}
+
+/**
+ * Draws `A` and `B` on the bottom border of the progress gauge at the bounds of the A-B loop
+ */
+struct LoopMarkers {
+    start: f64,
+    end: Option<f64>,
+    duration: f64,
+}
+
+impl Widget for LoopMarkers {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.width < 3 || area.height < 2 || self.duration <= 0.0 {
+            return;
+        }
+        let width = area.width - 2;
+        let markers = [(self.start, "A")]
+            .into_iter()
+            .chain(self.end.map(|x| (x, "B")));
+        for (time, symbol) in markers {
+            let offset = (time / self.duration * width as f64) as u16;
+            buf.get_mut(area.x + 1 + offset.min(width - 1), area.y + area.height - 1)
+                .set_symbol(symbol);
+        }
+    }
+}
+
+impl PlayerState {
+    fn status(&self) -> AppStatus {
+        if self.sink.is_paused() {
+            AppStatus::Paused
+        } else if self.sink.is_finished() {
+            AppStatus::NoMusic
+        } else {
+            AppStatus::Playing
+        }
+    }
+    /**
+     * Handles the keys while a position in the queue is typed after `j`
+     */
+    fn on_jump_key_press(&mut self, key: KeyEvent) -> EventResponse {
+        let input = match &mut self.jump_input {
+            Some(e) => e,
+            None => return EventResponse::None,
+        };
+        match key.code {
+            KeyCode::Char(c) if c.is_ascii_digit() && input.len() < 6 => input.push(c),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Enter => {
+                let position = input.parse::<usize>().unwrap_or(0);
+                self.jump_input = None;
+                if position == 0 || position > self.queue.len() {
+                    return ManagerMessage::Toast(format!(
+                        "There are {} songs in the queue",
+                        self.queue.len()
+                    ))
+                    .event();
+                }
+                self.apply_sound_action(SoundAction::Next(position));
+            }
+            KeyCode::Esc => self.jump_input = None,
+            _ => (),
+        }
+        EventResponse::None
+    }
+    /**
+     * Starts playing with `Space` when no song is loaded, see `space_when_idle` in the config
+     */
+    fn on_idle_space(&mut self) -> EventResponse {
+        match CONFIG.space_when_idle {
+            IdleSpaceAction::Nothing => {
+                self.apply_sound_action(SoundAction::PlayPause);
+                EventResponse::None
+            }
+            IdleSpaceAction::PlayQueue => {
+                // The finished queue is played again, the next update starts it
+                if self.queue.is_empty() {
+                    self.queue.extend(self.previous.drain(..));
+                }
+                EventResponse::None
+            }
+            IdleSpaceAction::ResumeLast => match playlist::last_playlist() {
+                Some((_, videos)) => ManagerMessage::ReplaceQueue(videos).event(),
+                None => ManagerMessage::Toast("No playlist was played yet".to_owned()).event(),
+            },
+        }
+    }
+    /**
+     * Splits the top of the screen between the playlist and the volume panel if it is shown
+     */
+    fn top_layout(&self, top_rect: Rect) -> (Rect, Option<Rect>) {
+        if self.hide_volume {
+            (top_rect, None)
+        } else {
+            let [list_rect, volume_rect] = split_x(top_rect, 10);
+            (list_rect, Some(volume_rect))
+        }
+    }
+    /**
+     * The number of lines of the playlist
+     */
+    fn list_len(&self) -> usize {
+        download_lines()
+            + self.previous.len().min(3)
+            + usize::from(self.current.is_some())
+            + self.queue.len()
+    }
+    /**
+     * Seeks to a position of the current song, from 0 for its start to 1 for its end
+     */
+    fn seek_to_ratio(&mut self, ratio: f64) {
+        if self.current.is_none() || self.sink.is_finished() {
+            return;
+        }
+        if let Some(duration) = self.sink.duration() {
+            self.sink.seek_to(Duration::from_secs_f64(
+                duration.max(0.0) * ratio.clamp(0.0, 1.0),
+            ));
+        }
+    }
+    /**
+     * Returns the song shown on a line of the queue, `None` for the downloads
+     */
+    fn video_at_line(&self, line: usize) -> Option<&Video> {
+        match get_action(line, &self.queue, &self.previous, &self.current)? {
+            MusicStatusAction::Before(a) => self.previous.get(self.previous.len().checked_sub(a)?),
+            MusicStatusAction::Current => self.current.as_ref(),
+            MusicStatusAction::Skip(a) => self.queue.get(a - 1),
+            MusicStatusAction::Downloading => None,
+        }
+    }
+    /**
+     * Returns the line of the selected song, the current song comes before the queue and the
+     * previous songs when the song is found several times
+     */
+    fn selected_line(&self) -> Option<usize> {
+        let id = self.selected.as_ref()?;
+        let previous = &self.previous[self.previous.len().saturating_sub(3)..];
+        let current_line = download_lines() + previous.len();
+        if self.current.as_ref().map(|x| &x.video_id) == Some(id) {
+            return Some(current_line);
+        }
+        let queue_start = current_line + usize::from(self.current.is_some());
+        if let Some(index) = self.queue.iter().position(|x| &x.video_id == id) {
+            return Some(queue_start + index);
+        }
+        previous
+            .iter()
+            .position(|x| &x.video_id == id)
+            .map(|index| download_lines() + index)
+    }
+    /**
+     * Returns the index in the queue of the selected song, `None` if it isn't in the queue
+     */
+    fn selected_queue_index(&self) -> Option<usize> {
+        match get_action(
+            self.selected_line()?,
+            &self.queue,
+            &self.previous,
+            &self.current,
+        ) {
+            Some(MusicStatusAction::Skip(a)) if a <= self.queue.len() => Some(a - 1),
+            _ => None,
+        }
+    }
+    /**
+     * Moves the selection among the songs of the queue, starting from its first or last song
+     */
+    fn move_selection(&mut self, offset: isize) {
+        if self.queue.is_empty() {
+            self.selected = None;
+            return;
+        }
+        let index = match self.selected_queue_index() {
+            Some(index) => (index as isize + offset).clamp(0, self.queue.len() as isize - 1),
+            None if offset < 0 => self.queue.len() as isize - 1,
+            None => 0,
+        };
+        self.selected = Some(self.queue[index as usize].video_id.clone());
+        let line = self.list_len() - self.queue.len() + index as usize;
+        self.list_offset = self.list_offset.min(line);
+    }
+    /**
+     * Plays the song of a line of the queue, or toggles the playback on the current song
+     */
+    fn play_line(&mut self, line: usize) {
+        self.selected = None;
+        match get_action(line, &self.queue, &self.previous, &self.current) {
+            Some(MusicStatusAction::Skip(a)) => {
+                self.apply_sound_action(SoundAction::Next(a));
+            }
+            Some(MusicStatusAction::Current) => {
+                self.apply_sound_action(SoundAction::PlayPause);
+            }
+            Some(MusicStatusAction::Before(a)) => {
+                self.apply_sound_action(SoundAction::Previous(a));
+            }
+            None | Some(MusicStatusAction::Downloading) => (),
+        }
+    }
 }
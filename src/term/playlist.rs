@@ -1,7 +1,16 @@
-use std::sync::Arc;
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEventKind};
 use flume::Sender;
+use once_cell::sync::Lazy;
 use tui::{
     layout::Rect,
     style::{Color, Style},
@@ -10,66 +19,206 @@ use tui::{
 };
 use ytpapi::Video;
 
-use crate::{consts::CACHE_DIR, systems::download, SoundAction, DATABASE};
+use crate::{
+    config::CONFIG, consts::CACHE_DIR, database::read_json, errors::SendOrLog, settings::SETTINGS,
+    systems::logger::log_, SoundAction, DATABASE,
+};
 
-use super::{rect_contains, relative_pos, EventResponse, ManagerMessage, Screen, Screens};
+use super::{details, rect_contains, relative_pos, EventResponse, ManagerMessage, Screen, Screens};
 
 pub struct Chooser {
     pub selected: usize,
+    // Set once the user changed the selection, the saved selection isn't restored after that
+    pub moved: bool,
     pub items: Vec<PlayListEntry>,
+    // Only the playlists containing this text are listed, typed after pressing `/`
+    pub filter: Option<String>,
+    // The playlist whose songs are listed instead of the playlists
+    pub opened: Option<usize>,
+    pub track_selected: usize,
+    // Only the songs containing this text are listed, typed after pressing `/`
+    pub track_filter: Option<String>,
+    // Set after a first press on `Delete`, the second press removes the song
+    pub confirm_delete: bool,
+    // Number of files read by the scan of the downloads, while it runs
+    pub scanning: Option<usize>,
+    // The id of the song being played, highlighted in the songs of the playlist
+    pub playing: Option<String>,
+    // The ids of the songs in the downloads, read when the songs of a playlist are listed
+    pub cached: HashSet<String>,
+    // The songs of the opened playlist when it is kept on the disk
+    pub loaded: Vec<Video>,
     pub action_sender: Arc<Sender<SoundAction>>,
 }
 
 pub struct PlayListEntry {
     pub name: String,
+    // Empty when the playlist is kept on the disk
     pub videos: Vec<Video>,
+    // The file holding the songs of a playlist longer than `eager_playlist_len`
+    pub stored: Option<Stored>,
     pub local_videos: usize,
     pub text_to_show: String,
 }
 
 impl PlayListEntry {
-    pub fn new(name: String, videos: Vec<Video>) -> Self {
-        let db = DATABASE.read().unwrap();
-        let local_videos = videos
-            .iter()
-            .filter(|x| db.iter().any(|y| x.video_id == y.video_id))
-            .count();
-        Self {
-            text_to_show: format!(
-                "{}     ({}/{} {}%)",
-                name,
-                local_videos,
-                videos.len(),
-                (local_videos as f32 / videos.len() as f32 * 100.0) as u8
-            ),
+    pub fn new(name: String, mut videos: Vec<Video>) -> Self {
+        let local_videos = {
+            let db = DATABASE.read().unwrap();
+            let ids = db.iter().map(|x| &x.video_id).collect::<HashSet<_>>();
+            videos.iter().filter(|x| ids.contains(&x.video_id)).count()
+        };
+        let text_to_show = format!(
+            "{}     ({}/{} {}%)",
             name,
             local_videos,
+            videos.len(),
+            (local_videos as f32 / videos.len() as f32 * 100.0) as u8
+        );
+        let stored = if CONFIG.eager_playlist_len != 0 && videos.len() > CONFIG.eager_playlist_len {
+            Some(Stored::new(&name, std::mem::take(&mut videos)))
+        } else {
+            None
+        };
+        Self {
+            text_to_show,
+            name,
             videos,
+            stored,
+            local_videos,
+        }
+    }
+
+    /**
+     * Returns the songs of the playlist, read from the disk if it is kept there
+     */
+    pub fn load(&self) -> Vec<Video> {
+        let stored = match &self.stored {
+            Some(e) => e,
+            None => return self.videos.clone(),
+        };
+        // Still in memory while it is written, or if it couldn't be
+        if let Some(videos) = stored.pending.lock().unwrap().as_ref() {
+            return videos.clone();
+        }
+        read_json(&stored.path).unwrap_or_default()
+    }
+}
+
+// The directory of the playlists kept on the disk
+static STORE_DIRECTORY: Lazy<PathBuf> = Lazy::new(|| CACHE_DIR.join("chooser"));
+
+// Removes the files left by the previous runs, forced before the first file is written
+static STORE_CLEARED: Lazy<()> = Lazy::new(|| {
+    if let Err(e) = std::fs::remove_dir_all(&*STORE_DIRECTORY) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            log_(format!("Can't clear {}: {}", STORE_DIRECTORY.display(), e));
+        }
+    }
+});
+
+// Tells apart the files of the versions of a playlist sent more than once
+static STORE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/**
+ * The songs of a long playlist, kept on the disk so they are only in memory while it is used
+ */
+pub struct Stored {
+    path: PathBuf,
+    // The songs until a thread has written them to `path`
+    pending: Arc<Mutex<Option<Vec<Video>>>>,
+}
+
+impl Stored {
+    /**
+     * Writes the songs to the disk on another thread, so the UI doesn't wait for it
+     */
+    fn new(name: &str, videos: Vec<Video>) -> Self {
+        let mut hasher = DefaultHasher::new();
+        name.hash(&mut hasher);
+        let path = STORE_DIRECTORY.join(format!(
+            "{:016x}-{}.json",
+            hasher.finish(),
+            STORE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let pending = Arc::new(Mutex::new(Some(videos)));
+        {
+            let path = path.clone();
+            let pending = pending.clone();
+            let name = name.to_owned();
+            std::thread::spawn(move || {
+                let mut pending = pending.lock().unwrap();
+                // `None` if the playlist was replaced before being written
+                if let Some(videos) = pending.as_ref() {
+                    match store(&path, videos) {
+                        Ok(()) => *pending = None,
+                        Err(e) => log_(format!(
+                            "Can't keep the playlist {} on the disk: {}",
+                            name, e
+                        )),
+                    }
+                }
+            });
         }
+        Self { path, pending }
     }
+}
 
-    pub fn tupplelize(&self) -> (&String, &Vec<Video>) {
-        (&self.name, &self.videos)
+impl Drop for Stored {
+    fn drop(&mut self) {
+        // The thread doesn't write the songs anymore once they are taken
+        self.pending.lock().unwrap().take();
+        let _ = std::fs::remove_file(&self.path);
     }
 }
+
+/**
+ * Writes the songs of a long playlist to a file
+ */
+fn store(path: &Path, videos: &[Video]) -> Result<(), String> {
+    Lazy::force(&STORE_CLEARED);
+    std::fs::create_dir_all(&*STORE_DIRECTORY).map_err(|e| e.to_string())?;
+    let content = serde_json::to_string(videos).map_err(|e| e.to_string())?;
+    std::fs::write(path, content).map_err(|e| e.to_string())
+}
 impl Screen for Chooser {
     fn on_mouse_press(
         &mut self,
         mouse_event: crossterm::event::MouseEvent,
         frame_data: &Rect,
     ) -> EventResponse {
+        if let MouseEventKind::ScrollUp | MouseEventKind::ScrollDown = mouse_event.kind {
+            if rect_contains(frame_data, mouse_event.column, mouse_event.row, 0) {
+                let step = CONFIG.scroll_sensitivity as isize;
+                self.scroll(if mouse_event.kind == MouseEventKind::ScrollUp {
+                    -step
+                } else {
+                    step
+                });
+            }
+        }
         if let MouseEventKind::Down(_) = mouse_event.kind {
             let x = mouse_event.column;
             let y = mouse_event.row;
             if rect_contains(frame_data, x, y, 1) {
                 let (_, y) = relative_pos(frame_data, x, y, 1);
-                let y = if self.selected == 0 {
+                let selected = self.track_selected_or_selected();
+                let y = if selected == 0 {
                     y
                 } else {
-                    y + self.selected as u16 - 1
+                    y + selected as u16 - 1
                 };
-                if self.items.len() > y as usize {
-                    self.selected = y as usize;
+                if let Some(opened) = self.opened {
+                    if self.visible_tracks(opened).len() > y as usize {
+                        self.track_selected = y as usize;
+                        return self.on_key_press(
+                            KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE),
+                            frame_data,
+                        );
+                    }
+                } else if let Some(index) = self.visible_items().get(y as usize) {
+                    self.selected = *index;
+                    self.remember_selection();
                     return self.on_key_press(
                         KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE),
                         frame_data,
@@ -81,44 +230,97 @@ impl Screen for Chooser {
     }
 
     fn on_key_press(&mut self, key: KeyEvent, _: &Rect) -> EventResponse {
+        if let Some(opened) = self.opened {
+            return self.on_track_key_press(key, opened);
+        }
+        if let Some(filter) = &mut self.filter {
+            match key.code {
+                KeyCode::Char(c) => {
+                    filter.push(c);
+                    self.snap_selection();
+                    return EventResponse::None;
+                }
+                KeyCode::Backspace => {
+                    filter.pop();
+                    self.snap_selection();
+                    return EventResponse::None;
+                }
+                KeyCode::Esc => {
+                    self.filter = None;
+                    return EventResponse::None;
+                }
+                _ => {}
+            }
+        }
+        // No playlist is selected when the filter matches none of them
+        let visible = self.visible_items().contains(&self.selected);
         match key.code {
             KeyCode::Esc => return ManagerMessage::ChangeState(Screens::MusicPlayer).event(),
             KeyCode::Char('f') => return ManagerMessage::ChangeState(Screens::Search).event(),
+            KeyCode::Char('h') => return ManagerMessage::ChangeState(Screens::History).event(),
+            KeyCode::Char('w') => return ManagerMessage::ChangeState(Screens::Downloads).event(),
+            KeyCode::Char('l') => return ManagerMessage::ChangeState(Screens::Library).event(),
+            KeyCode::Char('r') => return ManagerMessage::Refresh.event(),
+            KeyCode::Char('/') => {
+                self.filter = Some(String::new());
+            }
+            KeyCode::Enter if !visible => {}
             KeyCode::Enter => {
-                if let Some(a) = &self.items.get(self.selected) {
-                    if a.name != "Local musics" {
-                        std::fs::write(
-                            CACHE_DIR.join("last-playlist.json"),
-                            serde_json::to_string(&a.tupplelize()).unwrap(),
-                        )
-                        .unwrap();
-                    }
-                    self.action_sender.send(SoundAction::Cleanup).unwrap();
-                    download::clean(self.action_sender.clone());
-                    for video in self.items.get(self.selected).unwrap().videos.iter() {
-                        download::add(video.clone(), &self.action_sender);
-                    }
+                if let Some(videos) = self.play_selected() {
+                    return ManagerMessage::ReplaceQueue(videos).event();
                 }
                 return EventResponse::Message(vec![ManagerMessage::ChangeState(
                     Screens::MusicPlayer,
                 )]);
             }
-            KeyCode::Char('+') | KeyCode::Up => self.selected(self.selected as isize - 1),
-            KeyCode::Char('-') | KeyCode::Down => self.selected(self.selected as isize + 1),
+            // Plays the playlist but stays here to choose the next one
+            KeyCode::Char('p') if visible => {
+                if let Some(videos) = self.play_selected() {
+                    return ManagerMessage::ReplaceQueueInBackground(videos).event();
+                }
+            }
+            KeyCode::Char('*') if visible => {
+                return self
+                    .toggle_pin()
+                    .map_or(EventResponse::None, |x| ManagerMessage::Toast(x).event());
+            }
+            KeyCode::Right if visible => {
+                self.opened = Some(self.selected);
+                self.track_selected = 0;
+                self.track_filter = None;
+                self.cached = cached_ids();
+                self.loaded = self.load_stored(self.selected);
+            }
+            KeyCode::Char('+') | KeyCode::Up => self.move_selection(-1),
+            KeyCode::Char('-') | KeyCode::Down => self.move_selection(1),
             _ => {}
         }
         EventResponse::None
     }
 
     fn render(&mut self, frame: &mut Frame<tui::backend::CrosstermBackend<std::io::Stdout>>) {
+        if let Some(opened) = self.opened {
+            self.render_tracks(frame, opened);
+            return;
+        }
+        let visible = self.visible_items();
+        let position = visible
+            .iter()
+            .position(|x| *x == self.selected)
+            .unwrap_or(0);
         frame.render_stateful_widget(
             List::new(
-                self.items
-                    .iter()
-                    .enumerate()
-                    .skip(self.selected.saturating_sub(1))
-                    .map(|(index, i)| {
-                        ListItem::new(i.text_to_show.as_str()).style(
+                visible
+                    .into_iter()
+                    .skip(position.saturating_sub(1))
+                    .map(|index| {
+                        let i = &self.items[index];
+                        let pin = match (is_pinned(&i.name), super::ascii_glyphs()) {
+                            (false, _) => "",
+                            (true, false) => "★ ",
+                            (true, true) => "* ",
+                        };
+                        ListItem::new(format!("{}{}", pin, i.text_to_show)).style(
                             Style::default()
                                 .fg(if index == self.selected {
                                     Color::Black
@@ -134,19 +336,29 @@ impl Screen for Chooser {
                     })
                     .collect::<Vec<_>>(),
             )
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .title(" Select the playlist to play "),
-            ),
+            .block(Block::default().borders(Borders::ALL).title(
+                if let Some(filter) = &self.filter {
+                    format!(" Playlists matching: {}_ (Esc to clear) ", filter)
+                } else if let Some(files) = self.scanning {
+                    format!(
+                        " Select the playlist to play (Scanning library: {} files…) ",
+                        files
+                    )
+                } else {
+                    " Select the playlist to play ".to_owned()
+                },
+            )),
             frame.size(),
             &mut ListState::default(),
         );
     }
 
     fn handle_global_message(&mut self, message: super::ManagerMessage) -> EventResponse {
-        if let ManagerMessage::AddElementToChooser(a) = message {
-            self.add_element(a);
+        match message {
+            ManagerMessage::AddElementToChooser(a) => self.add_element(a),
+            ManagerMessage::ScanProgress(progress) => self.scanning = progress,
+            ManagerMessage::NowPlaying(playing) => self.playing = playing,
+            _ => {}
         }
         EventResponse::None
     }
@@ -156,20 +368,429 @@ impl Screen for Chooser {
     }
 
     fn open(&mut self) -> EventResponse {
+        // Songs may have been downloaded while the playlist was left open
+        if self.opened.is_some() {
+            self.cached = cached_ids();
+        }
         EventResponse::None
     }
+
+    fn refresh(&mut self) -> EventResponse {
+        if let Some(opened) = self.opened {
+            self.cached = cached_ids();
+            self.loaded = self.load_stored(opened);
+        }
+        ManagerMessage::Refresh.event()
+    }
+
+    fn footer_hints(&self) -> &'static str {
+        if self.opened.is_some() {
+            "enter play · / filter · i details · del remove · esc back"
+        } else {
+            "enter play · p play here · * pin · → songs · / filter · f search · l library · h history · r refresh · ? help"
+        }
+    }
 }
 impl Chooser {
-    fn selected(&mut self, selected: isize) {
-        if selected < 0 {
-            self.selected = self.items.len() - 1;
-        } else if selected >= self.items.len() as isize {
-            self.selected = 0;
+    /**
+     * Returns the songs of the selected playlist, it is saved as the last played playlist
+     */
+    fn play_selected(&self) -> Option<Vec<Video>> {
+        let a = self.items.get(self.selected)?;
+        let videos = a.load();
+        if a.name != "Local musics" {
+            // The playlist still plays, it just isn't shown on the next start
+            let result = serde_json::to_string(&(&a.name, &videos))
+                .map_err(std::io::Error::from)
+                .and_then(|x| std::fs::write(CACHE_DIR.join("last-playlist.json"), x));
+            if let Err(e) = result {
+                log_(format!("Can't save the last playlist {}: {}", a.name, e));
+            }
+        }
+        Some(videos)
+    }
+    /**
+     * Reads the songs of a playlist kept on the disk, nothing is read for the other playlists
+     */
+    fn load_stored(&self, index: usize) -> Vec<Video> {
+        match self.items.get(index) {
+            Some(entry) if entry.stored.is_some() => entry.load(),
+            _ => Vec::new(),
+        }
+    }
+    /**
+     * The songs of a playlist, the ones read from the disk if it is the opened one
+     */
+    fn tracks(&self, index: usize) -> &[Video] {
+        let entry = &self.items[index];
+        if entry.stored.is_some() && self.opened == Some(index) {
+            &self.loaded
         } else {
-            self.selected = selected as usize;
+            &entry.videos
+        }
+    }
+    /**
+     * The indexes of the playlists listed, all of them unless a filter is typed
+     */
+    fn visible_items(&self) -> Vec<usize> {
+        let filter = self
+            .filter
+            .as_ref()
+            .map(|x| x.to_lowercase())
+            .unwrap_or_default();
+        self.items
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| x.name.to_lowercase().contains(&filter))
+            .map(|(index, _)| index)
+            .collect()
+    }
+    /**
+     * Moves the selection among the listed playlists, going around at both ends
+     */
+    fn move_selection(&mut self, offset: isize) {
+        let visible = self.visible_items();
+        if visible.is_empty() {
+            return;
         }
+        let position = visible
+            .iter()
+            .position(|x| *x == self.selected)
+            .unwrap_or(0) as isize;
+        self.selected = visible[(position + offset).rem_euclid(visible.len() as isize) as usize];
+        self.remember_selection();
+    }
+    /**
+     * Moves the selection of the listed playlists or songs with the mouse wheel, stopping at both
+     * ends
+     */
+    fn scroll(&mut self, offset: isize) {
+        if let Some(opened) = self.opened {
+            let last = self.visible_tracks(opened).len().saturating_sub(1);
+            self.track_selected =
+                (self.track_selected as isize + offset).clamp(0, last as isize) as usize;
+            return;
+        }
+        let visible = self.visible_items();
+        if visible.is_empty() {
+            return;
+        }
+        let position = visible
+            .iter()
+            .position(|x| *x == self.selected)
+            .unwrap_or(0) as isize;
+        self.selected = visible[(position + offset).clamp(0, visible.len() as isize - 1) as usize];
+        self.remember_selection();
+    }
+    /**
+     * Selects the first listed playlist when the filter hid the selected one
+     */
+    fn snap_selection(&mut self) {
+        let visible = self.visible_items();
+        if !visible.contains(&self.selected) {
+            if let Some(first) = visible.first() {
+                self.selected = *first;
+                self.remember_selection();
+            }
+        }
+    }
+    /**
+     * Saves the name of the selected playlist so it is selected again on the next start
+     */
+    fn remember_selection(&mut self) {
+        self.moved = true;
+        if let Some(e) = self.items.get(self.selected) {
+            SETTINGS.write().unwrap().selected_playlist = Some(e.name.clone());
+            crate::settings::save();
+        }
+    }
+    /**
+     * Number of pinned playlists, they are all at the start of the list
+     */
+    fn pinned_count(&self) -> usize {
+        self.items.iter().take_while(|x| is_pinned(&x.name)).count()
+    }
+    /**
+     * Pins the selected playlist to the top of the list or unpins it, returns the message shown
+     */
+    fn toggle_pin(&mut self) -> Option<String> {
+        let name = self.items.get(self.selected)?.name.clone();
+        let pinned = {
+            let pins = &mut SETTINGS.write().unwrap().pinned_playlists;
+            if let Some(index) = pins.iter().position(|x| x == &name) {
+                pins.remove(index);
+                false
+            } else {
+                pins.push(name.clone());
+                true
+            }
+        };
+        crate::settings::save();
+        // The playlist goes right after the other pinned ones, or right after them once unpinned
+        let entry = self.items.remove(self.selected);
+        self.selected = self.pinned_count();
+        self.items.insert(self.selected, entry);
+        Some(if pinned {
+            format!("Pinned {}", name)
+        } else {
+            format!("Unpinned {}", name)
+        })
+    }
+    fn track_selected_or_selected(&self) -> usize {
+        if self.opened.is_some() {
+            self.track_selected
+        } else {
+            self.selected
+        }
+    }
+    /**
+     * The songs of the opened playlist matching the filter, with their index in the playlist
+     */
+    fn visible_tracks(&self, opened: usize) -> Vec<(usize, &Video)> {
+        let filter = self
+            .track_filter
+            .as_ref()
+            .map(|x| x.to_lowercase())
+            .unwrap_or_default();
+        self.tracks(opened)
+            .iter()
+            .enumerate()
+            .filter(|(_, video)| {
+                format!("{} {}", video.author, video.title)
+                    .to_lowercase()
+                    .contains(&filter)
+            })
+            .collect()
+    }
+    /**
+     * Handles the keys when the songs of a playlist are listed
+     */
+    fn on_track_key_press(&mut self, key: KeyEvent, opened: usize) -> EventResponse {
+        if key.code != KeyCode::Delete {
+            self.confirm_delete = false;
+        }
+        // While typing a filter the characters are added to it
+        if let Some(filter) = &mut self.track_filter {
+            match key.code {
+                KeyCode::Char(c) => {
+                    filter.push(c);
+                    self.track_selected = 0;
+                    return EventResponse::None;
+                }
+                KeyCode::Backspace => {
+                    filter.pop();
+                    self.track_selected = 0;
+                    return EventResponse::None;
+                }
+                KeyCode::Esc => {
+                    self.track_filter = None;
+                    self.track_selected = 0;
+                    return EventResponse::None;
+                }
+                _ => {}
+            }
+        }
+        let tracks = self.visible_tracks(opened);
+        let selected = tracks
+            .get(self.track_selected)
+            .map(|(i, x)| (*i, (*x).clone()));
+        let count = tracks.len();
+        match key.code {
+            KeyCode::Esc | KeyCode::Left => {
+                self.opened = None;
+                self.loaded = Vec::new();
+            }
+            KeyCode::Char('/') => {
+                self.track_filter = Some(String::new());
+                self.track_selected = 0;
+            }
+            KeyCode::Enter => {
+                if let Some((index, _)) = selected {
+                    return ManagerMessage::ReplaceQueue(self.tracks(opened)[index..].to_vec())
+                        .event();
+                }
+            }
+            KeyCode::Char('i') => {
+                if let Some((_, video)) = selected {
+                    return ManagerMessage::ShowDetails(video).event();
+                }
+            }
+            KeyCode::Char('o') => {
+                if let Some((_, video)) = selected {
+                    return ManagerMessage::Toast(details::reveal(&video)).event();
+                }
+            }
+            KeyCode::Delete => {
+                if let Some((_, video)) = selected {
+                    if self.confirm_delete {
+                        self.confirm_delete = false;
+                        self.action_sender
+                            .send_or_log(SoundAction::RemoveFromCache(video));
+                    } else {
+                        self.confirm_delete = true;
+                        return ManagerMessage::Toast(format!(
+                            "Press Delete again to remove {} from the cache",
+                            video.title
+                        ))
+                        .event();
+                    }
+                }
+            }
+            KeyCode::Char('+') | KeyCode::Up => {
+                self.track_selected = if self.track_selected == 0 {
+                    count.saturating_sub(1)
+                } else {
+                    self.track_selected - 1
+                };
+            }
+            KeyCode::Char('-') | KeyCode::Down => {
+                self.track_selected = if self.track_selected + 1 >= count {
+                    0
+                } else {
+                    self.track_selected + 1
+                };
+            }
+            _ => {}
+        }
+        EventResponse::None
+    }
+    fn render_tracks(
+        &self,
+        frame: &mut Frame<tui::backend::CrosstermBackend<std::io::Stdout>>,
+        opened: usize,
+    ) {
+        let entry = &self.items[opened];
+        let title = match &self.track_filter {
+            Some(filter) => format!(" {} | Filter: {}_ ", entry.name, filter),
+            None => format!(" {} | Press / to filter ", entry.name),
+        };
+        frame.render_stateful_widget(
+            List::new(
+                self.visible_tracks(opened)
+                    .into_iter()
+                    .enumerate()
+                    .skip(self.track_selected.saturating_sub(1))
+                    // Only the visible songs are drawn, the playlist may be very long
+                    .take(frame.size().height as usize)
+                    .map(|(index, (_, video))| {
+                        let local = self.cached.contains(&video.video_id);
+                        let stats = crate::stats::get(&video.video_id);
+                        let stats = if stats.skips + stats.completions == 0 {
+                            String::new()
+                        } else {
+                            format!("  ({} played, {} skipped)", stats.completions, stats.skips)
+                        };
+                        let playing = self.playing.as_ref() == Some(&video.video_id);
+                        let marker = match (playing, super::ascii_glyphs()) {
+                            (true, false) => "▶ ",
+                            (true, true) => "> ",
+                            (false, _) if local => "",
+                            (false, _) => match &CONFIG.missing_marker {
+                                Some(marker) => marker.as_str(),
+                                None if super::ascii_glyphs() => "v ",
+                                None => "↓ ",
+                            },
+                        };
+                        ListItem::new(format!(
+                            "{}{} | {}{}",
+                            marker, video.author, video.title, stats
+                        ))
+                        .style(
+                            Style::default()
+                                .fg(if index == self.track_selected {
+                                    Color::Black
+                                } else if playing {
+                                    CONFIG.playing_color.0
+                                } else if local {
+                                    Color::White
+                                } else {
+                                    CONFIG.missing_color.0
+                                })
+                                .bg(if index != self.track_selected {
+                                    Color::Black
+                                } else {
+                                    Color::White
+                                }),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .block(Block::default().borders(Borders::ALL).title(title)),
+            frame.size(),
+            &mut ListState::default(),
+        );
     }
     fn add_element(&mut self, element: (String, Vec<Video>)) {
-        self.items.push(PlayListEntry::new(element.0, element.1));
+        let entry = PlayListEntry::new(element.0, element.1);
+        // A playlist sent again replaces the previous version
+        if let Some((index, e)) = self
+            .items
+            .iter_mut()
+            .enumerate()
+            .find(|(_, x)| x.name == entry.name)
+        {
+            *e = entry;
+            if self.opened == Some(index) {
+                self.loaded = self.load_stored(index);
+                self.track_selected = self
+                    .track_selected
+                    .min(self.visible_tracks(index).len().saturating_sub(1));
+            }
+        } else {
+            let index = if is_pinned(&entry.name) {
+                self.pinned_count()
+            } else {
+                self.items.len()
+            };
+            // The selected and the opened playlists stay the same
+            if index <= self.selected && index < self.items.len() {
+                self.selected += 1;
+            }
+            if let Some(opened) = &mut self.opened {
+                if index <= *opened {
+                    *opened += 1;
+                }
+            }
+            // Playlists arrive in any order so the saved selection is matched by name
+            if !self.moved
+                && SETTINGS.read().unwrap().selected_playlist.as_ref() == Some(&entry.name)
+            {
+                self.selected = index;
+            }
+            self.items.insert(index, entry);
+        }
     }
 }
+
+/**
+ * The last playlist chosen in the playlist selector, with its name
+ */
+pub fn last_playlist() -> Option<(String, Vec<Video>)> {
+    read_json(&CACHE_DIR.join("last-playlist.json"))
+}
+
+fn is_pinned(name: &str) -> bool {
+    SETTINGS
+        .read()
+        .unwrap()
+        .pinned_playlists
+        .iter()
+        .any(|x| x == name)
+}
+
+/**
+ * Reads the ids of the songs in the downloads, the json is written once the song is complete
+ */
+fn cached_ids() -> HashSet<String> {
+    std::fs::read_dir(CACHE_DIR.join("downloads"))
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| {
+                    let name = entry.file_name();
+                    name.to_str()?.strip_suffix(".json").map(str::to_owned)
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
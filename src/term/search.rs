@@ -1,42 +1,80 @@
 use std::{
-    path::PathBuf,
-    str::FromStr,
-    sync::{Arc, RwLock},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, Instant},
 };
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEventKind};
 use flume::Sender;
+use once_cell::sync::Lazy;
 use tokio::task::JoinHandle;
 use tui::{
     layout::{Alignment, Rect},
-    style::{Color, Style},
-    widgets::{Block, BorderType, Borders, List, ListItem, ListState, Paragraph},
+    style::{Color, Modifier, Style},
+    widgets::{Block, BorderType, Borders, List, ListItem, ListState, Paragraph, Wrap},
     Frame,
 };
-use urlencoding::encode;
-use ytpapi::{Video, YTApi};
+use ytpapi::{Playlist, SearchContinuation, SearchType, Video, YTApi};
 
 use crate::{
-    systems::{download::start_task_unary, logger::log_},
+    api,
+    config::{EmptySearchAction, EscAction, CONFIG},
+    database::history,
+    errors::SendOrLog,
+    links,
+    systems::{
+        download::{self, start_task_unary},
+        logger::log_,
+        player,
+    },
     SoundAction, DATABASE,
 };
 
 use super::{
-    rect_contains, relative_pos, split_y_start, EventResponse, ManagerMessage, Screen, Screens,
+    keymap::{Action, KEYMAP},
+    rect_contains, relative_pos, split_y, split_y_start, EventResponse, ManagerMessage, Screen,
+    Screens,
 };
 
+// The keys of the search as they are bound, shown in the footer
+static HINTS: Lazy<String> = Lazy::new(|| {
+    format!(
+        "enter play · ctrl+enter play next · {} · esc back",
+        KEYMAP.hints(&[
+            (&[Action::AddToQueue], "add to queue"),
+            (&[Action::CycleSearchType], "type"),
+            (&[Action::StartStation], "station"),
+        ])
+    )
+});
+
 pub struct Search {
     pub text: String,
     pub selected: usize,
     pub items: Arc<RwLock<Vec<(String, Video, Status)>>>,
     pub search_handle: Option<JoinHandle<()>>,
+    pub continuation: Arc<RwLock<Option<SearchContinuation>>>,
+    pub loading_more: Arc<AtomicBool>,
+    // Number of results found, more than the length of `items` if `max_search_results` cut them
+    pub found: Arc<AtomicUsize>,
+    // Why the last search failed
+    pub error: Arc<RwLock<Option<String>>>,
     pub api: Option<Arc<ytpapi::YTApi>>,
+    // The last text submitted with `Enter` or left by changing screen
+    pub last_query: Option<String>,
+    // The kind of results searched, `search_type` on startup
+    pub search_type: SearchType,
     pub action_sender: Arc<Sender<SoundAction>>,
 }
 #[derive(Clone, Debug, PartialEq)]
 pub enum Status {
     Local,
     Unknown,
+    // An album, a playlist or an artist, its id is the `video_id`
+    Collection,
 }
 impl Screen for Search {
     fn on_mouse_press(
@@ -44,10 +82,22 @@ impl Screen for Search {
         mouse_event: crossterm::event::MouseEvent,
         frame_data: &Rect,
     ) -> EventResponse {
+        let splitted = self.layout(*frame_data);
+        let x = mouse_event.column;
+        let y = mouse_event.row;
+        if let MouseEventKind::ScrollUp | MouseEventKind::ScrollDown = mouse_event.kind {
+            if rect_contains(&splitted[1], x, y, 0) {
+                let step = CONFIG.scroll_sensitivity as isize;
+                if mouse_event.kind == MouseEventKind::ScrollUp {
+                    self.selected((self.selected as isize - step).max(0));
+                } else {
+                    let last = self.items.read().unwrap().len().saturating_sub(1);
+                    self.selected((self.selected as isize + step).min(last as isize));
+                    self.load_more();
+                }
+            }
+        }
         if let MouseEventKind::Down(_) = mouse_event.kind {
-            let splitted = split_y_start(*frame_data, 3);
-            let x = mouse_event.column;
-            let y = mouse_event.row;
             if rect_contains(&splitted[1], x, y, 1) {
                 let (_, y) = relative_pos(&splitted[1], x, y, 1);
                 let y = if self.selected == 0 {
@@ -69,12 +119,36 @@ impl Screen for Search {
 
     fn on_key_press(&mut self, key: KeyEvent, _: &Rect) -> EventResponse {
         if KeyCode::Esc == key.code {
-            return ManagerMessage::ChangeState(Screens::Playlist).event();
+            match CONFIG.esc_search {
+                EscAction::Nothing => return EventResponse::None,
+                // The results are updated like when the text is erased
+                EscAction::Clear if !self.text.is_empty() => {
+                    self.text.clear();
+                    self.search();
+                    return EventResponse::None;
+                }
+                _ => return ManagerMessage::ChangeState(Screens::Playlist).event(),
+            }
         }
         let textbefore = self.text.trim().to_owned();
         match key.code {
+            KeyCode::Enter if textbefore.is_empty() => return self.on_empty_enter(),
             KeyCode::Enter => {
-                if let Some(a) = self.items.read().unwrap().get(self.selected).cloned() {
+                self.last_query = Some(textbefore.clone());
+                let selected = self.items.read().unwrap().get(self.selected).cloned();
+                if let Some((_, collection, Status::Collection)) = selected {
+                    let stay = key.modifiers.contains(KeyModifiers::CONTROL);
+                    return self.open_collection(collection, stay);
+                }
+                if let Some(a) = selected {
+                    // The time of a link is only used for the song of the link
+                    if links::is_link(&self.text)
+                        && links::video_id(&self.text).as_ref() == Some(&a.1.video_id)
+                    {
+                        if let Some(offset) = links::start_offset(&self.text) {
+                            player::set_start_offset(&a.1.video_id, Duration::from_secs(offset));
+                        }
+                    }
                     start_task_unary(self.action_sender.clone(), a.1);
                     return if key.modifiers.contains(KeyModifiers::CONTROL) {
                         EventResponse::None
@@ -83,8 +157,44 @@ impl Screen for Search {
                     };
                 }
             }
+            _ if KEYMAP.matches(Action::CycleSearchType, &key) => {
+                self.search_type = self.search_type.next();
+                if !textbefore.is_empty() {
+                    self.search();
+                }
+                return EventResponse::None;
+            }
+            // Unlike `Enter`, the song is always played after the whole queue
+            _ if KEYMAP.matches(Action::AddToQueue, &key) => {
+                let selected = self.items.read().unwrap().get(self.selected).cloned();
+                match selected {
+                    Some((_, collection, Status::Collection)) => {
+                        return self.open_collection(collection, true);
+                    }
+                    Some((_, video, _)) => {
+                        let title = video.title.clone();
+                        download::add(video, &self.action_sender);
+                        return ManagerMessage::Toast(format!("Added to queue: {}", title)).event();
+                    }
+                    None => return EventResponse::None,
+                }
+            }
+            _ if KEYMAP.matches(Action::StartStation, &key) => {
+                let query = self.text.trim();
+                if query.is_empty() {
+                    self.action_sender.send_or_log(SoundAction::Station(None));
+                    return ManagerMessage::Toast("Station stopped".to_owned()).event();
+                }
+                download::replace_queue(&[], self.action_sender.clone());
+                self.action_sender
+                    .send_or_log(SoundAction::Station(Some(query.to_owned())));
+                return ManagerMessage::ChangeState(Screens::MusicPlayer).event();
+            }
             KeyCode::Char('+') | KeyCode::Up => self.selected(self.selected as isize - 1),
-            KeyCode::Char('-') | KeyCode::Down => self.selected(self.selected as isize + 1),
+            KeyCode::Char('-') | KeyCode::Down => {
+                self.selected(self.selected as isize + 1);
+                self.load_more();
+            }
             KeyCode::Delete | KeyCode::Backspace => {
                 self.text.pop();
             }
@@ -96,74 +206,12 @@ impl Screen for Search {
         if textbefore == self.text.trim() {
             return EventResponse::None;
         }
-
-        if let Some(handle) = self.search_handle.take() {
-            handle.abort();
-        }
-
-        let text = self.text.to_lowercase();
-
-        let local = DATABASE
-            .read()
-            .unwrap()
-            .iter()
-            .filter(|x| {
-                x.title.to_lowercase().contains(&text) || x.author.to_lowercase().contains(&text)
-            })
-            .cloned()
-            .map(|video| {
-                (
-                    format!("{} | {}", video.author, video.title),
-                    video,
-                    Status::Local,
-                )
-            })
-            .collect::<Vec<_>>();
-        self.items.write().unwrap().clear();
-        self.items
-            .write()
-            .unwrap()
-            .extend(local.clone().into_iter());
-
-        if let Some(api) = self.api.clone() {
-            let text = self.text.clone();
-            let items = self.items.clone();
-            self.selected = 0;
-            self.search_handle = Some(tokio::task::spawn(async move {
-                let mut item = Vec::new();
-                // HANDLE ERRORS
-                match api.search(&encode(&text).replace("%20", "+")).await {
-                    Ok(e) => {
-                        for video in e.into_iter() {
-                            let id = video.video_id.clone();
-                            item.push((
-                                format!("{} | {}", video.author, video.title),
-                                video,
-                                if DATABASE.read().unwrap().iter().any(|x| x.video_id == id) {
-                                    Status::Local
-                                } else {
-                                    Status::Unknown
-                                },
-                            ));
-                        }
-                    }
-                    Err(e) => {
-                        log_(format!("{:?}", e));
-                    }
-                }
-                items.write().unwrap().clear();
-                items.write().unwrap().extend(local.into_iter());
-                items.write().unwrap().extend(item.into_iter());
-            }));
-        } else {
-            self.set_elements(local);
-        }
-
+        self.search();
         EventResponse::None
     }
 
     fn render(&mut self, frame: &mut Frame<tui::backend::CrosstermBackend<std::io::Stdout>>) {
-        let splitted = split_y_start(frame.size(), 3);
+        let splitted = self.layout(frame.size());
         frame.render_widget(
             Paragraph::new(self.text.clone())
                 .style(Style::default().fg(Color::LightCyan))
@@ -172,11 +220,20 @@ impl Screen for Search {
                     Block::default()
                         .borders(Borders::ALL)
                         .style(Style::default().fg(Color::White))
-                        .title(" Search ")
+                        .title(match self.search_type {
+                            SearchType::All => " Search ",
+                            SearchType::Songs => " Search (songs) ",
+                            SearchType::Videos => " Search (videos) ",
+                            SearchType::Albums => " Search (albums) ",
+                            SearchType::Playlists => " Search (playlists) ",
+                            SearchType::Artists => " Search (artists) ",
+                        })
                         .border_type(BorderType::Plain),
                 ),
             splitted[0],
         );
+        // Inner width of the list, without the borders
+        let width = splitted[1].width.saturating_sub(2) as usize;
         frame.render_stateful_widget(
             List::new(
                 self.items
@@ -186,60 +243,217 @@ impl Screen for Search {
                     .enumerate()
                     .skip(self.selected.saturating_sub(1))
                     .map(|(index, i)| {
-                        ListItem::new(i.0.as_str()).style(
+                        let colors = &CONFIG.search_colors;
+                        let style = if index == self.selected {
+                            // Bold so the row stands out even if the colors are close
                             Style::default()
-                                .fg(if index == self.selected {
-                                    Color::Black
-                                } else if i.2 == Status::Local {
-                                    Color::White
-                                } else {
-                                    Color::LightBlue
-                                })
-                                .bg(if index != self.selected {
-                                    Color::Black
-                                } else {
-                                    Color::White
-                                }),
-                        )
+                                .fg(colors.selected_fg.0)
+                                .bg(colors.selected_bg.0)
+                                .add_modifier(Modifier::BOLD)
+                        } else if i.2 == Status::Local {
+                            Style::default().fg(colors.local_fg.0).bg(colors.bg.0)
+                        } else {
+                            Style::default().fg(colors.remote_fg.0).bg(colors.bg.0)
+                        };
+                        ListItem::new(search_row(i, width)).style(style)
                     })
+                    .chain(
+                        self.loading_more
+                            .load(Ordering::SeqCst)
+                            .then(|| ListItem::new("Loading more…")),
+                    )
                     .collect::<Vec<_>>(),
             )
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .title(" Select the playlist to play "),
-            ),
+            .block(Block::default().borders(Borders::ALL).title({
+                let shown = self.items.read().unwrap().len();
+                let found = self.found.load(Ordering::SeqCst);
+                let count = if found > shown {
+                    format!(" (showing {} of {})", shown, found)
+                } else {
+                    String::new()
+                };
+                match self.error.read().unwrap().as_ref() {
+                    Some(e) => format!(" Select the playlist to play{} | {} ", count, e),
+                    None => format!(" Select the playlist to play{} ", count),
+                }
+            })),
             splitted[1],
             &mut ListState::default(),
         );
+        if splitted[2].height > 0 {
+            if let Some((_, video, _)) = self.items.read().unwrap().get(self.selected) {
+                let duration = if video.duration.is_empty() {
+                    String::new()
+                } else {
+                    format!(" ({})", video.duration)
+                };
+                frame.render_widget(
+                    Paragraph::new(format!("{} | {}{}", video.author, video.title, duration))
+                        .style(Style::default().fg(Color::White))
+                        .wrap(Wrap { trim: true })
+                        .block(Block::default().borders(Borders::ALL).title(" Selected ")),
+                    splitted[2],
+                );
+            }
+        }
     }
 
-    fn handle_global_message(&mut self, _: super::ManagerMessage) -> EventResponse {
-        EventResponse::None
+    fn handle_global_message(&mut self, message: super::ManagerMessage) -> EventResponse {
+        match message {
+            ManagerMessage::ShowRelated(video) => {
+                // The results don't come from the text anymore
+                self.text.clear();
+                self.related(video)
+            }
+            _ => EventResponse::None,
+        }
     }
 
     fn close(&mut self, _: Screens) -> EventResponse {
+        if !self.text.trim().is_empty() {
+            self.last_query = Some(self.text.trim().to_owned());
+        }
         EventResponse::None
     }
 
     fn open(&mut self) -> EventResponse {
+        // The results of the last search may have changed since
+        if CONFIG.search_refresh_on_open && !self.text.trim().is_empty() {
+            self.search();
+        }
+        EventResponse::None
+    }
+
+    fn refresh(&mut self) -> EventResponse {
+        if !self.text.trim().is_empty() {
+            self.search();
+        }
         EventResponse::None
     }
+
+    fn footer_hints(&self) -> &'static str {
+        &HINTS
+    }
 }
 impl Search {
+    /**
+     * Splits the screen into the search bar, the results and the full name of the selected
+     * result. The last one is only shown if the screen is tall enough, its height is 0 otherwise.
+     */
+    fn layout(&self, area: Rect) -> [Rect; 3] {
+        let [search, list] = split_y_start(area, 3);
+        if list.height < 12 || self.items.read().unwrap().is_empty() {
+            let mut detail = list;
+            detail.y += list.height;
+            detail.height = 0;
+            return [search, list, detail];
+        }
+        let [list, detail] = split_y(list, 4);
+        [search, list, detail]
+    }
+
     pub async fn new(action_sender: Arc<Sender<SoundAction>>) -> Self {
+        let api = crate::setup::connect(Path::new("headers.txt"))
+            .await
+            .ok()
+            .map(Arc::new);
+        if let Some(api) = api.clone().filter(|_| CONFIG.prewarm_search) {
+            tokio::task::spawn(prewarm(api));
+        }
         Self {
             text: String::new(),
             selected: 0,
             items: Arc::new(RwLock::new(Vec::new())),
             search_handle: None,
-            api: YTApi::from_header_file(PathBuf::from_str("headers.txt").unwrap().as_path())
-                .await
-                .ok()
-                .map(Arc::new),
+            continuation: Arc::new(RwLock::new(None)),
+            loading_more: Arc::new(AtomicBool::new(false)),
+            found: Arc::new(AtomicUsize::new(0)),
+            error: Arc::new(RwLock::new(None)),
+            api,
+            last_query: None,
+            search_type: CONFIG.search_type,
             action_sender,
         }
     }
+    /**
+     * Shows the downloaded songs matching the text and searches it on YouTube
+     */
+    fn search(&mut self) {
+        if let Some(handle) = self.search_handle.take() {
+            handle.abort();
+        }
+        self.loading_more.store(false, Ordering::SeqCst);
+        *self.continuation.write().unwrap() = None;
+
+        let text = self.text.to_lowercase();
+        let link = links::is_link(&self.text)
+            .then(|| links::video_id(&self.text))
+            .flatten();
+
+        // The downloads are songs, not albums
+        let local = DATABASE
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|_| !self.search_type.is_collection())
+            .filter(|x| match &link {
+                Some(id) => &x.video_id == id,
+                None => {
+                    x.title.to_lowercase().contains(&text)
+                        || x.author.to_lowercase().contains(&text)
+                }
+            })
+            .cloned()
+            .map(search_item)
+            .collect::<Vec<_>>();
+        self.items.write().unwrap().clear();
+        self.items
+            .write()
+            .unwrap()
+            .extend(local.clone().into_iter());
+        cap_results(&mut self.items.write().unwrap(), &self.found);
+
+        if let Some(api) = self.api.clone() {
+            // A link is searched by its id, which finds the song of the link first
+            let text = link.unwrap_or_else(|| self.text.clone());
+            let items = self.items.clone();
+            let continuation = self.continuation.clone();
+            let error = self.error.clone();
+            let found = self.found.clone();
+            let search_type = self.search_type;
+            self.selected = 0;
+            self.search_handle = Some(tokio::task::spawn(async move {
+                let mut item = Vec::new();
+                let result = if search_type.is_collection() {
+                    api::retried(|| api.search_collections(&text, search_type))
+                        .await
+                        .map(|e| (e.into_iter().map(collection_item).collect(), None))
+                } else {
+                    api::retried(|| api.search_page_with_type(&text, search_type))
+                        .await
+                        .map(|(e, next)| (e.into_iter().map(search_item).collect(), next))
+                };
+                // HANDLE ERRORS
+                match result {
+                    Ok((e, next)) => {
+                        item = e;
+                        *continuation.write().unwrap() = next;
+                        *error.write().unwrap() = None;
+                    }
+                    Err(e) => {
+                        api::report_error(&e);
+                        *error.write().unwrap() = Some("Can't reach YouTube".to_owned());
+                    }
+                }
+                items.write().unwrap().clear();
+                items.write().unwrap().extend(local.into_iter());
+                items.write().unwrap().extend(item.into_iter());
+                cap_results(&mut items.write().unwrap(), &found);
+            }));
+        } else {
+            self.set_elements(local);
+        }
+    }
     fn selected(&mut self, selected: isize) {
         let k = self.items.read().unwrap().len();
         if selected < 0 {
@@ -254,8 +468,237 @@ impl Search {
             self.selected = selected as usize;
         }
     }
+    /**
+     * Handles `Enter` when nothing is typed, see `empty_search` in the config
+     */
+    fn on_empty_enter(&mut self) -> EventResponse {
+        match (CONFIG.empty_search, &self.last_query) {
+            (EmptySearchAction::RepeatLast, Some(query)) => {
+                self.text = query.clone();
+                self.search();
+                EventResponse::None
+            }
+            (EmptySearchAction::Recommendations, _) => self.recommendations(),
+            _ => ManagerMessage::Toast("Type something to search".to_owned()).event(),
+        }
+    }
+    /**
+     * Lists the songs related to the last song played
+     */
+    fn recommendations(&mut self) -> EventResponse {
+        match history::read().into_iter().next() {
+            Some(last) => self.related(last.video),
+            None => {
+                ManagerMessage::Toast("Play a song first to get recommendations".to_owned()).event()
+            }
+        }
+    }
+    /**
+     * Lists the songs of the mix generated by YouTube Music from the video
+     */
+    fn related(&mut self, video: Video) -> EventResponse {
+        let api = match self.api.clone() {
+            Some(api) => api,
+            None => return ManagerMessage::Toast("Can't reach YouTube".to_owned()).event(),
+        };
+        if let Some(handle) = self.search_handle.take() {
+            handle.abort();
+        }
+        *self.continuation.write().unwrap() = None;
+        self.set_elements(Vec::new());
+        let items = self.items.clone();
+        let error = self.error.clone();
+        let found = self.found.clone();
+        let mix = format!("RDAMVM{}", video.video_id);
+        let id = video.video_id.clone();
+        self.search_handle = Some(tokio::task::spawn(async move {
+            match api::retried(|| api.browse_playlist(&mix)).await {
+                Ok(videos) => {
+                    let mut items = items.write().unwrap();
+                    items.extend(
+                        videos
+                            .into_iter()
+                            .filter(|x| x.video_id != id)
+                            .map(search_item),
+                    );
+                    cap_results(&mut items, &found);
+                    // Some songs, like the uploads of the users, have no mix
+                    *error.write().unwrap() = items
+                        .is_empty()
+                        .then(|| "YouTube has no related songs for this one".to_owned());
+                }
+                Err(e) => {
+                    api::report_error(&e);
+                    *error.write().unwrap() = Some("Can't reach YouTube".to_owned());
+                }
+            }
+        }));
+        ManagerMessage::Toast(format!("Songs like {}", video.title)).event()
+    }
+    /**
+     * Queues the songs of an album or a playlist, an artist is searched in the songs instead.
+     * The search stays open if `stay` is set.
+     */
+    fn open_collection(&mut self, collection: Video, stay: bool) -> EventResponse {
+        if self.search_type == SearchType::Artists {
+            self.text = collection.title;
+            self.search_type = SearchType::Songs;
+            self.search();
+            return EventResponse::None;
+        }
+        let api = match self.api.clone() {
+            Some(api) => api,
+            None => return ManagerMessage::Toast("Can't reach YouTube".to_owned()).event(),
+        };
+        let sender = self.action_sender.clone();
+        let error = self.error.clone();
+        let title = collection.title.clone();
+        tokio::task::spawn(async move {
+            match api::retried(|| api.browse_playlist(&collection.video_id)).await {
+                Ok(videos) => {
+                    for video in videos {
+                        download::add(video, &sender);
+                    }
+                }
+                Err(e) => {
+                    api::report_error(&e);
+                    *error.write().unwrap() =
+                        Some(format!("Can't get the songs of {}", collection.title));
+                }
+            }
+        });
+        if stay {
+            ManagerMessage::Toast(format!("Added to queue: {}", title)).event()
+        } else {
+            ManagerMessage::ChangeState(Screens::MusicPlayer).event()
+        }
+    }
     fn set_elements(&mut self, element: Vec<(String, Video, Status)>) {
         *self.items.write().unwrap() = element;
+        cap_results(&mut self.items.write().unwrap(), &self.found);
         self.selected = 0;
     }
+    /**
+     * Fetches the next page of results when the last result is selected
+     */
+    fn load_more(&mut self) {
+        let len = self.items.read().unwrap().len();
+        // No more results are shown once the list was cut
+        if self.selected + 1 < len
+            || self.loading_more.load(Ordering::SeqCst)
+            || self.found.load(Ordering::SeqCst) > len
+        {
+            return;
+        }
+        let api = match &self.api {
+            Some(e) => e.clone(),
+            None => return,
+        };
+        let continuation = match self.continuation.write().unwrap().take() {
+            Some(e) => e,
+            None => return,
+        };
+        self.loading_more.store(true, Ordering::SeqCst);
+        let items = self.items.clone();
+        let next = self.continuation.clone();
+        let loading_more = self.loading_more.clone();
+        let found = self.found.clone();
+        // Stored as the search handle so a new search cancels it
+        self.search_handle = Some(tokio::task::spawn(async move {
+            match api::retried(|| api.search_continuation(&continuation)).await {
+                Ok((videos, continuation)) => {
+                    items
+                        .write()
+                        .unwrap()
+                        .extend(videos.into_iter().map(search_item));
+                    cap_results(&mut items.write().unwrap(), &found);
+                    *next.write().unwrap() = continuation;
+                }
+                Err(e) => {
+                    api::report_error(&e);
+                }
+            }
+            loading_more.store(false, Ordering::SeqCst);
+        }));
+    }
+}
+
+/**
+ * Counts the results and keeps only the first `max_search_results` of them
+ */
+fn cap_results(items: &mut Vec<(String, Video, Status)>, found: &AtomicUsize) {
+    found.store(items.len(), Ordering::SeqCst);
+    if CONFIG.max_search_results != 0 {
+        items.truncate(CONFIG.max_search_results);
+    }
+}
+
+/**
+ * Sends a search whose results are dropped, so the connection to YouTube Music is already open
+ * for the first search of the user
+ */
+async fn prewarm(api: Arc<YTApi>) {
+    let start = Instant::now();
+    match api::limited(api.search_page_with_type("music", CONFIG.search_type)).await {
+        Ok(_) => log_(format!(
+            "Warmed up the search in {}ms",
+            start.elapsed().as_millis()
+        )),
+        Err(e) => log_(format!("Can't warm up the search: {:?}", e)),
+    }
+}
+
+/**
+ * Formats a search result on one line: a marker if the song is downloaded, the name and the
+ * duration aligned on the right
+ */
+fn search_row((name, video, status): &(String, Video, Status), width: usize) -> String {
+    let marker = match (status, super::ascii_glyphs()) {
+        (Status::Local, false) => "✓ ",
+        (Status::Local, true) => "* ",
+        (Status::Collection, false) => "▤ ",
+        (Status::Collection, true) => "# ",
+        (Status::Unknown, _) => "  ",
+    };
+    let available = width.saturating_sub(video.duration.chars().count() + 3);
+    let mut name: String = name.chars().take(available).collect();
+    let padding = available.saturating_sub(name.chars().count()) + 1;
+    name.push_str(&" ".repeat(padding));
+    format!("{}{}{}", marker, name, video.duration)
+}
+
+/**
+ * Creates the search list entry of an album, a playlist or an artist
+ */
+fn collection_item(collection: Playlist) -> (String, Video, Status) {
+    let video = Video {
+        title: collection.name,
+        author: collection.subtitle,
+        album: String::new(),
+        video_id: collection.browse_id,
+        duration: String::new(),
+        chapters: Vec::new(),
+    };
+    (
+        format!("{} | {}", video.title, video.author),
+        video,
+        Status::Collection,
+    )
+}
+
+/**
+ * Creates the search list entry of a video
+ */
+fn search_item(video: Video) -> (String, Video, Status) {
+    let status = if DATABASE
+        .read()
+        .unwrap()
+        .iter()
+        .any(|x| x.video_id == video.video_id)
+    {
+        Status::Local
+    } else {
+        Status::Unknown
+    };
+    (format!("{} | {}", video.author, video.title), video, status)
 }
@@ -0,0 +1,46 @@
+use std::{io::Write, sync::Mutex};
+
+use crossterm::{execute, terminal::SetTitle};
+use once_cell::sync::Lazy;
+use ytpapi::Video;
+
+use crate::config::CONFIG;
+
+// The id of the video shown in the terminal title, `None` if the original title wasn't saved yet
+static SHOWN: Lazy<Mutex<Option<Option<String>>>> = Lazy::new(|| Mutex::new(None));
+
+/**
+ * Sets the terminal title to the current track if it changed since the last call
+ */
+pub fn update(current: Option<&Video>) {
+    if !CONFIG.terminal_title {
+        return;
+    }
+    let mut shown = SHOWN.lock().unwrap();
+    let id = current.map(|x| x.video_id.clone());
+    if shown.as_ref() == Some(&id) {
+        return;
+    }
+    let mut stdout = std::io::stdout();
+    if shown.is_none() {
+        // Save the original title on the xterm title stack
+        let _ = write!(stdout, "\x1b[22;0t");
+    }
+    let title = match current {
+        Some(video) => format!("{} - {}", video.author, video.title),
+        None => "YTerMusic".to_owned(),
+    };
+    let _ = execute!(stdout, SetTitle(title));
+    *shown = Some(id);
+}
+
+/**
+ * Restores the title the terminal had before the first update
+ */
+pub fn restore() {
+    if SHOWN.lock().unwrap().take().is_some() {
+        let mut stdout = std::io::stdout();
+        let _ = write!(stdout, "\x1b[23;0t");
+        let _ = stdout.flush();
+    }
+}
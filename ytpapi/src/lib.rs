@@ -11,8 +11,8 @@ use reqwest::{
 
 use string_utils::StringUtils;
 
-use structs::{get_playlist, from_json, get_video};
-pub use structs::{Playlist, Video};
+use structs::{from_json, get_collection, get_continuation, get_playlist, get_video};
+pub use structs::{parse_chapters, Chapter, Playlist, Video};
 
 const YTM_DOMAIN: &str = "https://music.youtube.com";
 
@@ -81,6 +81,8 @@ async fn get_visitor_id(
         .send()
         .await
         .map_err(Error::Reqwest)?
+        .error_for_status()
+        .map_err(Error::Reqwest)?
         .text()
         .await
         .map_err(Error::Reqwest)?;
@@ -102,6 +104,8 @@ async fn get_user_playlists(
         .send()
         .await
         .map_err(Error::Reqwest)?
+        .error_for_status()
+        .map_err(Error::Reqwest)?
         .text()
         .await
         .map_err(Error::Reqwest)?;
@@ -131,6 +135,95 @@ fn extract_json_search(string: &str) -> Result<String, Error> {
     unescape(&json)
 }
 
+/**
+ * The kind of results returned by a search
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchType {
+    // Everything YouTube Music shows, songs and videos mixed
+    #[default]
+    All,
+    // Only the official songs
+    Songs,
+    // Only the videos
+    Videos,
+    // The albums, played as a whole
+    Albums,
+    // The playlists of the users, played as a whole
+    Playlists,
+    // The artists
+    Artists,
+}
+
+impl SearchType {
+    /**
+     * The `params` of the search request that filters the results
+     */
+    fn params(&self) -> Option<&'static str> {
+        match self {
+            Self::All => None,
+            Self::Songs => Some("EgWKAQIIAWoMEA4QChADEAQQCRAF"),
+            Self::Videos => Some("EgWKAQIQAWoMEA4QChADEAQQCRAF"),
+            Self::Albums => Some("EgWKAQIYAWoMEA4QChADEAQQCRAF"),
+            Self::Playlists => Some("EgeKAQQoAEABagwQDhAKEAMQBBAJEAU="),
+            Self::Artists => Some("EgWKAQIgAWoMEA4QChADEAQQCRAF"),
+        }
+    }
+
+    /**
+     * Returns true if the results aren't songs, see `YTApi::search_collections`
+     */
+    pub fn is_collection(&self) -> bool {
+        matches!(self, Self::Albums | Self::Playlists | Self::Artists)
+    }
+
+    /**
+     * The type after this one, all the types are cycled through
+     */
+    pub fn next(&self) -> Self {
+        match self {
+            Self::All => Self::Songs,
+            Self::Songs => Self::Videos,
+            Self::Videos => Self::Albums,
+            Self::Albums => Self::Playlists,
+            Self::Playlists => Self::Artists,
+            Self::Artists => Self::All,
+        }
+    }
+}
+
+/**
+ * What is needed to fetch the next page of a search
+ */
+#[derive(Debug, Clone)]
+pub struct SearchContinuation {
+    token: String,
+    api_key: String,
+    client_version: String,
+}
+
+impl SearchContinuation {
+    fn new(html: &str, json: &str) -> Result<Option<Self>, Error> {
+        let token = match from_json(json, get_continuation)?.into_iter().next() {
+            Some(e) => e,
+            None => return Ok(None),
+        };
+        Ok(html
+            .between("\"INNERTUBE_API_KEY\":\"", "\"")
+            .to_owned_()
+            .zip(
+                html.between("\"INNERTUBE_CLIENT_VERSION\":\"", "\"")
+                    .to_owned_(),
+            )
+            .map(|(api_key, client_version)| Self {
+                token,
+                api_key,
+                client_version,
+            }))
+    }
+}
+
 pub struct YTApi {
     client: Client,
     playlists: Vec<Playlist>,
@@ -149,11 +242,51 @@ pub enum Error {
     InvalidEscapedSequence(String),
 }
 
+impl Error {
+    /**
+     * Returns true if YouTube answered as if the user wasn't logged in, usually because the
+     * cookie expired
+     */
+    pub fn is_auth_error(&self) -> bool {
+        match self {
+            Error::Reqwest(e) => matches!(e.status().map(|x| x.as_u16()), Some(401) | Some(403)),
+            Error::InvalidHTMLFile(_, html) => {
+                html.contains("\"LOGGED_IN\":false") || html.contains("ServiceLogin")
+            }
+            _ => false,
+        }
+    }
+    /**
+     * Returns true if the request may succeed when sent again: the connection failed, timed out,
+     * or YouTube is overloaded
+     */
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Reqwest(e) => {
+                e.is_timeout()
+                    || e.is_connect()
+                    || matches!(
+                        e.status(),
+                        Some(status) if status.is_server_error() || status.as_u16() == 429
+                    )
+            }
+            _ => false,
+        }
+    }
+}
+
 impl YTApi {
     pub async fn from_header_file(filepath: &Path) -> Result<Self, Error> {
-        let mut headers = HashMap::new();
+        let mut headers = Vec::new();
         let k = std::fs::read_to_string(filepath).map_err(Error::Io)?;
-        for line in k.lines() {
+        // The byte order mark, the blank lines and the spaces at the end of the lines, like the
+        // `\r` of Windows, are ignored. So are the HTTP/2 pseudo-headers like `:authority`
+        for line in k
+            .trim_start_matches('\u{feff}')
+            .lines()
+            .map(str::trim)
+            .filter(|x| !x.is_empty() && !x.starts_with(':'))
+        {
             let mut parts = line.splitn(2, ':');
             let key = parts.next().ok_or_else(|| {
                 Error::InvalidHeaderFormat(
@@ -167,26 +300,195 @@ impl YTApi {
                     "HeaderFormat:\nHEADER_NAME: HEADER_VALUE".to_string(),
                 )
             })?;
-            headers.insert(key.to_owned(), value.to_owned());
+            headers.push((key.to_owned(), value.to_owned()));
         }
+        Self::from_header_list(headers).await
+    }
+    /**
+     * Connects with the headers copied from the browser, the ones YouTube Music expects from a
+     * browser are added
+     */
+    pub async fn from_header_list(list: Vec<(String, String)>) -> Result<Self, Error> {
+        let mut headers = list.into_iter().collect::<HashMap<_, _>>();
         headers.insert("User-Agent".to_string(), "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:98.0) Gecko/20100101 Firefox/98.0".to_string());
         headers.insert("Accept".to_string(), "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,*/*;q=0.8".to_string());
         headers.insert("Accept-Language".to_string(), "en-US,en;q=0.5".to_string());
         Self::from_headers(&headers).await
     }
     pub async fn search(&self, search: &str) -> Result<Vec<Video>, Error> {
-        let k = extract_json_search(
-            &self
-                .client
-                .get(&format!("https://music.youtube.com/search?q={}", search))
-                .send()
-                .await
-                .map_err(Error::Reqwest)?
-                .text()
-                .await
-                .map_err(Error::Reqwest)?,
-        )?;
-        from_json(&k, get_video)
+        Ok(self.search_page(search).await?.0)
+    }
+    /**
+     * Searches and returns the first page of results with the continuation to get the next one if there is one.
+     */
+    pub async fn search_page(
+        &self,
+        search: &str,
+    ) -> Result<(Vec<Video>, Option<SearchContinuation>), Error> {
+        let html = self
+            .client
+            .get(&format!("https://music.youtube.com/search?q={}", search))
+            .send()
+            .await
+            .map_err(Error::Reqwest)?
+            .error_for_status()
+            .map_err(Error::Reqwest)?
+            .text()
+            .await
+            .map_err(Error::Reqwest)?;
+        let k = extract_json_search(&html)?;
+        let continuation = SearchContinuation::new(&html, &k)?;
+        Ok((from_json(&k, get_video)?, continuation))
+    }
+    /**
+     * Like `search_page` but only returns the results of the given type.
+     * The search is given as typed, it is url encoded with the query.
+     */
+    pub async fn search_page_with_type(
+        &self,
+        search: &str,
+        search_type: SearchType,
+    ) -> Result<(Vec<Video>, Option<SearchContinuation>), Error> {
+        let html = self
+            .client
+            .get(&format!("{YTM_DOMAIN}/search"))
+            .query(&[("q", search)])
+            .send()
+            .await
+            .map_err(Error::Reqwest)?
+            .error_for_status()
+            .map_err(Error::Reqwest)?
+            .text()
+            .await
+            .map_err(Error::Reqwest)?;
+        let params = match search_type.params() {
+            Some(e) => e,
+            None => {
+                let k = extract_json_search(&html)?;
+                let continuation = SearchContinuation::new(&html, &k)?;
+                return Ok((from_json(&k, get_video)?, continuation));
+            }
+        };
+        let json = self.filtered_search(&html, search, params).await?;
+        let continuation = SearchContinuation::new(&html, &json)?;
+        Ok((from_json(&json, get_video)?, continuation))
+    }
+    /**
+     * Searches the albums, the playlists or the artists, only the first page is returned.
+     * The search is given as typed, it is url encoded with the query.
+     */
+    pub async fn search_collections(
+        &self,
+        search: &str,
+        search_type: SearchType,
+    ) -> Result<Vec<Playlist>, Error> {
+        let params = match search_type.params() {
+            Some(e) if search_type.is_collection() => e,
+            _ => return Ok(Vec::new()),
+        };
+        let html = self
+            .client
+            .get(&format!("{YTM_DOMAIN}/search"))
+            .query(&[("q", search)])
+            .send()
+            .await
+            .map_err(Error::Reqwest)?
+            .error_for_status()
+            .map_err(Error::Reqwest)?
+            .text()
+            .await
+            .map_err(Error::Reqwest)?;
+        let json = self.filtered_search(&html, search, params).await?;
+        from_json(&json, get_collection)
+    }
+    /**
+     * Sends the search with the `params` filtering the results, `html` is the search page
+     */
+    async fn filtered_search(
+        &self,
+        html: &str,
+        search: &str,
+        params: &str,
+    ) -> Result<String, Error> {
+        // The filtered results are only available from the API, which needs the key of the page
+        let (api_key, client_version) = html
+            .between("\"INNERTUBE_API_KEY\":\"", "\"")
+            .to_owned_()
+            .zip(
+                html.between("\"INNERTUBE_CLIENT_VERSION\":\"", "\"")
+                    .to_owned_(),
+            )
+            .ok_or_else(|| Error::InvalidHTMLFile(3, html.to_owned()))?;
+        self.client
+            .post(&format!("{YTM_DOMAIN}/youtubei/v1/search?key={}", api_key))
+            .header("Content-Type", "application/json")
+            .body(
+                serde_json::json!({
+                    "context": {
+                        "client": {
+                            "clientName": "WEB_REMIX",
+                            "clientVersion": client_version,
+                        }
+                    },
+                    "query": search,
+                    "params": params,
+                })
+                .to_string(),
+            )
+            .send()
+            .await
+            .map_err(Error::Reqwest)?
+            .error_for_status()
+            .map_err(Error::Reqwest)?
+            .text()
+            .await
+            .map_err(Error::Reqwest)
+    }
+    /**
+     * Fetches the next page of a search
+     */
+    pub async fn search_continuation(
+        &self,
+        continuation: &SearchContinuation,
+    ) -> Result<(Vec<Video>, Option<SearchContinuation>), Error> {
+        let json = self
+            .client
+            .post(&format!("{YTM_DOMAIN}/youtubei/v1/search"))
+            // The token holds characters such as `%` or `+`, it is url encoded with the query
+            .query(&[
+                ("ctoken", continuation.token.as_str()),
+                ("continuation", continuation.token.as_str()),
+                ("type", "next"),
+                ("key", continuation.api_key.as_str()),
+            ])
+            .header("Content-Type", "application/json")
+            .body(
+                serde_json::json!({
+                    "context": {
+                        "client": {
+                            "clientName": "WEB_REMIX",
+                            "clientVersion": continuation.client_version,
+                        }
+                    }
+                })
+                .to_string(),
+            )
+            .send()
+            .await
+            .map_err(Error::Reqwest)?
+            .error_for_status()
+            .map_err(Error::Reqwest)?
+            .text()
+            .await
+            .map_err(Error::Reqwest)?;
+        let next = from_json(&json, get_continuation)?
+            .into_iter()
+            .next()
+            .map(|token| SearchContinuation {
+                token,
+                ..continuation.clone()
+            });
+        Ok((from_json(&json, get_video)?, next))
     }
     pub fn playlists(&self) -> &Vec<Playlist> {
         &self.playlists
@@ -231,6 +533,8 @@ impl YTApi {
                 .send()
                 .await
                 .map_err(Error::Reqwest)?
+                .error_for_status()
+                .map_err(Error::Reqwest)?
                 .text()
                 .await
                 .map_err(Error::Reqwest)?,
@@ -54,6 +54,60 @@ pub struct Video {
     pub album: String,
     pub video_id: String,
     pub duration: String,
+    #[serde(default)]
+    pub chapters: Vec<Chapter>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct Chapter {
+    // Start of the chapter in seconds
+    pub start: u32,
+    pub title: String,
+}
+
+/**
+ * Extracts the chapters from a video description.
+ * A chapter is a line starting with a timestamp (`1:23` or `1:02:03`) followed by its title.
+ * Like YouTube, the first chapter must start at `0:00` and there must be at least two chapters.
+ */
+pub fn parse_chapters(description: &str) -> Vec<Chapter> {
+    fn parse_timestamp(timestamp: &str) -> Option<u32> {
+        let mut seconds = 0;
+        let mut parts = 0;
+        for part in timestamp.split(':') {
+            if part.is_empty() || part.len() > 2 {
+                return None;
+            }
+            seconds = seconds * 60 + part.parse::<u32>().ok()?;
+            parts += 1;
+        }
+        if (2..=3).contains(&parts) {
+            Some(seconds)
+        } else {
+            None
+        }
+    }
+    let chapters = description
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let (timestamp, title) = line.split_once(char::is_whitespace)?;
+            let start = parse_timestamp(timestamp.trim_matches(|c| c == '(' || c == ')'))?;
+            Some(Chapter {
+                start,
+                title: title
+                    .trim_start_matches(|c: char| c.is_whitespace() || "-–|:".contains(c))
+                    .to_string(),
+            })
+        })
+        .collect::<Vec<_>>();
+    if chapters.len() < 2
+        || chapters[0].start != 0
+        || chapters.windows(2).any(|x| x[0].start >= x[1].start)
+    {
+        return Vec::new();
+    }
+    chapters
 }
 
 impl Display for Video {
@@ -139,6 +193,60 @@ pub fn get_videoid(value: &Value) -> Option<String> {
     }
 }
 
+/**
+ * Tries to find the value of the key in the json
+ */
+fn find_key<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    match value {
+        Value::Array(e) => e.iter().find_map(|x| find_key(x, key)),
+        Value::Object(e) => e
+            .get(key)
+            .or_else(|| e.values().find_map(|x| find_key(x, key))),
+        _ => None,
+    }
+}
+
+/**
+ * Tries to extract an album, a playlist or an artist from a search result.
+ * The `browse_id` is the playlist to play for the albums and the playlists, and the channel of
+ * the artists.
+ */
+pub(crate) fn get_collection(value: &Value) -> Option<Playlist> {
+    let object = value.as_object()?;
+    let mut texts = object.get("flexColumns")?.as_array()?.iter().flat_map(|x| {
+        x.as_object()
+            .and_then(|x| x.values().next())
+            .and_then(|x| get_text(x, true))
+    });
+    // The artists have no play button, only a link to their page
+    let browse_id = find_key(value, "watchPlaylistEndpoint")
+        .and_then(|x| x.get("playlistId"))
+        .or_else(|| {
+            object
+                .get("navigationEndpoint")?
+                .get("browseEndpoint")?
+                .get("browseId")
+        })
+        .and_then(Value::as_str)?;
+    Some(Playlist {
+        name: texts.next()?,
+        subtitle: texts.next().unwrap_or_default(),
+        browse_id: browse_id.to_string(),
+    })
+}
+
+/**
+ * Tries to extract the token to fetch the next page of results
+ */
+pub(crate) fn get_continuation(value: &Value) -> Option<String> {
+    value
+        .as_object()?
+        .get("nextContinuationData")?
+        .get("continuation")?
+        .as_str()
+        .map(|x| x.to_string())
+}
+
 /**
  * Tries to extract a video from a json value.
  * Quite flexible to reduce odds of API change breaking this.
@@ -162,5 +270,69 @@ pub(crate) fn get_video(value: &Value) -> Option<Video> {
         author: texts.next()?,
         album: texts.next().unwrap_or_default(),
         duration: String::new(),
+        chapters: Vec::new(),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_chapters, Chapter};
+
+    fn chapters(list: &[(u32, &str)]) -> Vec<Chapter> {
+        list.iter()
+            .map(|(start, title)| Chapter {
+                start: *start,
+                title: (*title).to_owned(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn chapters_are_read_from_the_timestamps() {
+        let description = "Tracklist:\n0:00 Intro\n1:23 - Verse\n(3:05) Chorus\n10:00 | Outro";
+        assert_eq!(
+            parse_chapters(description),
+            chapters(&[(0, "Intro"), (83, "Verse"), (185, "Chorus"), (600, "Outro")])
+        );
+    }
+
+    #[test]
+    fn hour_long_timestamps_are_read() {
+        let description = "00:00 First\n59:59 Second\n1:00:00 Third\n1:02:03 Fourth";
+        assert_eq!(
+            parse_chapters(description),
+            chapters(&[
+                (0, "First"),
+                (3599, "Second"),
+                (3600, "Third"),
+                (3723, "Fourth")
+            ])
+        );
+    }
+
+    #[test]
+    fn chapters_must_start_at_zero() {
+        assert!(parse_chapters("0:10 Intro\n1:00 Verse").is_empty());
+    }
+
+    #[test]
+    fn a_single_timestamp_isnt_a_chapter_list() {
+        assert!(parse_chapters("Listen from 0:00\n0:00 Intro\nThanks for listening").is_empty());
+    }
+
+    #[test]
+    fn chapters_must_be_in_order() {
+        assert!(parse_chapters("0:00 Intro\n2:00 Chorus\n1:00 Verse").is_empty());
+        assert!(parse_chapters("0:00 Intro\n1:00 Verse\n1:00 Chorus").is_empty());
+    }
+
+    #[test]
+    fn malformed_timestamps_are_ignored() {
+        // Neither a single number, a part of 3 digits nor 4 parts is a timestamp
+        let description = "0:00 Intro\n5 songs\n1:234 Verse\n1:02:03:04 Bridge\n2:00 Outro";
+        assert_eq!(
+            parse_chapters(description),
+            chapters(&[(0, "Intro"), (120, "Outro")])
+        );
+    }
+}